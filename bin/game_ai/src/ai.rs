@@ -2,9 +2,16 @@
 //! 
 //! The ai module should contain all the functions and structures related to the ai engine.
 //! Exposes functions to provide an input grid state and return an optimal estimated move.
-//! Divided in submodules `core` and `engine`.
+//! Divided in submodules `core`, `config`, `evaluation`, `expectimax`, `mcts`, `transposition` and `engine`.
 
 #![allow(dead_code)]
 
 mod core;
-pub mod engine;
\ No newline at end of file
+pub mod config;
+pub mod evaluation;
+pub mod expectimax;
+pub mod mcts;
+pub mod transposition;
+pub mod engine;
+
+pub use self::core::utility;
\ No newline at end of file
@@ -0,0 +1,183 @@
+//! # `config` module
+//!
+//! This module defines the parameters of an `AIEngine`, set per engine through an `AIConfig`: the search run, its transposition table,
+//! the time budget of each move and the evaluator of the grids. The parameters of the MCTS agent are part of its `SearchAlgorithm`.
+
+use std::{error, fmt, fmt::Display};
+use std::time::Duration;
+
+use crate::ai::engine::SearchAlgorithm;
+use crate::ai::evaluation::Evaluator;
+use crate::ai::mcts::MctsConfigError;
+use crate::ai::transposition::TableConfig;
+
+
+//------------------------------------------------
+// Types and Definitions
+//------------------------------------------------
+
+/// The parameters of the AI: search, transposition table, time budget per move and evaluator.
+/// With a time budget the search deepens iteratively until the budget expires, otherwise it runs to its full depth.
+/// Built with `AIConfig::builder()`, any parameter not set keeps its default.
+#[derive(Clone, Debug, Default)]
+pub struct AIConfig {
+  search: SearchAlgorithm,
+  table: TableConfig,
+  time_budget: Option<Duration>,
+  evaluator: Evaluator,
+}
+
+/// Builder of an `AIConfig`, validated by `build()`.
+#[derive(Clone, Debug)]
+pub struct AIConfigBuilder {
+  config: AIConfig,
+}
+
+/// Errors raised when building an invalid `AIConfig`.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum AIConfigError {
+  InvalidDepth(usize),
+  InvalidTimeBudget(Duration),
+  InvalidMcts(MctsConfigError),
+}
+
+
+//------------------------------------------------
+// Implementations
+//------------------------------------------------
+
+// Inherent
+
+impl AIConfig {
+
+  /// Returns a builder starting from the default parameters.
+  pub fn builder() -> AIConfigBuilder {
+    AIConfigBuilder {
+      config: AIConfig::default(),
+    }
+  }
+
+  // Getters
+  pub fn get_search(&self) -> SearchAlgorithm { self.search }
+  pub fn get_table(&self) -> TableConfig { self.table }
+  pub fn get_time_budget(&self) -> Option<Duration> { self.time_budget }
+  pub fn get_evaluator(&self) -> &Evaluator { &self.evaluator }
+
+  /// Checks that the parameters describe a search that can pick a move.
+  pub fn validate(&self) -> Result<(), AIConfigError> {
+    match self.search {
+      SearchAlgorithm::Expectimax { depth: 0 } => return Err(AIConfigError::InvalidDepth(0)),
+      SearchAlgorithm::MonteCarlo(mcts_config) => mcts_config.validate().map_err(AIConfigError::InvalidMcts)?,
+      _ => {},
+    }
+
+    if let Some(time_budget) = self.time_budget.filter(|budget| budget.is_zero()) {
+      return Err(AIConfigError::InvalidTimeBudget(time_budget));
+    }
+
+    Ok(())
+  }
+
+}
+
+impl AIConfigBuilder {
+
+  /// Sets the search run to pick the moves.
+  pub fn search(mut self, search: SearchAlgorithm) -> Self {
+    self.config.search = search;
+    self
+  }
+
+  /// Sets the size and replacement policy of the transposition table kept by the worker.
+  pub fn table(mut self, table: TableConfig) -> Self {
+    self.config.table = table;
+    self
+  }
+
+  /// Sets the time the search of each move may take, searching iteratively deeper until it expires.
+  pub fn time_budget(mut self, time_budget: Duration) -> Self {
+    self.config.time_budget = Some(time_budget);
+    self
+  }
+
+  /// Sets the evaluator valuing the grids reached by the searches, the same as `utility()` by default.
+  /// The random rollouts of the MCTS agent use no evaluator.
+  pub fn evaluator(mut self, evaluator: Evaluator) -> Self {
+    self.config.evaluator = evaluator;
+    self
+  }
+
+  /// Builds the config, checking its parameters.
+  pub fn build(self) -> Result<AIConfig, AIConfigError> {
+    self.config.validate()?;
+
+    Ok(self.config)
+  }
+
+}
+
+
+// Display
+
+impl Display for AIConfigError {
+
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      AIConfigError::InvalidDepth(depth) => write!(f, "search depth {} doesn't look any move ahead", depth),
+      AIConfigError::InvalidTimeBudget(budget) => write!(f, "time budget of {:?} leaves no time to search", budget),
+      AIConfigError::InvalidMcts(error) => write!(f, "invalid MCTS parameters: {}", error),
+    }
+  }
+
+}
+
+
+// Error
+
+impl error::Error for AIConfigError {}
+
+
+//------------------------------------------------
+// Unit tests
+//------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+
+  use super::*;
+  use crate::ai::evaluation::{Aggregation, Emptiness};
+  use crate::ai::mcts::MctsConfig;
+  use crate::ai::transposition::ReplacementPolicy;
+
+  // Test AIConfigBuilder
+
+  #[test]
+  pub fn test_ai_config_builder() {
+    let config = AIConfig::builder()
+      .search(SearchAlgorithm::Expectimax { depth: 4 })
+      .table(TableConfig::new(1024, ReplacementPolicy::Always))
+      .time_budget(Duration::from_millis(20))
+      .evaluator(Evaluator::builder().heuristic(Emptiness, 1.).aggregation(Aggregation::Linear).build().unwrap())
+      .build()
+      .unwrap();
+
+    assert_eq!(config.get_search(), SearchAlgorithm::Expectimax { depth: 4 });
+    assert_eq!(config.get_table(), TableConfig::new(1024, ReplacementPolicy::Always));
+    assert_eq!(config.get_time_budget(), Some(Duration::from_millis(20)));
+    assert_eq!(config.get_evaluator().get_weights(), vec![("emptiness", 1.)]);
+
+    let default = AIConfig::builder().build().unwrap();
+    assert_eq!(default.get_search(), SearchAlgorithm::LeafAverage);
+    assert_eq!(default.get_table(), TableConfig::default());
+    assert_eq!(default.get_time_budget(), None);
+    assert_eq!(default.get_evaluator().get_weights(), Evaluator::default().get_weights());
+  }
+
+  #[test]
+  pub fn test_ai_config_builder_errors() {
+    assert_eq!(AIConfig::builder().search(SearchAlgorithm::Expectimax { depth: 0 }).build().unwrap_err(), AIConfigError::InvalidDepth(0));
+    assert_eq!(AIConfig::builder().time_budget(Duration::from_millis(0)).build().unwrap_err(), AIConfigError::InvalidTimeBudget(Duration::from_millis(0)));
+    assert!(AIConfig::builder().search(SearchAlgorithm::MonteCarlo(MctsConfig::default())).build().is_ok());
+  }
+
+}
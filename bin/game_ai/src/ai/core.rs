@@ -4,8 +4,10 @@
 
 use std::cmp;
 
-use crate::encoding;
+use crate::ai::evaluation::Evaluator;
 use crate::game::core::*;
+use crate::game::board::BoardView;
+use crate::game::config::GameConfig;
 use crate::game::moves::PlayerMove;
 
 
@@ -23,17 +25,11 @@ pub const MOVES_QUEUE_CAPACITY: usize = 20;
 pub const PATH_PROBABILITY_THRESHOLD: f64 = 0.25;
 
 // Heuristics and utility parameters
-const LOG2_VICTORY_THRESHOLD: usize = 11; // need macro to make it log of VICTORY_THRESHOLD in game core.
-const TOT_MONOTONICITY_DIVISOR: usize = GRID_SIDE * (GRID_SIDE - 1) * 2;
+const LOG2_VICTORY_THRESHOLD: usize = VICTORY_THRESHOLD.trailing_zeros() as usize; // default, games set their own through GameConfig
 const GRID_NUM_GAP_SENSITIVITY: f64 = 0.8;
-const MONOTONICITY_WEIGHT: f64 = 0.4; // Monotonicity weight
-const EMPTINESS_WEIGHT: f64 = 0.2; // Emptiness weight
-const MERGEABILITY_WEIGHT: f64 = 0.15; // Mergeability weight
-const HOMOGENEITY_DEGREE: f64 = 8.; // Regulates the growth and concavity/convexity of the utility function
 
-// Bayesian inference parameters
-const ALPHA: f64 = 9.;
-const BETA: f64 = 1.;
+// Bayesian inference parameters, the Beta prior weighs as many spawns and is centered on the spawn probability of the rules
+const PRIOR_SPAWNS: f64 = 10.;
 
 
 // DATA STRUCTURES
@@ -43,7 +39,7 @@ const BETA: f64 = 1.;
 pub struct AINode {
   grid: Grid<EncodedGrid>,
   originating_move: Option<PlayerMove>,
-  delta_score: u32,
+  delta_score: u64,
   path_probability: f64,
   depth: usize,
 }
@@ -67,7 +63,7 @@ impl AINode {
   pub fn new(
     grid: &Grid<EncodedGrid>, 
     originating_move: Option<PlayerMove>,
-    delta_score: u32,
+    delta_score: u64,
     path_probability: f64,
     depth: usize
   ) -> Self {
@@ -83,7 +79,7 @@ impl AINode {
   // Getters
   pub fn get_grid(&self) -> &Grid<EncodedGrid> { &self.grid }
   pub fn get_originating_move(&self) -> Option<PlayerMove> { self.originating_move }
-  pub fn get_delta_score(&self) -> u32 { self.delta_score }
+  pub fn get_delta_score(&self) -> u64 { self.delta_score }
   pub fn get_path_probability(&self) -> f64 { self.path_probability }
   pub fn get_depth(&self) -> usize { self.depth }
 
@@ -152,86 +148,99 @@ fn decode_key(key: usize) -> (PlayerMove, EntryType, usize, usize) {
 
 }
 
-/// Computes the scores for each heurisitc used to evaluate the utility function.
-/// Returns: (monotonicity, emptiness, mergeability, maximum_tile).
-fn heuristics_scores(grid: &Grid<EncodedGrid>) -> (f64, f64, f64, f64) {
+/// Computes the heuristics scores of a `rows` x `cols` grid whose decoded tiles are given by `tile(row, col)`.
+/// Returns: (monotonicity, emptiness, mergeability, maximum_tile), the maximum tile score reaching 1 at the victory tile.
+fn sized_heuristics_scores<F: Fn(usize, usize) -> EntryType>(rows: usize, cols: usize, log2_victory_threshold: usize, tile: F) -> (f64, f64, f64, f64) {
+  (
+    monotonicity_score(rows, cols, &tile),
+    emptiness_score(rows, cols, &tile),
+    mergeability_score(rows, cols, &tile),
+    max_tile_score(rows, cols, log2_victory_threshold, &tile),
+  )
+}
+
+/// Scores how monotonic the rows and the columns are, from 0 for alternating tiles to 1 for sorted ones.
+pub(crate) fn monotonicity_score<F: Fn(usize, usize) -> EntryType>(rows: usize, cols: usize, tile: F) -> f64 {
   let (mut inc_h, mut inc_v, mut dec_h, mut dec_v) = (0, 0, 0, 0);
-  let mut sequence_completeness = [0; LOG2_VICTORY_THRESHOLD];
-  let mut log_entry;
-  let mut empty_tiles = 0;
-  let mut max_tile = 0;
+  let mut entry;
 
-  let dec_grid = encoding::decode_grid(grid.get_state());
+  // number of adjacent pairs of tiles, horizontally and vertically
+  let tot_monotonicity_divisor = rows * (cols - 1) + (rows - 1) * cols;
 
-  for j in 0..GRID_SIDE {
-    for i in 0..GRID_SIDE {
+  for i in 0..rows {
+    for j in 0..cols {
+      entry = tile(i, j);
 
-      // monotonicity
       if j > 0 {
-        if dec_grid[i][j] >= dec_grid[i][j - 1] { inc_h += 1; }
-        if dec_grid[i][j] <= dec_grid[i][j - 1] { dec_h += 1; }
-        if dec_grid[j][i] >= dec_grid[j - 1][i] { inc_v += 1; }
-        if dec_grid[j][i] <= dec_grid[j - 1][i] { dec_v += 1; }
+        if entry >= tile(i, j - 1) { inc_h += 1; }
+        if entry <= tile(i, j - 1) { dec_h += 1; }
       }
-
-      // mergeability
-      if dec_grid[i][j] > 0 {
-        log_entry = (dec_grid[i][j] as f64).log2() as usize;
-        sequence_completeness[log_entry - 1] = log_entry;
+      if i > 0 {
+        if entry >= tile(i - 1, j) { inc_v += 1; }
+        if entry <= tile(i - 1, j) { dec_v += 1; }
       }
+    }
+  }
+
+  ((cmp::max(inc_h, dec_h) + cmp::max(inc_v, dec_v)) as f64 - tot_monotonicity_divisor as f64 / 2.) / tot_monotonicity_divisor as f64 * 2.
+}
 
-      // emptiness
-      if dec_grid[i][j] == 0 { empty_tiles += 1; }
+/// Scores the fraction of empty cells.
+pub(crate) fn emptiness_score<F: Fn(usize, usize) -> EntryType>(rows: usize, cols: usize, tile: F) -> f64 {
+  let empty_tiles = (0..rows).flat_map(|i| (0..cols).map(move |j| (i, j))).filter(|&(i, j)| tile(i, j) == 0).count();
 
-      // max tile
-      if dec_grid[i][j] > max_tile { max_tile = dec_grid[i][j]; }
+  empty_tiles as f64 / (rows * cols) as f64
+}
+
+/// Scores how few distinct tiles clutter the grid below the maximum tile, 1 when at most the 2 tile is on it.
+pub(crate) fn mergeability_score<F: Fn(usize, usize) -> EntryType>(rows: usize, cols: usize, tile: F) -> f64 {
+  let mut sequence_completeness = [0; TILE_EXPONENTS];
+  let mut log_entry;
+  let mut entry;
 
+  for i in 0..rows {
+    for j in 0..cols {
+      entry = tile(i, j);
+
+      if entry > 0 {
+        log_entry = entry.trailing_zeros() as usize;
+        sequence_completeness[log_entry - 1] = log_entry;
+      }
     }
   }
 
-  let log_max = (max_tile as f64).log2() as usize;
+  let log_max = sequence_completeness.iter().copied().max().unwrap_or(0);
   let clutter_penalty: f64 = if log_max > 1 {
     sequence_completeness.iter().fold(0., |acc, value| acc + *value as f64) / (log_max * (log_max + 1) / 2) as f64
   } else {
     0.
   };
 
-  (
-    (cmp::max(inc_h, dec_h) + cmp::max(inc_v, dec_v) - TOT_MONOTONICITY_DIVISOR / 2) as f64 / TOT_MONOTONICITY_DIVISOR as f64 * 2., 
-    empty_tiles as f64 / (GRID_SIDE * GRID_SIDE) as f64, 
-    1. - clutter_penalty * GRID_NUM_GAP_SENSITIVITY, 
-    log_max as f64 / LOG2_VICTORY_THRESHOLD as f64,
-  )
+  1. - clutter_penalty * GRID_NUM_GAP_SENSITIVITY
 }
 
-/// Computes the utility of a grid from the set of heuristics scores.
-pub fn utility(grid: &Grid<EncodedGrid>) -> f64 {
-  let scores = heuristics_scores(grid);
-
-  // If we are not in a winning state
-  if scores.3 < 1. {
-
-    // If it's game over value as -Inf
-    // if scores.1 == 0. && engine::is_game_over(grid, moves_table) {
-    //   return -f64::INFINITY;
-    // }
-
-    // Otherwise compute Cobb-Douglas utility
-    scores.0.powf(HOMOGENEITY_DEGREE * MONOTONICITY_WEIGHT)
-    * scores.1.powf(HOMOGENEITY_DEGREE * EMPTINESS_WEIGHT)
-    * scores.2.powf(HOMOGENEITY_DEGREE * MERGEABILITY_WEIGHT)
-    * scores.3.powf(HOMOGENEITY_DEGREE * (1. - MONOTONICITY_WEIGHT - EMPTINESS_WEIGHT - MERGEABILITY_WEIGHT))
+/// Scores the exponent of the maximum tile relative to the victory tile's, reaching 1 at the victory tile.
+pub(crate) fn max_tile_score<F: Fn(usize, usize) -> EntryType>(rows: usize, cols: usize, log2_victory_threshold: usize, tile: F) -> f64 {
+  let max_tile = (0..rows).flat_map(|i| (0..cols).map(move |j| (i, j))).map(|(i, j)| tile(i, j)).max().unwrap_or(0);
+  let log_max = if max_tile > 0 { max_tile.trailing_zeros() as usize } else { 0 };
 
-  // Otherwise we won value Inf
-  } else {
-    f64::INFINITY
-  }
+  log_max as f64 / log2_victory_threshold as f64
+}
 
+/// Computes the utility of a grid, or of a board of any size, with the default `Evaluator`, for a game played with the rules in `config`.
+pub fn utility<B: BoardView>(board: &B, config: &GameConfig) -> f64 {
+  Evaluator::default().evaluate(board, config)
 }
 
-/// This function calculates the posterior probability of a 2-tile assuming a Beta likelihood.
-pub fn bayes_beta_update(grid: &Grid<EncodedGrid>, moves_count: usize) -> f64 {
-  (ALPHA + (2 * (moves_count + 1)) as f64 - 0.5 * grid.get_sum() as f64) / (ALPHA + BETA + moves_count as f64 + 1.)
+/// This function calculates the posterior probability of a 2-tile assuming a Beta likelihood,
+/// with a prior centered on the spawn probability of the rules in `config`.
+pub fn bayes_beta_update(grid: &Grid<EncodedGrid>, moves_count: usize, config: &GameConfig) -> f64 {
+  let alpha = PRIOR_SPAWNS * config.get_prob_tile2();
+  let beta = PRIOR_SPAWNS - alpha;
+  let spawns = moves_count + config.get_starting_tiles();
+
+  // every spawn adds 2 or 4 to the sum of the tiles, so the number of 2s is twice the spawns less half the sum
+  (alpha + (2 * spawns) as f64 - 0.5 * grid.get_sum() as f64) / (alpha + beta + spawns as f64)
 }
 
 
@@ -243,6 +252,8 @@ pub fn bayes_beta_update(grid: &Grid<EncodedGrid>, moves_count: usize) -> f64 {
 mod tests {
 
   use super::*;
+  use crate::encoding;
+  use crate::game::board::Board;
 
 
   // Testing encode_key()
@@ -285,7 +296,14 @@ mod tests {
   }
 
 
-  // Testing heuristics_scores()
+  fn heuristics_scores(grid: &Grid<EncodedGrid>, log2_victory_threshold: usize) -> (f64, f64, f64, f64) {
+    let dec_grid = encoding::decode_grid(grid.get_state());
+
+    sized_heuristics_scores(GRID_SIDE, GRID_SIDE, log2_victory_threshold, |i, j| dec_grid[i][j])
+  }
+
+
+  // Testing heuristics scores
 
   #[test]
   pub fn test_heuristics_scores_low() {
@@ -296,7 +314,7 @@ mod tests {
       [4, 2, 4, 2],
     ]);
 
-    let result = heuristics_scores(&grid);
+    let result = heuristics_scores(&grid, LOG2_VICTORY_THRESHOLD);
 
     assert_eq!(result.0, 0., "Monotonicity");
     assert_eq!(result.1, 0., "Emptiness");
@@ -313,7 +331,7 @@ mod tests {
       [0, 0, 0, 0],
     ]);
 
-    let result = heuristics_scores(&grid);
+    let result = heuristics_scores(&grid, LOG2_VICTORY_THRESHOLD);
 
     assert_eq!(result.0, 1., "Monotonicity");
     assert_eq!(result.1, 1., "Emptiness");
@@ -330,7 +348,7 @@ mod tests {
       [0, 0, 0, 0],
     ]);
 
-    let result = heuristics_scores(&grid);
+    let result = heuristics_scores(&grid, LOG2_VICTORY_THRESHOLD);
 
     assert_eq!(result.0, 1., "Monotonicity");
     assert_eq!(result.1, 10. / (GRID_SIDE * GRID_SIDE) as f64, "Emptiness");
@@ -339,6 +357,87 @@ mod tests {
   }
 
 
+  #[test]
+  pub fn test_heuristics_scores_beyond_victory() {
+    let grid = Grid::from_decoded(&[
+      [4096, 2048, 2, 0],
+      [0, 0, 0, 0],
+      [0, 0, 0, 0],
+      [0, 0, 0, 0],
+    ]);
+
+    let result = heuristics_scores(&grid, LOG2_VICTORY_THRESHOLD);
+
+    assert_eq!(result.3, 12. / LOG2_VICTORY_THRESHOLD as f64, "Maximum tile");
+    assert_eq!(utility(&grid, &GameConfig::default()), f64::INFINITY);
+  }
+
+
+  #[test]
+  pub fn test_heuristics_scores_largest_tiles() {
+    let grid = Grid::from_decoded(&[
+      [LARGEST_TILE, 1 << 30, 1 << 20, 131072],
+      [0, 0, 0, 65536],
+      [0, 0, 0, 0],
+      [0, 0, 0, 2],
+    ]);
+
+    let result = heuristics_scores(&grid, LOG2_VICTORY_THRESHOLD);
+
+    assert_eq!(result.3, 31. / LOG2_VICTORY_THRESHOLD as f64, "Maximum tile");
+    assert!(result.2 > 0.);
+  }
+
+  #[test]
+  pub fn test_utility_victory_threshold() {
+    let grid = Grid::from_decoded(&[
+      [2, 4, 8, 16],
+      [0, 0, 0, 32],
+      [0, 0, 0, 64],
+      [0, 0, 0, 128],
+    ]);
+    let config = GameConfig::builder().victory_threshold(128).build().unwrap();
+
+    assert_eq!(heuristics_scores(&grid, config.get_log2_victory_threshold()).3, 1.);
+    assert_eq!(utility(&grid, &config), f64::INFINITY);
+    assert!(utility(&grid, &GameConfig::default()) < f64::INFINITY);
+  }
+
+
+  // Testing sized_heuristics_scores()
+
+  #[test]
+  pub fn test_sized_heuristics_scores_matches_default_size() {
+    let grid = Grid::from_decoded(&[
+      [0, 8, 4, 2],
+      [0, 2, 64, 128],
+      [8, 64, 4, 2],
+      [4, 2, 16, 8],
+    ]);
+    let dec_grid = encoding::decode_grid(grid.get_state());
+
+    let board = Board::from_decoded(&dec_grid.iter().map(|row| row.to_vec()).collect::<Vec<_>>());
+
+    assert_eq!(sized_heuristics_scores(GRID_SIDE, GRID_SIDE, LOG2_VICTORY_THRESHOLD, |i, j| dec_grid[i][j]), heuristics_scores(&grid, LOG2_VICTORY_THRESHOLD));
+    assert_eq!(utility(&board, &GameConfig::default()), utility(&grid, &GameConfig::default()));
+  }
+
+  #[test]
+  pub fn test_sized_heuristics_scores_rectangular() {
+    let board = Board::from_decoded(&[
+      vec![8, 4, 2],
+      vec![4, 2, 0],
+    ]);
+    let dec_board = board.get_decoded();
+
+    let result = sized_heuristics_scores(2, 3, LOG2_VICTORY_THRESHOLD, |i, j| dec_board[i][j]);
+
+    assert_eq!(result.0, 1., "Monotonicity");
+    assert_eq!(result.1, 1. / 6., "Emptiness");
+    assert_eq!(result.3, 3. / LOG2_VICTORY_THRESHOLD as f64, "Maximum tile");
+  }
+
+
   // Testing bayes_beta_update()
 
   #[test]
@@ -350,7 +449,7 @@ mod tests {
       [0, 0, 0, 2],
     ]);
 
-    assert_eq!(bayes_beta_update(&grid, 0), (ALPHA + 1.) / (ALPHA + BETA + 1.));
+    assert_eq!(bayes_beta_update(&grid, 0, &GameConfig::default()), (9. + 1.) / (9. + 1. + 1.));
   }
 
   #[test]
@@ -363,9 +462,25 @@ mod tests {
     ]);
 
     let move_count = 220;
-    let result = (ALPHA + 2. * (move_count + 1) as f64 - 0.5 * (46. + 64. + 256. + 128.)) / (ALPHA + BETA + move_count as f64 + 1.);
+    let result = (9. + 2. * (move_count + 1) as f64 - 0.5 * (46. + 64. + 256. + 128.)) / (9. + 1. + move_count as f64 + 1.);
+
+    assert_eq!(bayes_beta_update(&grid, move_count, &GameConfig::default()), result);
+  }
+
+  #[test]
+  pub fn test_bayes_beta_update_config() {
+    let grid = Grid::from_decoded(&[
+      [0, 0, 0, 0],
+      [0, 0, 0, 0],
+      [0, 0, 0, 2],
+      [0, 0, 0, 2],
+    ]);
+
+    // the prior follows the spawn probability and the starting tiles of the rules
+    let config = GameConfig::builder().prob_tile2(0.5).starting_tiles(2).build().unwrap();
 
-    assert_eq!(bayes_beta_update(&grid, move_count), result);
+    assert_eq!(bayes_beta_update(&grid, 0, &config), (5. + 2.) / (5. + 5. + 2.));
+    assert!(bayes_beta_update(&grid, 0, &config) < bayes_beta_update(&grid, 0, &GameConfig::default()));
   }
 
 }
\ No newline at end of file
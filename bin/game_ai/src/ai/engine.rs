@@ -1,19 +1,33 @@
 //! # `engine` module
 //! 
 //! Contains the AI engine that exposes the API to the user.
+//! The engine plays any board implementing `SearchBoard`: the default grid with every search algorithm,
+//! and runtime-sized boards with expectimax, the other searches being optimized for the grid.
 
 use std::collections::{VecDeque, HashMap};
 use std::thread;
 use std::thread::JoinHandle;
+use std::sync::{Arc, Mutex};
 use std::sync::mpsc;
+use std::time::Instant;
+
+use rand::SeedableRng;
 use std::sync::mpsc::{Sender, Receiver};
 
 use crate::ai::core::*;
+use crate::ai::config::AIConfig;
+use crate::ai::evaluation::Evaluator;
+use crate::ai::expectimax;
+use crate::ai::mcts;
+use crate::ai::mcts::MctsConfig;
+use crate::ai::transposition::{TranspositionTable, TableConfig, TableStats};
 use crate::game::core::*;
+use crate::game::board::{Board, GameBoard};
+use crate::game::config::GameConfig;
 use crate::game::moves;
-use crate::game::moves::{PlayerMove, LineStackingResult, MoveStackingResult};
-use crate::game::engine;
-use crate::game::engine::{Game, GameStatus, GameState, AnimationData, GameAPI};
+use crate::game::moves::{PlayerMove, MovesTable, MoveStackingResult};
+use crate::game::engine::{BoardGame, GameStatus, GameState, AnimationData, GameAPI, GameRng};
+use crate::game::observer::GameObserver;
 
 
 //------------------------------------------------
@@ -27,6 +41,18 @@ pub enum AIState {
   Inactive,
 }
 
+/// Describes the search the AI runs to choose its moves, selected through the `AIConfig` of the `AIEngine`.
+#[derive(Copy, Clone, PartialEq, Debug, Default)]
+pub enum SearchAlgorithm {
+  /// Averages the utilities of the leaves of a breadth-first forecast tree for each first move.
+  #[default]
+  LeafAverage,
+  /// Depth-limited expectimax looking `depth` player moves ahead, at most with a time budget, see the `expectimax` module.
+  Expectimax { depth: usize },
+  /// Monte Carlo Tree Search with the given parameters, stopping early when the time budget expires, see the `mcts` module.
+  MonteCarlo(MctsConfig),
+}
+
 /// Describes the possible states the worker thread can be in.
 enum WorkerState {
   Working,
@@ -37,9 +63,9 @@ enum WorkerState {
 
 /// Specifies the messages that can be sent to the worker thread.
 /// The move message includes the grid to work on and the moves count for that particular grid.
-#[derive(Copy, Clone)]
-enum WorkerMessage {
-  Work(Grid<EncodedGrid>, usize),
+#[derive(Clone)]
+enum WorkerMessage<B> {
+  Work(B, usize),
   Pause,
   Shutdown,
   MoveReceived,
@@ -53,39 +79,94 @@ enum WorkerResponse {
   BufferFull,
 }
 
-/// The basic structure of the AI.
-/// The AI owns the game and exposes its public API to the user, so only an instance of `AIEngine` is needed to run the full application.
-pub struct AIEngine {
-  game: Game,
+/// The AI playing the default `GRID_SIDE` x `GRID_SIDE` grid.
+pub type AIEngine = BoardAIEngine<Grid<EncodedGrid>>;
+
+/// The basic structure of the AI, playing a game on a board of type `B`.
+/// The AI owns the game and exposes its public API to the user, so only an instance of `BoardAIEngine` is needed to run the full application.
+/// The worker keeps a transposition table of the grids evaluated across the searches, whose statistics are shared after each search.
+pub struct BoardAIEngine<B: SearchBoard> {
+  game: BoardGame<B>,
   state: AIState,
+  ai_config: AIConfig,
+  table_stats: Arc<Mutex<TableStats>>,
   moves_worker: Option<JoinHandle<()>>,
-  worker_task_sender: Sender<WorkerMessage>,
+  worker_task_sender: Sender<WorkerMessage<B>>,
   worker_response_receiver: Receiver<WorkerResponse>,
 }
 
 
+//------------------------------------------------
+// Traits
+//------------------------------------------------
+
+/// Trait for the boards a `BoardAIEngine` plays, each searched its own way.
+pub trait SearchBoard: GameBoard {
+
+  /// Calculates the optimal move with the search of `ai_config`, within its time budget if any, `None` if no move changes the board.
+  fn search_optimal_move(
+    &self,
+    move_count: usize,
+    ai_config: &AIConfig,
+    config: &GameConfig,
+    table: &mut TranspositionTable,
+    rng: &mut GameRng,
+  ) -> Option<PlayerMove>;
+
+}
+
+
 //------------------------------------------------
 // Implementations
 //------------------------------------------------
 
 // Inherent
 
-impl AIEngine {
+impl<B: SearchBoard> BoardAIEngine<B> {
 
   /// Constructor.
-  /// Sets up the initial shared state and communication channels between main thread and moves worker thread.
   pub fn new() -> Self {
+    BoardAIEngine::with_game(BoardGame::new())
+  }
+
+  /// Constructor with a given seed for the random tiles generator of the game, see `BoardGame::with_seed()`.
+  pub fn with_seed(seed: u64) -> Self {
+    BoardAIEngine::with_game(BoardGame::with_seed(seed))
+  }
+
+  /// Constructor playing an existing game, e.g. one restored from a save.
+  pub fn with_game(game: BoardGame<B>) -> Self {
+    BoardAIEngine::with_game_and_config(game, AIConfig::default())
+  }
+
+  /// Constructor playing an existing game with the given search and transposition table, and no time budget.
+  /// Panics if the search parameters are invalid, see `AIConfig::validate()`.
+  pub fn with_game_and_search(game: BoardGame<B>, search: SearchAlgorithm, table_config: TableConfig) -> Self {
+    let ai_config = AIConfig::builder().search(search).table(table_config).build().expect("invalid search parameters");
+
+    BoardAIEngine::with_game_and_config(game, ai_config)
+  }
+
+  /// Constructor playing an existing game with the given search parameters.
+  /// Sets up the initial shared state and communication channels between main thread and moves worker thread.
+  pub fn with_game_and_config(game: BoardGame<B>, ai_config: AIConfig) -> Self {
 
     // transmission channels endpoints for full duplex communication between main thread and worker thread
-    let (worker_task_sender, worker_task_receiver): (Sender<WorkerMessage>, Receiver<WorkerMessage>) = mpsc::channel();
+    let (worker_task_sender, worker_task_receiver): (Sender<WorkerMessage<B>>, Receiver<WorkerMessage<B>>) = mpsc::channel();
     let (worker_response_sender, worker_response_receiver): (Sender<WorkerResponse>, Receiver<WorkerResponse>) = mpsc::channel();
 
     // this worker thread precomputes and buffers a sequence of optimal moves to make the game flow smoother
-    let moves_worker = Some(thread::spawn(move || worker_job(worker_task_receiver, worker_response_sender)));
-
-    AIEngine {
-      game: Game::new(),
+    let config = *game.get_config();
+    let table_stats = Arc::new(Mutex::new(TableStats::default()));
+    let worker_table_stats = Arc::clone(&table_stats);
+    let worker_ai_config = ai_config.clone();
+    let moves_worker = Some(thread::spawn(move || worker_job(worker_task_receiver, worker_response_sender, config, worker_ai_config, worker_table_stats)));
+
+    BoardAIEngine {
+      game,
       state: AIState::Inactive,
+      ai_config,
+      table_stats,
       moves_worker,
       worker_task_sender,
       worker_response_receiver,
@@ -94,8 +175,19 @@ impl AIEngine {
   }
 
   // Getters
-  pub fn get_game(&self) -> &Game { &self.game }
+  pub fn get_game(&self) -> &BoardGame<B> { &self.game }
   pub fn get_ai_state(&self) -> AIState { self.state }
+  pub fn get_ai_config(&self) -> &AIConfig { &self.ai_config }
+
+  /// Returns the statistics of the transposition table as of the last search completed by the worker.
+  pub fn get_table_stats(&self) -> TableStats {
+    *self.table_stats.lock().unwrap()
+  }
+
+  /// Registers an observer of the game played, see `Game::add_observer()`.
+  pub fn add_observer(&mut self, observer: Box<dyn GameObserver + Send>) {
+    self.game.add_observer(observer);
+  }
 
   /// Gets the next optimal move enqueued based on the current state of the grid.
   pub fn get_next_optimal_move(&self) -> Option<PlayerMove> {
@@ -145,7 +237,7 @@ impl AIEngine {
 
         // should always be able to send
         self.worker_task_sender.send(WorkerMessage::Work(
-          *self.game.get_grid(),
+          self.game.get_grid().clone(),
           self.game.get_state().get_move_count() as usize,
         )).unwrap();
 
@@ -162,11 +254,13 @@ impl AIEngine {
 
 // GameAPI
 
-impl GameAPI for AIEngine {
+impl<B: SearchBoard> GameAPI for BoardAIEngine<B> {
+
+  type Board = B;
 
-  fn get_grid(&self) -> &Grid<EncodedGrid> { &self.game.get_grid() }
+  fn get_grid(&self) -> &B { self.game.get_grid() }
 
-  fn get_state(&self) -> &GameState { &self.game.get_state() }
+  fn get_state(&self) -> &GameState { self.game.get_state() }
 
   fn reset(&mut self) { 
     
@@ -178,7 +272,7 @@ impl GameAPI for AIEngine {
     self.game.reset();
   }
 
-  fn process_move(&mut self, mut player_move: Option<PlayerMove>) -> Option<AnimationData> {
+  fn process_move(&mut self, mut player_move: Option<PlayerMove>) -> Option<AnimationData<B>> {
 
     // if the AI is active override the move and apply the next optimal move enqueued
     if let AIState::Active = self.state {
@@ -214,12 +308,90 @@ impl GameAPI for AIEngine {
 
   }
 
+  fn redo_move(&mut self) {
+
+    // process redoing only if the AI is not active
+    if let AIState::Inactive = self.state {
+      self.game.redo_move();
+    }
+
+  }
+
+  fn history_len(&self) -> usize { self.game.history_len() }
+
+  fn jump_to(&mut self, move_index: usize) -> bool {
+
+    // process jumping only if the AI is not active, as undoing and redoing
+    if let AIState::Active = self.state {
+      return false;
+    }
+
+    self.game.jump_to(move_index)
+  }
+
+}
+
+
+// SearchBoard
+
+impl SearchBoard for Grid<EncodedGrid> {
+
+  fn search_optimal_move(
+    &self,
+    move_count: usize,
+    ai_config: &AIConfig,
+    config: &GameConfig,
+    table: &mut TranspositionTable,
+    rng: &mut GameRng,
+  ) -> Option<PlayerMove> {
+
+    // shared with Game, built only once
+    let precomputed_moves = moves::precomputed_moves();
+
+    search_optimal_move(ai_config, self, move_count, precomputed_moves, config, table, rng)
+  }
+
+}
+
+// The forecast tree and Monte Carlo searches are written for the grid, so boards are searched by expectimax whatever the search algorithm,
+// as deep as the expectimax search set in `ai_config` or `DEFAULT_BOARD_EXPECTIMAX_DEPTH` moves ahead otherwise
+impl SearchBoard for Board {
+
+  fn search_optimal_move(
+    &self,
+    _move_count: usize,
+    ai_config: &AIConfig,
+    config: &GameConfig,
+    _table: &mut TranspositionTable,
+    _rng: &mut GameRng,
+  ) -> Option<PlayerMove> {
+
+    let deadline = ai_config.get_time_budget().map(|budget| Instant::now() + budget);
+    let depth = match ai_config.get_search() {
+      SearchAlgorithm::Expectimax { depth } => depth,
+      _ => expectimax::DEFAULT_BOARD_EXPECTIMAX_DEPTH,
+    };
+
+    expectimax::board_expectimax_move(self, depth, deadline, config, ai_config.get_evaluator())
+  }
+
+}
+
+
+// Default
+
+impl<B: SearchBoard> Default for BoardAIEngine<B> {
+
+  fn default() -> Self {
+    BoardAIEngine::new()
+  }
+
 }
 
 
 // Drop
 
-impl Drop for AIEngine {
+impl<B: SearchBoard> Drop for BoardAIEngine<B> {
 
   fn drop(&mut self) {
 
@@ -235,23 +407,22 @@ impl Drop for AIEngine {
 // Functions
 //------------------------------------------------
 
-/// This function generates the leaves of the forecast tree.
+/// This function generates the leaves of the forecast tree, `None` if `deadline` expires before they are all generated.
 fn generate_leaves(
   grid: &Grid<EncodedGrid>, 
   move_count: usize, 
   max_depth: usize, 
-  precomputed_moves: &HashMap<EncodedEntryType, LineStackingResult>
-) -> VecDeque<AINode> {
+  precomputed_moves: &MovesTable,
+  config: &GameConfig,
+  deadline: Option<Instant>,
+) -> Option<VecDeque<AINode>> {
 
   let mut queue = VecDeque::with_capacity(100);
   let mut current_node: AINode;
   let mut new_node: AINode;
-  let directions = [PlayerMove::Up, PlayerMove::Left, PlayerMove::Right, PlayerMove::Down]; // enums not iterable so order must be the same
   let mut move_result: MoveStackingResult;
-  let base_mask = (ENCODING_BITS as f64).exp2() as EncodedEntryType - 1;
-  let mut mask_j: EncodedEntryType;
   let mut temp_grid: Grid<EncodedGrid>;
-  let estimated_probability = bayes_beta_update(grid, move_count as usize);
+  let estimated_probability = bayes_beta_update(grid, move_count, config);
 
   let mut current_depth = 0;
   let root = AINode::new(
@@ -265,64 +436,52 @@ fn generate_leaves(
   queue.push_back(root);
 
   // generate nodes in a Breadth-First fashion to reach the sequence of leaves
-  while queue.len() > 0 {
+  while !queue.is_empty() {
     current_node = queue.pop_front().unwrap();
 
+    if expired(deadline) {
+      return None;
+    }
+
     // stochastic pruning of very unlikely paths (paths where disproportionally too many 4s appear) - risky heuristic
     if current_node.get_depth() <= 2 || current_node.get_path_probability().powf(1. / current_node.get_depth() as f64) >= PATH_PROBABILITY_THRESHOLD {
 
-      // process each move for the current grid
-      for &direction in directions.iter() {
+      // process each move changing the current grid, null moves generate no tile allocations
+      for direction in precomputed_moves.legal_moves(current_node.get_grid()) {
         move_result = moves::process_grid_stacking(direction, current_node.get_grid(), precomputed_moves);
-        mask_j = base_mask;
-
-        // generate all the possible tile allocations only if the move was not null
-        if engine::is_effective_move(&move_result) {
-
-          // run through each tile position
-          for j in 0..GRID_SIDE {
-            for i in 0..GRID_SIDE {
-
-              // select only empty tiles
-              if move_result.get_new_grid()[i] & mask_j == 0 {
-
-                // make both 2 and 4 tile using the log2 versions [1, 2] to manipulate encoded grid directly
-                for tile in 1..=2 {
-                  temp_grid = *move_result.get_new_grid();
-                  temp_grid[i] |= tile << ENCODING_BITS * j;
-
-                  new_node = AINode::new(
-                    &temp_grid,
-                    match current_node.get_originating_move() {
-                      Some(player_move) => Some(player_move),
-                      _ => Some(direction),
-                    },
-                    move_result.get_delta_score(),
-                    match tile {
-                      1 => estimated_probability,
-                      _ => 1. - estimated_probability,
-                    },
-                    current_node.get_depth() + 1,
-                  );
-
-                  // when a new node reaches a new depth, stop if the number of leaves has reached a certain threshold or depth reached a certain level
-                  if current_depth != new_node.get_depth() && (queue.len() > TREE_SIZE_THRESHOLD || new_node.get_depth() > max_depth) {
-                    queue.push_front(current_node);
-                    return queue;
-                  }
-
-                  // otherwise update depth and append new node
-                  current_depth = new_node.get_depth();
-                  queue.push_back(new_node);
-
-                }
-
-              }
 
+        // run through each empty tile position
+        for (i, j) in move_result.get_new_grid().empty_cells() {
+
+          // make both 2 and 4 tile
+          for &tile in [2, 4].iter() {
+            temp_grid = *move_result.get_new_grid();
+            temp_grid.set_tile(i, j, tile);
+
+            new_node = AINode::new(
+              &temp_grid,
+              match current_node.get_originating_move() {
+                Some(player_move) => Some(player_move),
+                _ => Some(direction),
+              },
+              move_result.get_delta_score(),
+              match tile {
+                2 => estimated_probability,
+                _ => 1. - estimated_probability,
+              },
+              current_node.get_depth() + 1,
+            );
+
+            // when a new node reaches a new depth, stop if the number of leaves has reached a certain threshold or depth reached a certain level
+            if current_depth != new_node.get_depth() && (queue.len() > TREE_SIZE_THRESHOLD || new_node.get_depth() > max_depth) {
+              queue.push_front(current_node);
+              return Some(queue);
             }
 
-            // shift to new column of the encoded grid
-            mask_j <<= ENCODING_BITS;
+            // otherwise update depth and append new node
+            current_depth = new_node.get_depth();
+            queue.push_back(new_node);
+
           }
 
         }
@@ -337,28 +496,33 @@ fn generate_leaves(
 
   // if we can reduce the depth we try and reduce it
   if max_depth > 0 {
-    return generate_leaves(grid, move_count, max_depth - 1, precomputed_moves);
+    return generate_leaves(grid, move_count, max_depth - 1, precomputed_moves, config, deadline);
   }
 
   // otherwise nothing can be done, meaning game over, return empty queue
-  queue
+  Some(queue)
 
 }
 
-/// this function calculates the optimal move given an initial state
+/// this function calculates the optimal move given an initial state, `None` if no move can be made or `deadline` expires first
+#[allow(clippy::too_many_arguments)]
 fn calculate_optimal_move(
   grid: &Grid<EncodedGrid>, 
   move_count: usize, 
   max_depth: usize, 
-  precomputed_moves: &HashMap<EncodedEntryType, LineStackingResult>
+  precomputed_moves: &MovesTable,
+  config: &GameConfig,
+  evaluator: &Evaluator,
+  table: &mut TranspositionTable,
+  deadline: Option<Instant>,
 ) -> Option<PlayerMove> {
 
   use PlayerMove::{Up, Left, Right, Down};
   
-  let leaves = generate_leaves(grid, move_count, max_depth, precomputed_moves);
+  let leaves = generate_leaves(grid, move_count, max_depth, precomputed_moves, config, deadline)?;
 
   // if empty tree or just root no move can be made so return None
-  if leaves.len() == 0 || leaves[0].get_depth() == 0 {
+  if leaves.is_empty() || leaves[0].get_depth() == 0 {
     return None;
   }
 
@@ -376,10 +540,14 @@ fn calculate_optimal_move(
 
   // evaluate each leaf
   for node in leaves.iter() {
+    if expired(deadline) {
+      return None;
+    }
+
     direction = node.get_originating_move().unwrap();
     utility_data_ref = moves_utilities.get_mut(&direction).unwrap();
 
-    utility_data_ref.inc_expected_utility(node.get_path_probability() * utility(node.get_grid()));
+    utility_data_ref.inc_expected_utility(node.get_path_probability() * leaf_utility(node.get_grid(), config, evaluator, table));
     utility_data_ref.inc_count();
   }
 
@@ -402,20 +570,89 @@ fn calculate_optimal_move(
   optimal_move
 }
 
+/// Utility of a leaf of the forecast tree, looked up in the transposition table as a grid searched 0 moves deep.
+fn leaf_utility(grid: &Grid<EncodedGrid>, config: &GameConfig, evaluator: &Evaluator, table: &mut TranspositionTable) -> f64 {
+  table.get(grid, 0).unwrap_or_else(|| {
+    let value = evaluator.evaluate(grid, config);
+    table.insert(grid, 0, value);
+    value
+  })
+}
+
+/// Calculates the optimal move with the search of `ai_config`, within its time budget if any.
+/// The leaf average search always completes a forecast 1 move deep, then deepens it until the deadline interrupts a forecast.
+fn search_optimal_move(
+  ai_config: &AIConfig,
+  grid: &Grid<EncodedGrid>,
+  move_count: usize,
+  precomputed_moves: &MovesTable,
+  config: &GameConfig,
+  table: &mut TranspositionTable,
+  rng: &mut GameRng,
+) -> Option<PlayerMove> {
+
+  let deadline = ai_config.get_time_budget().map(|budget| Instant::now() + budget);
+  let evaluator = ai_config.get_evaluator();
+
+  // the values of the previous moves stay valid, but give way to the ones of this search
+  table.new_search();
+
+  match (ai_config.get_search(), deadline) {
+
+    (SearchAlgorithm::LeafAverage, None) => calculate_optimal_move(grid, move_count, DEFAULT_TREE_DEPTH, precomputed_moves, config, evaluator, table, None),
+
+    (SearchAlgorithm::LeafAverage, Some(deadline)) => {
+      let mut optimal_move = calculate_optimal_move(grid, move_count, 1, precomputed_moves, config, evaluator, table, None);
+
+      // a deeper forecast finds no move only if the deadline interrupted it, as the shallower one found a move
+      for depth in 2..=DEFAULT_TREE_DEPTH {
+        match calculate_optimal_move(grid, move_count, depth, precomputed_moves, config, evaluator, table, Some(deadline)) {
+          Some(player_move) => optimal_move = Some(player_move),
+          None => break,
+        }
+      }
+
+      optimal_move
+    },
+
+    (SearchAlgorithm::Expectimax { depth }, None) => expectimax::expectimax_move(grid, depth, precomputed_moves, config, evaluator, table),
+
+    (SearchAlgorithm::Expectimax { depth }, Some(deadline)) => expectimax::iterative_deepening_move(grid, depth, deadline, precomputed_moves, config, evaluator, table),
+
+    (SearchAlgorithm::MonteCarlo(mcts_config), deadline) => mcts::mcts_move(grid, &mcts_config, deadline, precomputed_moves, config, evaluator, rng),
+  }
+}
+
+/// Whether the deadline, if any, has expired.
+fn expired(deadline: Option<Instant>) -> bool {
+  deadline.is_some_and(|deadline| Instant::now() >= deadline)
+}
+
 /// Defines the job of the moves worker.
-fn worker_job(tasks: Receiver<WorkerMessage>, responses: Sender<WorkerResponse>) {
+/// The transposition table lives as long as the worker, and its statistics are published to `table_stats` after each search.
+fn worker_job<B: SearchBoard>(
+  tasks: Receiver<WorkerMessage<B>>,
+  responses: Sender<WorkerResponse>,
+  config: GameConfig,
+  ai_config: AIConfig,
+  table_stats: Arc<Mutex<TableStats>>,
+) {
 
   use WorkerMessage::{Work, Pause, Shutdown, MoveReceived};
   use WorkerState::{Paused, Working, Waiting, Terminating};
 
   // worker state variables
-  let mut buffered_count = 0; // keeps track of how many moves have been sent to the main thread without an acknowledgement.
+  let mut buffered_count: usize = 0; // keeps track of how many moves have been sent to the main thread without an acknowledgement.
   let mut worker_state = Paused;
 
   // worker data variables
-  let mut current_grid = Grid::new(&[0; GRID_SIDE]);
+  let mut current_grid = B::empty(config.get_rows(), config.get_cols());
   let mut current_move_count: usize = 0;
-  let precomputed_moves = moves::make_precomputed_hashmap(); // Duplicated with Game... should be under Arc<Mutex<_>> with Game to save memory...
+  let mut table = TranspositionTable::new(ai_config.get_table());
+  let mut rng = match ai_config.get_search() {
+    SearchAlgorithm::MonteCarlo(mcts_config) => GameRng::seed_from_u64(mcts_config.get_seed()),
+    _ => GameRng::seed_from_u64(0),
+  };
 
   // Worker loop
   loop {
@@ -442,7 +679,7 @@ fn worker_job(tasks: Receiver<WorkerMessage>, responses: Sender<WorkerResponse>)
 
         // Receive acknowledgement from main of move received, so make space for a new move to send
         MoveReceived => {
-          if buffered_count > 0 { buffered_count -= 1; }
+          buffered_count = buffered_count.saturating_sub(1);
         },
 
         // Enter terminating state on shutdown command and break out of the message checking loop
@@ -467,10 +704,10 @@ fn worker_job(tasks: Receiver<WorkerMessage>, responses: Sender<WorkerResponse>)
 
           buffered_count += 1;
 
-          responses.send(
-            WorkerResponse::OptimalMove(
-              calculate_optimal_move(&current_grid, current_move_count, DEFAULT_TREE_DEPTH, &precomputed_moves)
-            )).unwrap();
+          let optimal_move = current_grid.search_optimal_move(current_move_count, &ai_config, &config, &mut table, &mut rng);
+          *table_stats.lock().unwrap() = table.get_stats();
+
+          responses.send(WorkerResponse::OptimalMove(optimal_move)).unwrap();
 
         // if the buffer is full, send info and yield to the OS scheduler
         } else {
@@ -515,6 +752,10 @@ fn worker_job(tasks: Receiver<WorkerMessage>, responses: Sender<WorkerResponse>)
 mod tests {
 
   use super::*;
+  use std::time::Duration;
+  use crate::ai::evaluation::{Aggregation, Emptiness, Monotonicity};
+  use crate::ai::transposition::TableConfig;
+  use crate::game::engine::Game;
 
 
   // testing generate_leaves()
@@ -523,7 +764,7 @@ mod tests {
   pub fn test_generate_leaves_first_level() {
 
     use PlayerMove::{Up, Left};
-    let precomputed_moves = moves::make_precomputed_hashmap();
+    let precomputed_moves = moves::precomputed_moves();
 
     let grid = Grid::from_decoded(&[
       [0, 8, 4, 2],
@@ -584,7 +825,7 @@ mod tests {
       ]), Some(Left), 0, 0.1, 1),
     ]);
 
-    let result = generate_leaves(&grid, 143, 1, &precomputed_moves);
+    let result = generate_leaves(&grid, 143, 1, precomputed_moves, &GameConfig::default(), None).unwrap();
 
     // compare all the actual results with all the expected results
     for k in 0..result.len() {
@@ -605,7 +846,7 @@ mod tests {
   #[test]
   pub fn test_generate_leaves_same_level_only() {
 
-    let precomputed_moves = moves::make_precomputed_hashmap();
+    let precomputed_moves = moves::precomputed_moves();
 
     let grid = Grid::from_decoded(&[
       [0, 8, 4, 2],
@@ -614,7 +855,7 @@ mod tests {
       [4, 2, 16, 8],
     ]);
 
-    let result = generate_leaves(&grid, 143, DEFAULT_TREE_DEPTH, &precomputed_moves);
+    let result = generate_leaves(&grid, 143, DEFAULT_TREE_DEPTH, precomputed_moves, &GameConfig::default(), None).unwrap();
 
     let depth = result[result.len() - 1].get_depth();
     for (k, node) in result.iter().rev().enumerate() {
@@ -626,7 +867,7 @@ mod tests {
   #[test]
   pub fn test_generate_leaves_terminating_leaves() {
 
-    let precomputed_moves = moves::make_precomputed_hashmap();
+    let precomputed_moves = moves::precomputed_moves();
 
     let grid = Grid::from_decoded(&[
       [32, 32, 8, 32],
@@ -635,7 +876,7 @@ mod tests {
       [8, 4, 8, 4],
     ]);
 
-    let result = generate_leaves(&grid, 143, DEFAULT_TREE_DEPTH, &precomputed_moves);
+    let result = generate_leaves(&grid, 143, DEFAULT_TREE_DEPTH, precomputed_moves, &GameConfig::default(), None).unwrap();
 
     assert_eq!(result.len(), 1);
     assert_eq!(*result[0].get_grid(), grid);
@@ -644,7 +885,7 @@ mod tests {
   #[test]
   pub fn test_generate_leaves_terminating_root() {
 
-    let precomputed_moves = moves::make_precomputed_hashmap();
+    let precomputed_moves = moves::precomputed_moves();
 
     let grid = Grid::from_decoded(&[
       [32, 64, 8, 32],
@@ -653,7 +894,7 @@ mod tests {
       [8, 4, 8, 4],
     ]);
 
-    let result = generate_leaves(&grid, 143, DEFAULT_TREE_DEPTH, &precomputed_moves);
+    let result = generate_leaves(&grid, 143, DEFAULT_TREE_DEPTH, precomputed_moves, &GameConfig::default(), None).unwrap();
 
     assert_eq!(result.len(), 0);
   }
@@ -664,7 +905,7 @@ mod tests {
   #[test]
   pub fn test_calculate_optimal_move() {
 
-    let precomputed_moves = moves::make_precomputed_hashmap();
+    let precomputed_moves = moves::precomputed_moves();
 
     let grid = Grid::from_decoded(&[
       [4, 2, 4, 2],
@@ -675,7 +916,254 @@ mod tests {
 
     let move_count = 909;
 
-    assert_eq!(calculate_optimal_move(&grid, move_count, DEFAULT_TREE_DEPTH, &precomputed_moves), Some(PlayerMove::Left));
+    assert_eq!(calculate_optimal_move(&grid, move_count, DEFAULT_TREE_DEPTH, precomputed_moves, &GameConfig::default(), &Evaluator::default(), &mut TranspositionTable::new(TableConfig::disabled()), None), Some(PlayerMove::Left));
+  }
+
+
+  // Testing AIEngine::with_seed()
+
+  #[test]
+  pub fn test_ai_engine_with_seed() {
+
+    let mut engine = AIEngine::with_seed(7);
+    let mut game = Game::with_seed(7);
+
+    assert_eq!(*engine.get_grid(), *game.get_grid());
+
+    use PlayerMove::{Up, Left, Right, Down};
+    for &player_move in [Left, Up, Right, Down, Left, Up].iter() {
+      engine.process_move(Some(player_move));
+      game.process_move(Some(player_move));
+    }
+
+    assert_eq!(*engine.get_grid(), *game.get_grid());
+    assert_eq!(*engine.get_state(), *game.get_state());
+  }
+
+
+  // Testing AIEngine::with_game_and_config()
+
+  #[test]
+  pub fn test_ai_engine_expectimax() {
+
+    let ai_config = AIConfig::builder().search(SearchAlgorithm::Expectimax { depth: 2 }).build().unwrap();
+    let mut engine = AIEngine::with_game_and_config(Game::with_seed(9), ai_config);
+
+    assert_eq!(engine.get_ai_config().get_search(), SearchAlgorithm::Expectimax { depth: 2 });
+    assert_eq!(engine.get_table_stats(), TableStats::default());
+
+    let grid = *engine.get_grid();
+    let expected = expectimax::expectimax_move(&grid, 2, moves::precomputed_moves(), &GameConfig::default(), &Evaluator::default(), &mut TranspositionTable::new(TableConfig::disabled()));
+
+    // the worker plays the expectimax move on the current grid
+    engine.toggle_ai();
+    assert_eq!(engine.get_next_optimal_move(), expected);
+    engine.toggle_ai();
+
+    assert!(expected.is_some());
+
+    // the statistics of the worker's table are published after each search
+    let stats = engine.get_table_stats();
+    assert!(stats.get_stored() > 0);
+    assert!(stats.get_hits() > 0);
+  }
+
+
+  // Testing AIEngine::with_game_and_search()
+
+  #[test]
+  pub fn test_ai_engine_with_game_and_search() {
+
+    let search = SearchAlgorithm::Expectimax { depth: 2 };
+    let engine = AIEngine::with_game_and_search(Game::with_seed(9), search, TableConfig::disabled());
+
+    assert_eq!(engine.get_ai_config().get_search(), search);
+    assert_eq!(engine.get_ai_config().get_table(), TableConfig::disabled());
+    assert_eq!(engine.get_ai_config().get_time_budget(), None);
+  }
+
+
+  #[test]
+  pub fn test_ai_engine_monte_carlo() {
+
+    let mcts_config = MctsConfig::builder().budget(mcts::MctsBudget::Iterations(100)).rollout_moves(10).seed(4).build().unwrap();
+    let ai_config = AIConfig::builder().search(SearchAlgorithm::MonteCarlo(mcts_config)).build().unwrap();
+    let mut engine = AIEngine::with_game_and_config(Game::with_seed(9), ai_config);
+
+    // the worker's first search draws the same random numbers as a fresh generator seeded alike
+    let grid = *engine.get_grid();
+    let expected = mcts::mcts_move(&grid, &mcts_config, None, moves::precomputed_moves(), &GameConfig::default(), &Evaluator::default(), &mut GameRng::seed_from_u64(4));
+
+    engine.toggle_ai();
+    assert_eq!(engine.get_next_optimal_move(), expected);
+    engine.toggle_ai();
+
+    assert!(expected.is_some());
+  }
+
+
+  #[test]
+  pub fn test_ai_engine_evaluator() {
+
+    let evaluator = Evaluator::builder().heuristic(Emptiness, 1.).heuristic(Monotonicity, 0.5).aggregation(Aggregation::Linear).build().unwrap();
+    let ai_config = AIConfig::builder().search(SearchAlgorithm::Expectimax { depth: 1 }).evaluator(evaluator.clone()).build().unwrap();
+    let mut engine = AIEngine::with_game_and_config(Game::with_seed(9), ai_config);
+
+    assert_eq!(engine.get_ai_config().get_evaluator().get_weights(), vec![("emptiness", 1.), ("monotonicity", 0.5)]);
+
+    // the worker values the grids with the evaluator of its engine
+    let grid = *engine.get_grid();
+    let expected = expectimax::expectimax_move(&grid, 1, moves::precomputed_moves(), &GameConfig::default(), &evaluator, &mut TranspositionTable::new(TableConfig::disabled()));
+
+    engine.toggle_ai();
+    assert_eq!(engine.get_next_optimal_move(), expected);
+    engine.toggle_ai();
+
+    assert!(expected.is_some());
+  }
+
+
+  // Testing search_optimal_move()
+
+  #[test]
+  pub fn test_search_optimal_move_time_budget() {
+
+    let grid = Grid::from_decoded(&[
+      [4, 2, 4, 2],
+      [8, 512, 64, 4],
+      [1024, 256, 32, 16],
+      [64, 8, 8, 2],
+    ]);
+
+    // the search may overrun the budget by one step of its loop at most
+    const SEARCH_OVERHEAD: Duration = Duration::from_millis(10);
+    let budget = Duration::from_millis(20);
+    let precomputed_moves = moves::precomputed_moves();
+
+    // the lazily built tables of moves aren't part of the search time
+    moves::precomputed_bitboard_moves();
+
+    let mcts_config = MctsConfig::builder().budget(mcts::MctsBudget::Iterations(usize::MAX)).build().unwrap();
+
+    for &search in [SearchAlgorithm::LeafAverage, SearchAlgorithm::Expectimax { depth: 8 }, SearchAlgorithm::MonteCarlo(mcts_config)].iter() {
+      let ai_config = AIConfig::builder().search(search).time_budget(budget).build().unwrap();
+      let mut table = TranspositionTable::new(TableConfig::disabled());
+
+      let start = Instant::now();
+      let optimal_move = search_optimal_move(&ai_config, &grid, 909, precomputed_moves, &GameConfig::default(), &mut table, &mut GameRng::seed_from_u64(0));
+
+      // a move is always found, and the search stops about when the budget expires
+      assert!(optimal_move.is_some(), "{:?}", search);
+      assert!(start.elapsed() < budget + SEARCH_OVERHEAD, "{:?} took {:?}", search, start.elapsed());
+    }
+  }
+
+  #[test]
+  pub fn test_search_optimal_move_board_time_budget() {
+
+    let config = GameConfig::builder().board_size(5, 5).build().unwrap();
+    let board = Board::from_decoded(&[
+      vec![4, 2, 4, 2, 8],
+      vec![8, 512, 64, 4, 0],
+      vec![1024, 256, 32, 16, 2],
+      vec![64, 8, 8, 2, 4],
+      vec![2, 4, 0, 16, 32],
+    ]);
+
+    const SEARCH_OVERHEAD: Duration = Duration::from_millis(10);
+    let budget = Duration::from_millis(20);
+
+    // boards are searched by expectimax whatever the search algorithm, deepening until the budget expires
+    for &search in [SearchAlgorithm::LeafAverage, SearchAlgorithm::Expectimax { depth: 8 }].iter() {
+      let ai_config = AIConfig::builder().search(search).time_budget(budget).build().unwrap();
+      let mut table = TranspositionTable::new(TableConfig::disabled());
+
+      let start = Instant::now();
+      let optimal_move = board.search_optimal_move(0, &ai_config, &config, &mut table, &mut GameRng::seed_from_u64(0));
+
+      assert!(optimal_move.is_some(), "{:?}", search);
+      assert!(start.elapsed() < budget + SEARCH_OVERHEAD, "{:?} took {:?}", search, start.elapsed());
+    }
+  }
+
+
+  // Testing BoardAIEngine on runtime-sized boards
+
+  #[test]
+  pub fn test_board_ai_engine_expectimax() {
+
+    let config = GameConfig::builder().board_size(5, 4).build().unwrap();
+    let ai_config = AIConfig::builder().search(SearchAlgorithm::Expectimax { depth: 1 }).build().unwrap();
+    let mut engine = BoardAIEngine::with_game_and_config(BoardGame::<Board>::with_config_and_seed(config, 9), ai_config);
+
+    let board = engine.get_grid().clone();
+    let expected = expectimax::board_expectimax_move(&board, 1, None, &config, &Evaluator::default());
+
+    // the worker plays the expectimax move on the current board
+    engine.toggle_ai();
+    assert_eq!(engine.get_next_optimal_move(), expected);
+    engine.toggle_ai();
+
+    assert!(expected.is_some());
+  }
+
+  #[test]
+  pub fn test_board_ai_engine_plays() {
+
+    let config = GameConfig::builder().board_size(3, 3).build().unwrap();
+    let ai_config = AIConfig::builder().search(SearchAlgorithm::Expectimax { depth: 1 }).build().unwrap();
+    let mut engine = BoardAIEngine::with_game_and_config(BoardGame::<Board>::with_config_and_seed(config, 1), ai_config);
+
+    // each activation restarts the worker from the current board, so every move played is effective
+    for count in 1..=5 {
+      engine.toggle_ai();
+      assert!(engine.process_move(None).is_some());
+      engine.toggle_ai();
+
+      assert_eq!((engine.get_grid().get_rows(), engine.get_grid().get_cols()), (3, 3));
+      assert_eq!(engine.get_state().get_move_count() as usize, count);
+    }
+
+    assert_eq!(engine.get_game().get_record().verify(), Ok(*engine.get_state()));
+  }
+
+
+  // Testing AIEngine::jump_to()
+
+  #[test]
+  pub fn test_ai_engine_jump_to() {
+
+    let mut engine = AIEngine::with_seed(8);
+
+    use PlayerMove::{Up, Left, Right, Down};
+    for &player_move in [Left, Up, Right, Down, Left, Up].iter() {
+      engine.process_move(Some(player_move));
+    }
+
+    let move_count = engine.get_state().get_move_count() as usize;
+    let grid = *engine.get_grid();
+
+    engine.toggle_ai();
+    engine.process_move(None);
+
+    // the history can't be navigated while the AI plays, as with undo and redo
+    let played = *engine.get_grid();
+    assert!(!engine.jump_to(move_count));
+    engine.undo_last_move();
+    assert_eq!(*engine.get_grid(), played);
+
+    engine.toggle_ai();
+
+    assert!(engine.jump_to(move_count));
+    assert_eq!(*engine.get_grid(), grid);
+    assert_eq!(engine.history_len(), engine.get_game().history_len());
+    assert!(!engine.jump_to(move_count + 2));
+
+    // the worker restarts from the restored grid, so the next optimal move is effective from there
+    engine.toggle_ai();
+    assert!(engine.process_move(None).is_some());
+    assert_eq!(engine.get_state().get_move_count() as usize, move_count + 1);
+    engine.toggle_ai();
   }
 
 
@@ -689,10 +1177,10 @@ mod tests {
 
     let game = Game::new();
 
-    let (worker_task_sender, worker_task_receiver): (Sender<WorkerMessage>, Receiver<WorkerMessage>) = mpsc::channel();
+    let (worker_task_sender, worker_task_receiver) = mpsc::channel::<WorkerMessage<Grid<EncodedGrid>>>();
     let (worker_response_sender, worker_response_receiver): (Sender<WorkerResponse>, Receiver<WorkerResponse>) = mpsc::channel();
 
-    let worker = thread::spawn(move || worker_job(worker_task_receiver, worker_response_sender));
+    let worker = thread::spawn(move || worker_job(worker_task_receiver, worker_response_sender, GameConfig::default(), AIConfig::default(), Arc::default()));
 
     let mut response: WorkerResponse;
     let mut response_count = 0;
@@ -711,10 +1199,7 @@ mod tests {
     response_count += 1;
 
     // the first move should never be None
-    assert!(match response {
-      OptimalMove(_) => true,
-      _ => false,
-    });
+    assert!(matches!(response, OptimalMove(_)));
 
     // consume all the responses until a BufferFull is met
     for response in worker_response_receiver.iter() {
@@ -726,37 +1211,25 @@ mod tests {
     assert_eq!(response_count, MOVES_QUEUE_CAPACITY + 1);
 
     // After a BufferFull acknowledgement is received, the buffer of messages from the worker should be empty and trying to receive should return a TryRecvError
-    assert!(match worker_response_receiver.try_recv() {
-      Err(_) => true,
-      _ => false,
-    });
+    assert!(worker_response_receiver.try_recv().is_err());
 
     // Sending a MoveReceived acknouledgement should make the worker produce a new move and a BufferFull message again
     worker_task_sender.send(MoveReceived).unwrap();
 
     // The first response after sending MoveReceived should be a valid move
     response = worker_response_receiver.recv().unwrap();
-    assert!(match response {
-      OptimalMove(_) => true,
-      _ => false,
-    });
+    assert!(matches!(response, OptimalMove(_)));
 
     // The second response should be a BufferFull again
     response = worker_response_receiver.recv().unwrap();
-    assert!(match response {
-      BufferFull => true,
-      _ => false,
-    });
+    assert!(matches!(response, BufferFull));
 
     // tell the worker to pause
     worker_task_sender.send(Pause).unwrap();
 
     // the buffer is now full so the first response should only be Paused
     response = worker_response_receiver.recv().unwrap();
-    assert!(match response {
-      Paused => true,
-      _ => false,
-    });
+    assert!(matches!(response, Paused));
 
     // tell the worker to restart working and see if it fills the buffer again after a Pause command
     worker_task_sender.send(Work(
@@ -783,10 +1256,10 @@ mod tests {
   #[should_panic]
   pub fn test_worker_job_panics() {
 
-    let (worker_task_sender, worker_task_receiver): (Sender<WorkerMessage>, Receiver<WorkerMessage>) = mpsc::channel();
+    let (worker_task_sender, worker_task_receiver) = mpsc::channel::<WorkerMessage<Grid<EncodedGrid>>>();
     let (worker_response_sender, _): (Sender<WorkerResponse>, Receiver<WorkerResponse>) = mpsc::channel();
 
-    let worker = thread::spawn(move || worker_job(worker_task_receiver, worker_response_sender));
+    let worker = thread::spawn(move || worker_job(worker_task_receiver, worker_response_sender, GameConfig::default(), AIConfig::default(), Arc::default()));
 
     worker_task_sender.send(WorkerMessage::Shutdown).unwrap();
     worker.join().unwrap();
@@ -0,0 +1,453 @@
+//! # `evaluation` module
+//!
+//! Contains the evaluation of the grids reached by the AI searches.
+//! A `Heuristic` scores a single feature of a grid, and an `Evaluator` combines the scores of a set of heuristics with runtime weights,
+//! so the AI is tuned by building an evaluator instead of editing constants. The default evaluator, also behind `utility()`,
+//! is the Cobb-Douglas product of the monotonicity, emptiness, mergeability and maximum tile scores.
+
+use std::{error, fmt, fmt::Display};
+use std::sync::Arc;
+
+use crate::ai::core::*;
+use crate::game::board::BoardView;
+use crate::game::config::GameConfig;
+
+
+// Parameters of the default evaluator
+const MONOTONICITY_WEIGHT: f64 = 0.4; // Monotonicity weight
+const EMPTINESS_WEIGHT: f64 = 0.2; // Emptiness weight
+const MERGEABILITY_WEIGHT: f64 = 0.15; // Mergeability weight
+const HOMOGENEITY_DEGREE: f64 = 8.; // Regulates the growth and concavity/convexity of the utility function
+
+
+//------------------------------------------------
+// Traits
+//------------------------------------------------
+
+/// Trait for a feature of a grid, or of a board of any size, scored by an `Evaluator`.
+/// The scores should lie in [0, 1] to be combined by a Cobb-Douglas product, higher scores being better for the player.
+/// Heuristics are shared with the worker thread of the `AIEngine`, hence `Send` and `Sync`.
+pub trait Heuristic: Send + Sync {
+
+  /// Name of the heuristic, reported along with its weight by `Evaluator::get_weights()`.
+  fn name(&self) -> &str;
+
+  /// Scores `board`, for a game played with the rules in `config`.
+  fn score(&self, board: &dyn BoardView, config: &GameConfig) -> f64;
+
+}
+
+
+//------------------------------------------------
+// Types and Definitions
+//------------------------------------------------
+
+/// Scores how monotonic the rows and the columns are.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub struct Monotonicity;
+
+/// Scores the fraction of empty cells.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub struct Emptiness;
+
+/// Scores how few distinct tiles clutter the grid below the maximum tile.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub struct Mergeability;
+
+/// Scores the maximum tile relative to the victory tile.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub struct MaxTile;
+
+/// Describes how an `Evaluator` combines the scores of its heuristics.
+#[derive(Copy, Clone, Debug)]
+pub enum Aggregation {
+  /// Product of the scores, each raised to its weight times `degree`, which regulates the growth and concavity of the utility.
+  CobbDouglas { degree: f64 },
+  /// Sum of the scores times their weights.
+  Linear,
+  /// Any function of the scores and the weights, both given in the order the heuristics were added.
+  Custom(fn(&[f64], &[f64]) -> f64),
+}
+
+/// Combines the scores of a set of heuristics with their weights into the utility of a grid.
+/// A grid holding the victory tile is worth the victory value whatever the heuristics, infinity by default so the searches always take the victory,
+/// or is scored by the heuristics alone without a victory value.
+/// Built with `Evaluator::builder()`.
+#[derive(Clone)]
+pub struct Evaluator {
+  heuristics: Vec<(Arc<dyn Heuristic>, f64)>,
+  aggregation: Aggregation,
+  victory_value: Option<f64>,
+}
+
+/// Builder of an `Evaluator`, validated by `build()`.
+#[derive(Clone, Debug)]
+pub struct EvaluatorBuilder {
+  evaluator: Evaluator,
+}
+
+/// Errors raised when building an invalid `Evaluator`.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum EvaluatorError {
+  NoHeuristics,
+  InvalidWeight(f64),
+  InvalidDegree(f64),
+  InvalidVictoryValue(f64),
+}
+
+
+//------------------------------------------------
+// Implementations
+//------------------------------------------------
+
+// Heuristic
+
+impl Heuristic for Monotonicity {
+
+  fn name(&self) -> &str { "monotonicity" }
+
+  fn score(&self, board: &dyn BoardView, _config: &GameConfig) -> f64 {
+    monotonicity_score(board.get_rows(), board.get_cols(), |i, j| board.get_tile(i, j))
+  }
+
+}
+
+impl Heuristic for Emptiness {
+
+  fn name(&self) -> &str { "emptiness" }
+
+  fn score(&self, board: &dyn BoardView, _config: &GameConfig) -> f64 {
+    emptiness_score(board.get_rows(), board.get_cols(), |i, j| board.get_tile(i, j))
+  }
+
+}
+
+impl Heuristic for Mergeability {
+
+  fn name(&self) -> &str { "mergeability" }
+
+  fn score(&self, board: &dyn BoardView, _config: &GameConfig) -> f64 {
+    mergeability_score(board.get_rows(), board.get_cols(), |i, j| board.get_tile(i, j))
+  }
+
+}
+
+impl Heuristic for MaxTile {
+
+  fn name(&self) -> &str { "max_tile" }
+
+  fn score(&self, board: &dyn BoardView, config: &GameConfig) -> f64 {
+    max_tile_score(board.get_rows(), board.get_cols(), config.get_log2_victory_threshold(), |i, j| board.get_tile(i, j))
+  }
+
+}
+
+
+// Inherent
+
+impl Evaluator {
+
+  /// Returns a builder with no heuristics, the Cobb-Douglas aggregation of `utility()` and an infinite victory value.
+  pub fn builder() -> EvaluatorBuilder {
+    EvaluatorBuilder {
+      evaluator: Evaluator {
+        heuristics: Vec::new(),
+        aggregation: Aggregation::CobbDouglas { degree: HOMOGENEITY_DEGREE },
+        victory_value: Some(f64::INFINITY),
+      },
+    }
+  }
+
+  // Getters
+  pub fn get_aggregation(&self) -> Aggregation { self.aggregation }
+  pub fn get_victory_value(&self) -> Option<f64> { self.victory_value }
+
+  /// Returns the names and weights of the heuristics, in the order they were added.
+  pub fn get_weights(&self) -> Vec<(&str, f64)> {
+    self.heuristics.iter().map(|(heuristic, weight)| (heuristic.name(), *weight)).collect()
+  }
+
+  /// Computes the utility of `board`, for a game played with the rules in `config`.
+  pub fn evaluate<B: BoardView>(&self, board: &B, config: &GameConfig) -> f64 {
+    let victory = board.max_tile() >= config.get_victory_threshold();
+
+    self.evaluate_scores(self.heuristics.iter().map(|(heuristic, _)| heuristic.score(board, config)), victory)
+  }
+
+  /// Computes the utility of a grid from the scores of the heuristics, given in the order they were added, and whether it holds the victory tile.
+  pub(crate) fn evaluate_scores<I: Iterator<Item = f64>>(&self, scores: I, victory: bool) -> f64 {
+
+    if let Some(victory_value) = self.victory_value.filter(|_| victory) {
+      return victory_value;
+    }
+
+    let weights = self.heuristics.iter().map(|(_, weight)| *weight);
+
+    match self.aggregation {
+      Aggregation::CobbDouglas { degree } => scores.zip(weights).map(|(score, weight)| score.powf(degree * weight)).product(),
+      Aggregation::Linear => scores.zip(weights).map(|(score, weight)| score * weight).sum(),
+      Aggregation::Custom(aggregate) => {
+        let scores: Vec<f64> = scores.collect();
+        let weights: Vec<f64> = weights.collect();
+
+        aggregate(&scores, &weights)
+      },
+    }
+  }
+
+  /// Checks that there is a heuristic to evaluate and that the weights and degree are usable.
+  pub fn validate(&self) -> Result<(), EvaluatorError> {
+    if self.heuristics.is_empty() {
+      return Err(EvaluatorError::NoHeuristics);
+    }
+
+    if let Some(&(_, weight)) = self.heuristics.iter().find(|(_, weight)| !weight.is_finite() || *weight < 0.) {
+      return Err(EvaluatorError::InvalidWeight(weight));
+    }
+
+    if let Some(victory_value) = self.victory_value.filter(|value| value.is_nan()) {
+      return Err(EvaluatorError::InvalidVictoryValue(victory_value));
+    }
+
+    match self.aggregation {
+      Aggregation::CobbDouglas { degree } if !degree.is_finite() || degree <= 0. => Err(EvaluatorError::InvalidDegree(degree)),
+      _ => Ok(()),
+    }
+  }
+
+}
+
+impl EvaluatorBuilder {
+
+  /// Adds `heuristic` with weight `weight`.
+  pub fn heuristic<H: Heuristic + 'static>(mut self, heuristic: H, weight: f64) -> Self {
+    self.evaluator.heuristics.push((Arc::new(heuristic), weight));
+    self
+  }
+
+  /// Sets how the scores of the heuristics are combined.
+  pub fn aggregation(mut self, aggregation: Aggregation) -> Self {
+    self.evaluator.aggregation = aggregation;
+    self
+  }
+
+  /// Sets the utility of the grids holding the victory tile, `None` to score them with the heuristics as any other grid.
+  pub fn victory_value(mut self, victory_value: Option<f64>) -> Self {
+    self.evaluator.victory_value = victory_value;
+    self
+  }
+
+  /// Builds the evaluator, checking its parameters.
+  pub fn build(self) -> Result<Evaluator, EvaluatorError> {
+    self.evaluator.validate()?;
+
+    Ok(self.evaluator)
+  }
+
+}
+
+
+// Default
+
+impl Default for Evaluator {
+
+  fn default() -> Self {
+    Evaluator::builder()
+      .heuristic(Monotonicity, MONOTONICITY_WEIGHT)
+      .heuristic(Emptiness, EMPTINESS_WEIGHT)
+      .heuristic(Mergeability, MERGEABILITY_WEIGHT)
+      .heuristic(MaxTile, 1. - MONOTONICITY_WEIGHT - EMPTINESS_WEIGHT - MERGEABILITY_WEIGHT)
+      .build()
+      .unwrap()
+  }
+
+}
+
+
+// Debug
+
+impl fmt::Debug for Evaluator {
+
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    f.debug_struct("Evaluator")
+      .field("heuristics", &self.get_weights())
+      .field("aggregation", &self.aggregation)
+      .field("victory_value", &self.victory_value)
+      .finish()
+  }
+
+}
+
+
+// Display
+
+impl Display for EvaluatorError {
+
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      EvaluatorError::NoHeuristics => write!(f, "an evaluator needs at least a heuristic"),
+      EvaluatorError::InvalidWeight(weight) => write!(f, "heuristic weight {} is not a finite non-negative number", weight),
+      EvaluatorError::InvalidDegree(degree) => write!(f, "Cobb-Douglas degree {} is not a finite positive number", degree),
+      EvaluatorError::InvalidVictoryValue(value) => write!(f, "victory value {} is not a number", value),
+    }
+  }
+
+}
+
+
+// Error
+
+impl error::Error for EvaluatorError {}
+
+
+//------------------------------------------------
+// Unit tests
+//------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+
+  use super::*;
+  use crate::game::core::*;
+  use crate::game::board::Board;
+
+  fn test_grids() -> Vec<Grid<EncodedGrid>> {
+    vec![
+      Grid::from_decoded(&[
+        [0, 8, 4, 2],
+        [0, 2, 64, 128],
+        [8, 64, 4, 2],
+        [4, 2, 16, 8],
+      ]),
+      Grid::from_decoded(&[
+        [8, 4, 2, 0],
+        [4, 2, 0, 0],
+        [2, 0, 0, 0],
+        [0, 0, 0, 0],
+      ]),
+      Grid::from_decoded(&[
+        [2048, 4, 2, 0],
+        [0, 0, 0, 0],
+        [0, 0, 0, 0],
+        [0, 0, 0, 2],
+      ]),
+    ]
+  }
+
+  // Heuristic counting the tiles in the corners
+  struct Corners;
+
+  impl Heuristic for Corners {
+    fn name(&self) -> &str { "corners" }
+
+    fn score(&self, board: &dyn BoardView, _config: &GameConfig) -> f64 {
+      let (last_row, last_col) = (board.get_rows() - 1, board.get_cols() - 1);
+
+      [(0, 0), (0, last_col), (last_row, 0), (last_row, last_col)].iter().filter(|&&(i, j)| board.get_tile(i, j) > 0).count() as f64 / 4.
+    }
+  }
+
+  fn min_score(scores: &[f64], _weights: &[f64]) -> f64 {
+    scores.iter().copied().fold(f64::INFINITY, f64::min)
+  }
+
+  // Testing Evaluator::evaluate()
+
+  #[test]
+  pub fn test_default_evaluator_matches_utility() {
+    let config = GameConfig::default();
+    let evaluator = Evaluator::default();
+
+    for grid in test_grids().iter() {
+      assert_eq!(evaluator.evaluate(grid, &config), utility(grid, &config));
+    }
+
+    assert_eq!(evaluator.evaluate(&test_grids()[2], &config), f64::INFINITY);
+  }
+
+  #[test]
+  pub fn test_evaluator_victory_value() {
+    let config = GameConfig::default();
+    let victory = test_grids()[2];
+
+    let capped = Evaluator::builder().heuristic(Emptiness, 1.).aggregation(Aggregation::Linear).victory_value(Some(2.)).build().unwrap();
+    assert_eq!(capped.evaluate(&victory, &config), 2.);
+    assert_eq!(capped.evaluate(&test_grids()[1], &config), Emptiness.score(&test_grids()[1], &config));
+
+    // without a victory value the heuristics score the victory too
+    let plain = Evaluator::builder().heuristic(Emptiness, 1.).aggregation(Aggregation::Linear).victory_value(None).build().unwrap();
+    assert_eq!(plain.evaluate(&victory, &config), 12. / 16.);
+  }
+
+  #[test]
+  pub fn test_evaluator_linear() {
+    let config = GameConfig::default();
+    let evaluator = Evaluator::builder()
+      .heuristic(Emptiness, 2.)
+      .heuristic(Corners, 0.5)
+      .aggregation(Aggregation::Linear)
+      .build()
+      .unwrap();
+
+    let grid = test_grids()[1];
+
+    assert_eq!(evaluator.evaluate(&grid, &config), 2. * 10. / 16. + 0.5 * 1. / 4.);
+    assert_eq!(evaluator.get_weights(), vec![("emptiness", 2.), ("corners", 0.5)]);
+  }
+
+  #[test]
+  pub fn test_evaluator_custom() {
+    let config = GameConfig::default();
+    let evaluator = Evaluator::builder()
+      .heuristic(Monotonicity, 1.)
+      .heuristic(Emptiness, 1.)
+      .aggregation(Aggregation::Custom(min_score))
+      .build()
+      .unwrap();
+
+    let grid = test_grids()[0];
+
+    assert_eq!(evaluator.evaluate(&grid, &config), Monotonicity.score(&grid, &config).min(Emptiness.score(&grid, &config)));
+  }
+
+  #[test]
+  pub fn test_evaluator_board() {
+    let config = GameConfig::builder().board_size(2, 3).victory_threshold(64).build().unwrap();
+    let evaluator = Evaluator::builder()
+      .heuristic(Emptiness, 2.)
+      .heuristic(Corners, 0.5)
+      .aggregation(Aggregation::Linear)
+      .build()
+      .unwrap();
+
+    let board = Board::from_decoded(&[
+      vec![8, 4, 0],
+      vec![4, 0, 2],
+    ]);
+    let victory = Board::from_decoded(&[
+      vec![64, 0, 0],
+      vec![0, 0, 2],
+    ]);
+
+    assert_eq!(evaluator.evaluate(&board, &config), 2. * 2. / 6. + 0.5 * 3. / 4.);
+    assert_eq!(evaluator.evaluate(&victory, &config), f64::INFINITY);
+  }
+
+
+  // Testing EvaluatorBuilder
+
+  #[test]
+  pub fn test_evaluator_builder_errors() {
+    assert_eq!(Evaluator::builder().build().unwrap_err(), EvaluatorError::NoHeuristics);
+    assert_eq!(Evaluator::builder().heuristic(Emptiness, -1.).build().unwrap_err(), EvaluatorError::InvalidWeight(-1.));
+    assert_eq!(
+      Evaluator::builder().heuristic(Emptiness, 1.).aggregation(Aggregation::CobbDouglas { degree: 0. }).build().unwrap_err(),
+      EvaluatorError::InvalidDegree(0.)
+    );
+    assert!(matches!(
+      Evaluator::builder().heuristic(Emptiness, 1.).victory_value(Some(f64::NAN)).build().unwrap_err(),
+      EvaluatorError::InvalidVictoryValue(value) if value.is_nan()
+    ));
+  }
+
+}
@@ -0,0 +1,618 @@
+//! # `expectimax` module
+//!
+//! Contains the depth-limited expectimax search of the optimal move.
+//! The player maximises the value at max nodes, while chance nodes average the values over every empty cell and 2 or 4 spawn,
+//! each cell being equally likely and the tiles weighted by the spawn probabilities of the game. The leaves are valued by an `Evaluator`.
+//! The values of max nodes are cached in a `TranspositionTable` when no spawn sequence below them fell under the probability cutoff,
+//! so that a value cut short on an unlikely path is never reused where the full search was expected.
+//! The search plays on bitboards, stacked with their own row table, while the tiles fit in them, and on encoded grids otherwise.
+//! Runtime-sized boards are searched the same way by `board_expectimax_move()`, without transposition table, so less deep by default.
+
+use std::convert::TryFrom;
+use std::time::Instant;
+
+use crate::ai::evaluation::Evaluator;
+use crate::ai::transposition::{TranspositionTable, TableConfig};
+use crate::game::core::*;
+use crate::game::board::{self, Board, GameBoard};
+use crate::game::config::GameConfig;
+use crate::game::moves;
+use crate::game::moves::{PlayerMove, MoveSet, MovesTable, BitboardMovesTable};
+
+
+//------------------------------------------------
+// Types and Definitions
+//------------------------------------------------
+
+/// Default number of player moves looked ahead.
+pub const DEFAULT_EXPECTIMAX_DEPTH: usize = 3;
+
+/// Default number of player moves looked ahead on runtime-sized boards, which are searched without transposition table.
+pub const DEFAULT_BOARD_EXPECTIMAX_DEPTH: usize = 2;
+
+// Spawn sequences less likely than this are valued as leaves instead of being expanded further
+const PROBABILITY_CUTOFF: f64 = 1e-4;
+
+// The parameters shared by the nodes of a search
+struct Search<'a> {
+  deadline: Option<Instant>,
+  precomputed_moves: &'a MovesTable,
+  bitboard_moves: &'a BitboardMovesTable,
+  config: &'a GameConfig,
+  evaluator: &'a Evaluator,
+  table: &'a mut TranspositionTable,
+}
+
+
+//------------------------------------------------
+// Traits
+//------------------------------------------------
+
+// Grid representations the search plays on
+trait SearchGrid: Clone {
+
+  // Moves changing the grid
+  fn legal_moves(&self, search: &Search) -> MoveSet;
+
+  // Grid after the move, `None` if the representation can't hold a merged tile
+  fn stacked(&self, player_move: PlayerMove, search: &Search) -> Option<Self>;
+
+  // Grid with `tile` spawned in the empty cell (`row`, `col`), `None` if the cell is occupied
+  fn spawned(&self, row: usize, col: usize, tile: EntryType) -> Option<Self>;
+
+  fn get_zeros(&self) -> usize;
+
+  // Number of rows and columns
+  fn size(&self) -> (usize, usize);
+
+  // The same grid encoded, to look it up in the transposition table, `None` for the boards the table doesn't hold
+  fn encoded(&self) -> Option<Grid<EncodedGrid>>;
+
+  // Utility of the grid
+  fn evaluate(&self, search: &Search) -> f64;
+
+}
+
+
+//------------------------------------------------
+// Implementations
+//------------------------------------------------
+
+// Inherent
+
+impl<'a> Search<'a> {
+
+  /// Picks the move of highest value `depth` moves ahead, `None` if the deadline expired before the search completed.
+  /// Plays on a bitboard whenever the tiles of `grid` fit in one.
+  fn root_move(&mut self, grid: &Grid<EncodedGrid>, depth: usize) -> Option<Option<PlayerMove>> {
+    match Grid::<BitboardGrid>::try_from(*grid) {
+      Ok(bitboard) => self.best_move(&bitboard, depth),
+      Err(_) => self.best_move(grid, depth),
+    }
+  }
+
+  fn best_move<G: SearchGrid>(&mut self, grid: &G, depth: usize) -> Option<Option<PlayerMove>> {
+
+    let mut optimal_move = None;
+    let mut max_value = -f64::INFINITY;
+
+    for direction in grid.legal_moves(self) {
+      let (value, _) = self.move_value(grid, direction, depth.saturating_sub(1), 1.)?;
+
+      if value > max_value || optimal_move.is_none() {
+        max_value = value;
+        optimal_move = Some(direction);
+      }
+    }
+
+    Some(optimal_move)
+  }
+
+  /// Value of a max node, where the player picks the move of highest value with `depth` moves left,
+  /// along with whether it is exact, i.e. no spawn sequence below it was cut off.
+  /// Returns `None` once the deadline expires, leaving out of the table the values not completed.
+  fn max_value<G: SearchGrid>(&mut self, grid: &G, depth: usize, probability: f64) -> Option<(f64, bool)> {
+
+    let encoded = grid.encoded();
+
+    // only exact values are stored
+    if let Some(value) = encoded.and_then(|encoded| self.table.get(&encoded, depth)) {
+      return Some((value, true));
+    }
+
+    if self.deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+      return None;
+    }
+
+    let legal_moves = grid.legal_moves(self);
+
+    // leaf, or game over with no move to choose from
+    let (value, exact) = if depth == 0 || legal_moves.is_empty() {
+      (grid.evaluate(self), true)
+    } else {
+      let mut max_value = -f64::INFINITY;
+      let mut exact = true;
+
+      for direction in legal_moves {
+        let (value, exact_value) = self.move_value(grid, direction, depth - 1, probability)?;
+
+        max_value = max_value.max(value);
+        exact &= exact_value;
+      }
+
+      (max_value, exact)
+    };
+
+    if let (true, Some(encoded)) = (exact, encoded) {
+      self.table.insert(&encoded, depth, value);
+    }
+
+    Some((value, exact))
+  }
+
+  /// Value of playing `direction` on `grid`, moving to the encoded grid if the move merges past the largest tile of the representation.
+  fn move_value<G: SearchGrid>(&mut self, grid: &G, direction: PlayerMove, depth: usize, probability: f64) -> Option<(f64, bool)> {
+    match grid.stacked(direction, self) {
+      Some(child) => self.chance_value(&child, depth, probability),
+      None => {
+        let encoded = grid.encoded().expect("only bitboards overflow, and they are encoded");
+        let move_result = moves::process_grid_stacking(direction, &encoded, self.precomputed_moves);
+        self.chance_value(move_result.get_new_grid(), depth, probability)
+      },
+    }
+  }
+
+  /// Value of a chance node, the grid after a move and before its spawn: the average of the values of every possible spawn,
+  /// along with whether it is exact, i.e. no spawn sequence was cut off.
+  /// Returns `None` once the deadline expires.
+  fn chance_value<G: SearchGrid>(&mut self, grid: &G, depth: usize, probability: f64) -> Option<(f64, bool)> {
+
+    let empty_tiles = grid.get_zeros();
+
+    // an effective move always leaves an empty tile, this only guards the division
+    if empty_tiles == 0 {
+      return Some((grid.evaluate(self), true));
+    }
+
+    let prob_tile2 = self.config.get_prob_tile2();
+    let mut value = 0.;
+    let mut exact = true;
+    let (rows, cols) = grid.size();
+
+    for k in 0..(rows * cols) {
+      for &(tile, tile_probability) in [(2, prob_tile2), (4, 1. - prob_tile2)].iter() {
+
+        // a tile that never spawns has no weight, and would turn an infinite utility into NaN
+        if tile_probability == 0. {
+          continue;
+        }
+
+        let child = match grid.spawned(k / cols, k % cols, tile) {
+          Some(child) => child,
+          None => break,
+        };
+        let weight = tile_probability / empty_tiles as f64;
+
+        // a cut off spawn with moves left is valued early, and makes the value inexact
+        let (child_value, exact_child) = if probability * weight < PROBABILITY_CUTOFF {
+          (child.evaluate(self), depth == 0)
+        } else {
+          self.max_value(&child, depth, probability * weight)?
+        };
+
+        value += weight * child_value;
+        exact &= exact_child;
+      }
+    }
+
+    Some((value, exact))
+  }
+
+}
+
+
+// SearchGrid
+
+impl SearchGrid for Grid<EncodedGrid> {
+
+  fn legal_moves(&self, search: &Search) -> MoveSet {
+    search.precomputed_moves.legal_moves(self)
+  }
+
+  fn stacked(&self, player_move: PlayerMove, search: &Search) -> Option<Self> {
+    Some(*moves::process_grid_stacking(player_move, self, search.precomputed_moves).get_new_grid())
+  }
+
+  fn spawned(&self, row: usize, col: usize, tile: EntryType) -> Option<Self> {
+    if self.get_exponent(row, col) != 0 {
+      return None;
+    }
+
+    let mut child = *self;
+    child.set_tile(row, col, tile);
+
+    Some(child)
+  }
+
+  fn get_zeros(&self) -> usize { Grid::<EncodedGrid>::get_zeros(self) }
+
+  fn size(&self) -> (usize, usize) { (GRID_SIDE, GRID_SIDE) }
+
+  fn encoded(&self) -> Option<Grid<EncodedGrid>> { Some(*self) }
+
+  fn evaluate(&self, search: &Search) -> f64 { search.evaluator.evaluate(self, search.config) }
+
+}
+
+impl SearchGrid for Grid<BitboardGrid> {
+
+  fn legal_moves(&self, search: &Search) -> MoveSet {
+    search.bitboard_moves.legal_moves(self)
+  }
+
+  fn stacked(&self, player_move: PlayerMove, search: &Search) -> Option<Self> {
+    moves::process_bitboard_stacking(player_move, self, search.bitboard_moves).ok()
+  }
+
+  fn spawned(&self, row: usize, col: usize, tile: EntryType) -> Option<Self> {
+    if self.get_exponent(row, col) != 0 {
+      return None;
+    }
+
+    let mut child = *self;
+    child.set_tile(row, col, tile);
+
+    Some(child)
+  }
+
+  fn get_zeros(&self) -> usize { Grid::<BitboardGrid>::get_zeros(self) }
+
+  fn size(&self) -> (usize, usize) { (GRID_SIDE, GRID_SIDE) }
+
+  fn encoded(&self) -> Option<Grid<EncodedGrid>> { Some(self.to_encoded()) }
+
+  fn evaluate(&self, search: &Search) -> f64 { search.evaluator.evaluate(&self.to_encoded(), search.config) }
+
+}
+
+impl SearchGrid for Board {
+
+  fn legal_moves(&self, _search: &Search) -> MoveSet { GameBoard::legal_moves(self) }
+
+  fn stacked(&self, player_move: PlayerMove, _search: &Search) -> Option<Self> {
+    Some(board::process_board_stacking(player_move, self).get_new_board().clone())
+  }
+
+  fn spawned(&self, row: usize, col: usize, tile: EntryType) -> Option<Self> {
+    if self.get_tile(row, col) != 0 {
+      return None;
+    }
+
+    let mut child = self.clone();
+    child.set_tile(row, col, tile);
+
+    Some(child)
+  }
+
+  fn get_zeros(&self) -> usize { Board::get_zeros(self) }
+
+  fn size(&self) -> (usize, usize) { (self.get_rows(), self.get_cols()) }
+
+  fn encoded(&self) -> Option<Grid<EncodedGrid>> { None }
+
+  fn evaluate(&self, search: &Search) -> f64 { search.evaluator.evaluate(self, search.config) }
+
+}
+
+
+//------------------------------------------------
+// Functions
+//------------------------------------------------
+
+/// Returns the move maximising the expected utility `depth` player moves ahead, `None` if no move changes the grid.
+pub fn expectimax_move(
+  grid: &Grid<EncodedGrid>,
+  depth: usize,
+  precomputed_moves: &MovesTable,
+  config: &GameConfig,
+  evaluator: &Evaluator,
+  table: &mut TranspositionTable,
+) -> Option<PlayerMove> {
+  let bitboard_moves = moves::precomputed_bitboard_moves();
+  let mut search = Search { deadline: None, precomputed_moves, bitboard_moves, config, evaluator, table };
+
+  search.root_move(grid, depth).flatten()
+}
+
+/// Searches 1, 2, ... up to `max_depth` moves ahead until `deadline`, and returns the move of the deepest search completed in time.
+/// The search 1 move ahead always completes, so a move is returned whenever one changes the grid.
+/// The values of the earlier searches are in the transposition table, which speeds up the deeper ones.
+pub fn iterative_deepening_move(
+  grid: &Grid<EncodedGrid>,
+  max_depth: usize,
+  deadline: Instant,
+  precomputed_moves: &MovesTable,
+  config: &GameConfig,
+  evaluator: &Evaluator,
+  table: &mut TranspositionTable,
+) -> Option<PlayerMove> {
+
+  let mut optimal_move = expectimax_move(grid, 1, precomputed_moves, config, evaluator, table);
+  let bitboard_moves = moves::precomputed_bitboard_moves();
+  let mut search = Search { deadline: Some(deadline), precomputed_moves, bitboard_moves, config, evaluator, table };
+
+  for depth in 2..=max_depth {
+    match search.root_move(grid, depth) {
+      Some(deeper_move) => optimal_move = deeper_move,
+      None => break,
+    }
+  }
+
+  optimal_move
+}
+
+
+/// Returns the move maximising the expected utility `depth` player moves ahead on a runtime-sized board, `None` if no move changes it.
+/// With a deadline the search deepens 1, 2, ... up to `depth` moves ahead as `iterative_deepening_move()` does.
+/// Boards are not cached in the transposition table, so every search starts anew.
+pub fn board_expectimax_move(
+  board: &Board,
+  depth: usize,
+  deadline: Option<Instant>,
+  config: &GameConfig,
+  evaluator: &Evaluator,
+) -> Option<PlayerMove> {
+  let mut table = TranspositionTable::new(TableConfig::disabled());
+  let precomputed_moves = moves::precomputed_moves();
+  let bitboard_moves = moves::precomputed_bitboard_moves();
+  let mut search = Search { deadline: None, precomputed_moves, bitboard_moves, config, evaluator, table: &mut table };
+
+  let deadline = match deadline {
+    Some(deadline) => deadline,
+    None => return search.best_move(board, depth).flatten(),
+  };
+
+  let mut optimal_move = search.best_move(board, 1).flatten();
+  search.deadline = Some(deadline);
+
+  for depth in 2..=depth {
+    match search.best_move(board, depth) {
+      Some(deeper_move) => optimal_move = deeper_move,
+      None => break,
+    }
+  }
+
+  optimal_move
+}
+
+
+//------------------------------------------------
+// Unit tests
+//------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+
+  use super::*;
+  use crate::ai::core::utility;
+  use crate::ai::transposition::TableConfig;
+  use crate::game::moves::precomputed_moves;
+
+  fn no_table() -> TranspositionTable {
+    TranspositionTable::new(TableConfig::disabled())
+  }
+
+  fn search<'a>(config: &'a GameConfig, evaluator: &'a Evaluator, table: &'a mut TranspositionTable) -> Search<'a> {
+    Search { deadline: None, precomputed_moves: precomputed_moves(), bitboard_moves: moves::precomputed_bitboard_moves(), config, evaluator, table }
+  }
+
+  // Testing chance_value()
+
+  #[test]
+  pub fn test_chance_value_averages_spawns() {
+    let config = GameConfig::default();
+    let grid = Grid::from_decoded(&[
+      [2, 4, 8, 16],
+      [4, 8, 16, 32],
+      [8, 16, 32, 64],
+      [16, 32, 0, 0],
+    ]);
+
+    let prob_tile2 = config.get_prob_tile2();
+    let mut expected = 0.;
+
+    for &(i, j) in [(3, 2), (3, 3)].iter() {
+      for &(tile, tile_probability) in [(2, prob_tile2), (4, 1. - prob_tile2)].iter() {
+        let mut child = grid;
+        child.set_tile(i, j, tile);
+        expected += tile_probability / 2. * utility(&child, &config);
+      }
+    }
+
+    let (value, exact) = search(&config, &Evaluator::default(), &mut no_table()).chance_value(&grid, 0, 1.).unwrap();
+
+    assert!((value - expected).abs() < 1e-12);
+    assert!(exact);
+  }
+
+
+  // Testing max_value()
+
+  #[test]
+  pub fn test_max_value_takes_best_move() {
+    let config = GameConfig::default();
+    let grid = Grid::from_decoded(&[
+      [2, 4, 8, 16],
+      [4, 8, 16, 32],
+      [8, 16, 32, 64],
+      [16, 32, 2, 0],
+    ]);
+
+    let evaluator = Evaluator::default();
+    let mut table = no_table();
+    let mut search = search(&config, &evaluator, &mut table);
+
+    let best = precomputed_moves().legal_moves(&grid).iter()
+      .map(|direction| search.chance_value(moves::process_grid_stacking(direction, &grid, precomputed_moves()).get_new_grid(), 0, 1.).unwrap().0)
+      .fold(-f64::INFINITY, f64::max);
+
+    assert_eq!(search.max_value(&grid, 1, 1.), Some((best, true)));
+    assert_eq!(search.max_value(&grid, 0, 1.), Some((utility(&grid, &config), true)));
+  }
+
+
+  #[test]
+  pub fn test_max_value_cut_off_not_stored() {
+    let config = GameConfig::default();
+    let grid = Grid::from_decoded(&[
+      [0, 2, 4, 8],
+      [0, 0, 4, 16],
+      [2, 0, 8, 32],
+      [0, 4, 16, 64],
+    ]);
+
+    let evaluator = Evaluator::default();
+    let mut table = TranspositionTable::new(TableConfig::default());
+
+    // reached by an unlikely path, every spawn below is cut off and the value is inexact
+    let (cut_off, exact) = search(&config, &evaluator, &mut table).max_value(&grid, 2, PROBABILITY_CUTOFF).unwrap();
+
+    assert!(!exact);
+    assert_eq!(table.get(&grid, 2), None);
+
+    // the full search is exact, stored, and differs from the cut off value
+    let (full, exact) = search(&config, &evaluator, &mut table).max_value(&grid, 2, 1.).unwrap();
+
+    assert!(exact);
+    assert_ne!(full, cut_off);
+    assert_eq!(table.get(&grid, 2), Some(full));
+  }
+
+
+  #[test]
+  pub fn test_max_value_bitboard_matches_encoded() {
+    let config = GameConfig::default();
+    let evaluator = Evaluator::default();
+
+    // the largest tiles of the bitboard merge past what it can hold, the search goes on with the encoded grid
+    let grid = Grid::from_decoded(&[
+      [32768, 32768, 2, 0],
+      [4, 8, 16, 0],
+      [2, 0, 0, 0],
+      [0, 0, 0, 2],
+    ]);
+    let bitboard = Grid::<BitboardGrid>::try_from(grid).unwrap();
+
+    assert!(bitboard.stacked(PlayerMove::Left, &search(&config, &evaluator, &mut no_table())).is_none());
+    assert_eq!(
+      search(&config, &evaluator, &mut no_table()).max_value(&bitboard, 2, 1.),
+      search(&config, &evaluator, &mut no_table()).max_value(&grid, 2, 1.),
+    );
+  }
+
+
+  // Testing expectimax_move()
+
+  #[test]
+  pub fn test_expectimax_move_game_over() {
+    let grid = Grid::from_decoded(&[
+      [32, 64, 8, 32],
+      [8, 16, 4, 16],
+      [2, 8, 16, 2],
+      [8, 4, 8, 4],
+    ]);
+
+    assert_eq!(expectimax_move(&grid, DEFAULT_EXPECTIMAX_DEPTH, precomputed_moves(), &GameConfig::default(), &Evaluator::default(), &mut no_table()), None);
+  }
+
+  #[test]
+  pub fn test_expectimax_move_takes_victory() {
+    let grid = Grid::from_decoded(&[
+      [1024, 1024, 2, 4],
+      [4, 8, 16, 32],
+      [8, 16, 32, 64],
+      [2, 4, 8, 0],
+    ]);
+
+    let optimal_move = expectimax_move(&grid, 2, precomputed_moves(), &GameConfig::default(), &Evaluator::default(), &mut no_table());
+
+    assert!(matches!(optimal_move, Some(PlayerMove::Left) | Some(PlayerMove::Right)), "{:?}", optimal_move);
+  }
+
+
+  // Testing the transposition table
+
+  #[test]
+  pub fn test_expectimax_move_with_table() {
+    let config = GameConfig::default();
+    let grid = Grid::from_decoded(&[
+      [0, 2, 4, 8],
+      [0, 0, 4, 16],
+      [2, 0, 8, 32],
+      [0, 4, 16, 64],
+    ]);
+
+    let mut table = TranspositionTable::new(TableConfig::default());
+    let optimal_move = expectimax_move(&grid, 2, precomputed_moves(), &config, &Evaluator::default(), &mut table);
+    let stats = table.get_stats();
+
+    // transpositions are found within a search, and the same search is answered from the table
+    assert_eq!(optimal_move, expectimax_move(&grid, 2, precomputed_moves(), &config, &Evaluator::default(), &mut no_table()));
+    assert!(stats.get_hits() > 0);
+
+    expectimax_move(&grid, 2, precomputed_moves(), &config, &Evaluator::default(), &mut table);
+    assert_eq!(table.get_stats().get_misses(), stats.get_misses());
+  }
+
+
+  // Testing iterative_deepening_move()
+
+  #[test]
+  pub fn test_iterative_deepening_expired_deadline() {
+    let config = GameConfig::default();
+    let grid = Grid::from_decoded(&[
+      [0, 2, 4, 8],
+      [0, 0, 4, 16],
+      [2, 0, 8, 32],
+      [0, 4, 16, 64],
+    ]);
+
+    // with no time left only the search 1 move ahead completes
+    let optimal_move = iterative_deepening_move(&grid, 4, Instant::now(), precomputed_moves(), &config, &Evaluator::default(), &mut no_table());
+
+    assert_eq!(optimal_move, expectimax_move(&grid, 1, precomputed_moves(), &config, &Evaluator::default(), &mut no_table()));
+    let (evaluator, mut table) = (Evaluator::default(), no_table());
+    let mut expired = Search { deadline: Some(Instant::now()), ..search(&config, &evaluator, &mut table) };
+    assert!(expired.root_move(&grid, 2).is_none());
+  }
+
+  #[test]
+  pub fn test_iterative_deepening_completes_in_time() {
+    let config = GameConfig::default();
+    let grid = Grid::from_decoded(&[
+      [0, 2, 4, 8],
+      [0, 0, 4, 16],
+      [2, 0, 8, 32],
+      [0, 4, 16, 64],
+    ]);
+
+    let deadline = Instant::now() + std::time::Duration::from_secs(60);
+    let mut table = TranspositionTable::new(TableConfig::default());
+    let optimal_move = iterative_deepening_move(&grid, 2, deadline, precomputed_moves(), &config, &Evaluator::default(), &mut table);
+
+    assert_eq!(optimal_move, expectimax_move(&grid, 2, precomputed_moves(), &config, &Evaluator::default(), &mut no_table()));
+  }
+
+  #[test]
+  pub fn test_iterative_deepening_game_over() {
+    let grid = Grid::from_decoded(&[
+      [32, 64, 8, 32],
+      [8, 16, 4, 16],
+      [2, 8, 16, 2],
+      [8, 4, 8, 4],
+    ]);
+
+    assert_eq!(iterative_deepening_move(&grid, 3, Instant::now(), precomputed_moves(), &GameConfig::default(), &Evaluator::default(), &mut no_table()), None);
+  }
+
+}
@@ -0,0 +1,643 @@
+//! # `mcts` module
+//!
+//! Contains the Monte Carlo Tree Search agent, an alternative to the heuristic tree searches.
+//! The tree alternates decision nodes, where the player picks a move by UCT, and chance nodes, where a spawn is sampled.
+//! Each iteration descends the tree, expands an untried move, plays a rollout with `process_grid_stacking()` and backs up
+//! the score gained from the root, so with random rollouts the agent needs no heuristic at all.
+//! The move returned is the most visited one at the root.
+
+use std::{error, fmt, fmt::Display};
+use std::time::{Duration, Instant};
+
+use rand::Rng;
+
+use crate::ai::evaluation::Evaluator;
+use crate::game::core::*;
+use crate::game::config::GameConfig;
+use crate::game::moves;
+use crate::game::moves::{PlayerMove, MoveSet, MovesTable};
+
+
+//------------------------------------------------
+// Types and Definitions
+//------------------------------------------------
+
+/// Default exploration constant of UCT, the theoretical sqrt(2) for rewards in [0, 1].
+pub const DEFAULT_EXPLORATION: f64 = std::f64::consts::SQRT_2;
+
+/// Default number of iterations of a search.
+pub const DEFAULT_MCTS_ITERATIONS: usize = 2000;
+
+/// Default number of moves of a rollout.
+pub const DEFAULT_ROLLOUT_MOVES: usize = 40;
+
+/// Describes how the moves of a rollout are picked.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub enum RolloutPolicy {
+  /// A random legal move, free of any heuristic.
+  #[default]
+  Random,
+  /// The legal move whose grid is valued highest by the `Evaluator` of the search.
+  Greedy,
+}
+
+/// Describes how the spawns are drawn at chance nodes.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub enum ChanceSampling {
+  /// Each visit draws a new spawn with the probabilities of the game.
+  #[default]
+  Full,
+  /// Each chance node keeps at most the given number of distinct spawns, visited uniformly once all drawn.
+  Limited(usize),
+}
+
+/// Describes when a search stops.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum MctsBudget {
+  Iterations(usize),
+  Time(Duration),
+}
+
+/// The parameters of the MCTS agent.
+/// Built with `MctsConfig::builder()`, any parameter not set keeps its default.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct MctsConfig {
+  exploration: f64,
+  rollout_policy: RolloutPolicy,
+  rollout_moves: usize,
+  budget: MctsBudget,
+  chance_sampling: ChanceSampling,
+  seed: u64,
+}
+
+/// Builder of an `MctsConfig`, validated by `build()`.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct MctsConfigBuilder {
+  config: MctsConfig,
+}
+
+/// Errors raised when building an invalid `MctsConfig`.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum MctsConfigError {
+  InvalidExploration(f64),
+  InvalidBudget(MctsBudget),
+  InvalidChanceSampling(usize),
+}
+
+// Outcome of a chance node: the row, column and value of the spawned tile
+type Spawn = (usize, usize, EntryType);
+
+/// Node of the search tree, stored in the arena of the `SearchTree`.
+struct TreeNode {
+  grid: Grid<EncodedGrid>,
+  visits: u32,
+  total_reward: f64,
+  kind: NodeKind,
+}
+
+enum NodeKind {
+  /// Grid where the player moves, with the moves not expanded yet.
+  Decision { untried: MoveSet, children: Vec<(PlayerMove, usize)> },
+  /// Grid after a move scoring `delta_score`, before its spawn.
+  Chance { delta_score: u64, children: Vec<(Spawn, usize)> },
+}
+
+struct SearchTree<'a> {
+  nodes: Vec<TreeNode>,
+  max_reward: f64,
+  mcts_config: &'a MctsConfig,
+  precomputed_moves: &'a MovesTable,
+  config: &'a GameConfig,
+  evaluator: &'a Evaluator,
+}
+
+
+//------------------------------------------------
+// Implementations
+//------------------------------------------------
+
+// Inherent
+
+impl MctsConfig {
+
+  /// Returns a builder starting from the default parameters.
+  pub fn builder() -> MctsConfigBuilder {
+    MctsConfigBuilder {
+      config: MctsConfig::default(),
+    }
+  }
+
+  // Getters
+  pub fn get_exploration(&self) -> f64 { self.exploration }
+  pub fn get_rollout_policy(&self) -> RolloutPolicy { self.rollout_policy }
+  pub fn get_rollout_moves(&self) -> usize { self.rollout_moves }
+  pub fn get_budget(&self) -> MctsBudget { self.budget }
+  pub fn get_chance_sampling(&self) -> ChanceSampling { self.chance_sampling }
+  pub fn get_seed(&self) -> u64 { self.seed }
+
+  /// Checks that the parameters describe a search that can pick a move.
+  pub fn validate(&self) -> Result<(), MctsConfigError> {
+    if !(self.exploration >= 0. && self.exploration.is_finite()) {
+      return Err(MctsConfigError::InvalidExploration(self.exploration));
+    }
+
+    match self.budget {
+      MctsBudget::Iterations(0) => return Err(MctsConfigError::InvalidBudget(self.budget)),
+      MctsBudget::Time(time) if time.is_zero() => return Err(MctsConfigError::InvalidBudget(self.budget)),
+      _ => {},
+    }
+
+    if let ChanceSampling::Limited(0) = self.chance_sampling {
+      return Err(MctsConfigError::InvalidChanceSampling(0));
+    }
+
+    Ok(())
+  }
+
+}
+
+impl MctsConfigBuilder {
+
+  /// Sets the exploration constant of UCT, weighing the less visited moves against the best ones.
+  pub fn exploration(mut self, exploration: f64) -> Self {
+    self.config.exploration = exploration;
+    self
+  }
+
+  /// Sets how the moves of a rollout are picked.
+  pub fn rollout_policy(mut self, rollout_policy: RolloutPolicy) -> Self {
+    self.config.rollout_policy = rollout_policy;
+    self
+  }
+
+  /// Sets how many moves a rollout plays at most, 0 valuing a new node by the score of its path only.
+  pub fn rollout_moves(mut self, rollout_moves: usize) -> Self {
+    self.config.rollout_moves = rollout_moves;
+    self
+  }
+
+  /// Sets when a search stops.
+  pub fn budget(mut self, budget: MctsBudget) -> Self {
+    self.config.budget = budget;
+    self
+  }
+
+  /// Sets how the spawns are drawn at chance nodes.
+  pub fn chance_sampling(mut self, chance_sampling: ChanceSampling) -> Self {
+    self.config.chance_sampling = chance_sampling;
+    self
+  }
+
+  /// Sets the seed of the random numbers of the searches, for reproducible games.
+  pub fn seed(mut self, seed: u64) -> Self {
+    self.config.seed = seed;
+    self
+  }
+
+  /// Builds the config, checking its parameters.
+  pub fn build(self) -> Result<MctsConfig, MctsConfigError> {
+    self.config.validate()?;
+
+    Ok(self.config)
+  }
+
+}
+
+impl<'a> SearchTree<'a> {
+
+  fn new(grid: &Grid<EncodedGrid>, mcts_config: &'a MctsConfig, precomputed_moves: &'a MovesTable, config: &'a GameConfig, evaluator: &'a Evaluator) -> Self {
+    let mut tree = SearchTree {
+      nodes: Vec::new(),
+      max_reward: 0.,
+      mcts_config,
+      precomputed_moves,
+      config,
+      evaluator,
+    };
+
+    tree.add_decision_node(grid);
+    tree
+  }
+
+  fn add_decision_node(&mut self, grid: &Grid<EncodedGrid>) -> usize {
+    self.nodes.push(TreeNode {
+      grid: *grid,
+      visits: 0,
+      total_reward: 0.,
+      kind: NodeKind::Decision { untried: self.precomputed_moves.legal_moves(grid), children: Vec::new() },
+    });
+
+    self.nodes.len() - 1
+  }
+
+  fn add_chance_node(&mut self, grid: &Grid<EncodedGrid>, delta_score: u64) -> usize {
+    self.nodes.push(TreeNode {
+      grid: *grid,
+      visits: 0,
+      total_reward: 0.,
+      kind: NodeKind::Chance { delta_score, children: Vec::new() },
+    });
+
+    self.nodes.len() - 1
+  }
+
+  /// Runs an iteration: selection, expansion, rollout and backup of the score gained from the root.
+  fn iterate<R: Rng>(&mut self, rng: &mut R) {
+    let mut path = vec![0];
+    let mut node = 0;
+    let mut reward = 0.;
+
+    loop {
+      let untried = match &self.nodes[node].kind {
+        NodeKind::Decision { untried, .. } => *untried,
+        NodeKind::Chance { .. } => unreachable!("the descent crosses chance nodes to one of their spawns"),
+      };
+
+      // expansion of a random untried move, then its spawn, ending the descent
+      if !untried.is_empty() {
+        let player_move = untried.iter().nth(rng.gen_range(0..untried.len())).unwrap();
+        let move_result = moves::process_grid_stacking(player_move, &self.nodes[node].grid, self.precomputed_moves);
+        let chance = self.add_chance_node(move_result.get_new_grid(), move_result.get_delta_score());
+
+        if let NodeKind::Decision { untried, children } = &mut self.nodes[node].kind {
+          untried.remove(player_move);
+          children.push((player_move, chance));
+        }
+
+        reward += move_result.get_delta_score() as f64;
+        node = self.spawn_child(chance, rng);
+        path.push(chance);
+        path.push(node);
+        break;
+      }
+
+      // no child, game over
+      let chance = match self.select_child(node) {
+        Some(chance) => chance,
+        None => break,
+      };
+
+      if let NodeKind::Chance { delta_score, .. } = self.nodes[chance].kind {
+        reward += delta_score as f64;
+      }
+
+      node = self.spawn_child(chance, rng);
+      path.push(chance);
+      path.push(node);
+    }
+
+    reward += self.rollout(&self.nodes[node].grid, rng);
+    self.max_reward = self.max_reward.max(reward);
+
+    for &index in path.iter() {
+      self.nodes[index].visits += 1;
+      self.nodes[index].total_reward += reward;
+    }
+  }
+
+  /// Picks the child of a decision node maximising UCT, the mean rewards normalised by the largest reward seen.
+  fn select_child(&self, node: usize) -> Option<usize> {
+    let parent_visits = self.nodes[node].visits.max(1) as f64;
+    let scale = if self.max_reward > 0. { self.max_reward } else { 1. };
+    let mut best = (None, -f64::INFINITY);
+
+    if let NodeKind::Decision { children, .. } = &self.nodes[node].kind {
+      for &(_, child) in children.iter() {
+        let visits = self.nodes[child].visits.max(1) as f64;
+        let uct = self.nodes[child].total_reward / visits / scale
+          + self.mcts_config.get_exploration() * (parent_visits.ln() / visits).sqrt();
+
+        if uct > best.1 {
+          best = (Some(child), uct);
+        }
+      }
+    }
+
+    best.0
+  }
+
+  /// Returns the decision node reached by a spawn on the grid of a chance node, drawn according to the chance sampling.
+  fn spawn_child<R: Rng>(&mut self, chance: usize, rng: &mut R) -> usize {
+    let grid = self.nodes[chance].grid;
+    let limit = match self.mcts_config.get_chance_sampling() {
+      ChanceSampling::Full => usize::MAX,
+      ChanceSampling::Limited(limit) => limit,
+    };
+
+    if let NodeKind::Chance { children, .. } = &self.nodes[chance].kind {
+      if children.len() >= limit {
+        return children[rng.gen_range(0..children.len())].1;
+      }
+    }
+
+    let spawn = random_spawn(&grid, self.config.get_prob_tile2(), rng);
+
+    if let NodeKind::Chance { children, .. } = &self.nodes[chance].kind {
+      if let Some(&(_, child)) = children.iter().find(|(drawn, _)| *drawn == spawn) {
+        return child;
+      }
+    }
+
+    let mut child_grid = grid;
+    child_grid.set_tile(spawn.0, spawn.1, spawn.2);
+    let child = self.add_decision_node(&child_grid);
+
+    if let NodeKind::Chance { children, .. } = &mut self.nodes[chance].kind {
+      children.push((spawn, child));
+    }
+
+    child
+  }
+
+  /// Plays up to `rollout_moves` moves from the grid with the rollout policy, and returns the score gained.
+  fn rollout<R: Rng>(&self, grid: &Grid<EncodedGrid>, rng: &mut R) -> f64 {
+    let mut grid = *grid;
+    let mut score = 0;
+
+    for _ in 0..self.mcts_config.get_rollout_moves() {
+      let legal_moves = self.precomputed_moves.legal_moves(&grid);
+
+      if legal_moves.is_empty() {
+        break;
+      }
+
+      let move_result = match self.mcts_config.get_rollout_policy() {
+        RolloutPolicy::Random => {
+          let player_move = legal_moves.iter().nth(rng.gen_range(0..legal_moves.len())).unwrap();
+          moves::process_grid_stacking(player_move, &grid, self.precomputed_moves)
+        },
+        RolloutPolicy::Greedy => legal_moves.iter()
+          .map(|player_move| moves::process_grid_stacking(player_move, &grid, self.precomputed_moves))
+          .max_by(|a, b| self.evaluator.evaluate(a.get_new_grid(), self.config).total_cmp(&self.evaluator.evaluate(b.get_new_grid(), self.config)))
+          .unwrap(),
+      };
+
+      score += move_result.get_delta_score();
+      grid = *move_result.get_new_grid();
+
+      let (i, j, tile) = random_spawn(&grid, self.config.get_prob_tile2(), rng);
+      grid.set_tile(i, j, tile);
+    }
+
+    score as f64
+  }
+
+  /// Returns the most visited move at the root, `None` if no move was expanded.
+  fn best_move(&self) -> Option<PlayerMove> {
+    match &self.nodes[0].kind {
+      NodeKind::Decision { children, .. } => children.iter()
+        .max_by_key(|&&(_, child)| self.nodes[child].visits)
+        .map(|&(player_move, _)| player_move),
+      NodeKind::Chance { .. } => None,
+    }
+  }
+
+}
+
+
+// Default
+
+impl Default for MctsConfig {
+
+  fn default() -> Self {
+    MctsConfig {
+      exploration: DEFAULT_EXPLORATION,
+      rollout_policy: RolloutPolicy::default(),
+      rollout_moves: DEFAULT_ROLLOUT_MOVES,
+      budget: MctsBudget::Iterations(DEFAULT_MCTS_ITERATIONS),
+      chance_sampling: ChanceSampling::default(),
+      seed: 0,
+    }
+  }
+
+}
+
+
+// Display
+
+impl Display for MctsConfigError {
+
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      MctsConfigError::InvalidExploration(exploration) => write!(f, "exploration constant {} is not a finite non-negative number", exploration),
+      MctsConfigError::InvalidBudget(budget) => write!(f, "budget {:?} allows no iteration", budget),
+      MctsConfigError::InvalidChanceSampling(limit) => write!(f, "chance nodes limited to {} spawns can't be crossed", limit),
+    }
+  }
+
+}
+
+
+// Error
+
+impl error::Error for MctsConfigError {}
+
+
+//------------------------------------------------
+// Functions
+//------------------------------------------------
+
+/// Returns the move picked by MCTS on the grid, `None` if no move changes the grid.
+/// The search stops at the end of the budget of `mcts_config`, or earlier at `deadline` if any, after at least one iteration.
+pub fn mcts_move<R: Rng>(
+  grid: &Grid<EncodedGrid>,
+  mcts_config: &MctsConfig,
+  deadline: Option<Instant>,
+  precomputed_moves: &MovesTable,
+  config: &GameConfig,
+  evaluator: &Evaluator,
+  rng: &mut R,
+) -> Option<PlayerMove> {
+
+  let legal_moves = precomputed_moves.legal_moves(grid);
+
+  // nothing to search with fewer than two moves
+  if legal_moves.len() < 2 {
+    return legal_moves.iter().next();
+  }
+
+  let start = Instant::now();
+  let deadline = match mcts_config.get_budget() {
+    MctsBudget::Time(time) => Some(deadline.map_or(start + time, |deadline| deadline.min(start + time))),
+    MctsBudget::Iterations(_) => deadline,
+  };
+  let iterations = match mcts_config.get_budget() {
+    MctsBudget::Iterations(iterations) => iterations,
+    MctsBudget::Time(_) => usize::MAX,
+  };
+
+  let mut tree = SearchTree::new(grid, mcts_config, precomputed_moves, config, evaluator);
+
+  for iteration in 0..iterations {
+    if iteration > 0 && deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+      break;
+    }
+
+    tree.iterate(rng);
+  }
+
+  tree.best_move()
+}
+
+/// Draws a spawn on an empty cell of the grid, uniformly among the cells and a 2 with probability `prob_tile2`.
+fn random_spawn<R: Rng>(grid: &Grid<EncodedGrid>, prob_tile2: f64, rng: &mut R) -> Spawn {
+  let tile = if rng.gen::<f64>() < prob_tile2 { 2 } else { 4 };
+  let (i, j) = grid.empty_cells().nth(rng.gen_range(0..grid.get_zeros())).unwrap();
+
+  (i, j, tile)
+}
+
+
+//------------------------------------------------
+// Unit tests
+//------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+
+  use super::*;
+  use rand::SeedableRng;
+  use crate::game::engine::GameRng;
+  use crate::game::moves::precomputed_moves;
+
+  fn test_config(budget: MctsBudget) -> MctsConfig {
+    MctsConfig::builder().budget(budget).rollout_moves(10).build().unwrap()
+  }
+
+  // Test MctsConfigBuilder
+
+  #[test]
+  pub fn test_mcts_config_builder() {
+    let config = MctsConfig::builder()
+      .exploration(0.5)
+      .rollout_policy(RolloutPolicy::Greedy)
+      .rollout_moves(5)
+      .budget(MctsBudget::Time(Duration::from_millis(20)))
+      .chance_sampling(ChanceSampling::Limited(4))
+      .seed(3)
+      .build()
+      .unwrap();
+
+    assert_eq!(config.get_exploration(), 0.5);
+    assert_eq!(config.get_rollout_policy(), RolloutPolicy::Greedy);
+    assert_eq!(config.get_rollout_moves(), 5);
+    assert_eq!(config.get_budget(), MctsBudget::Time(Duration::from_millis(20)));
+    assert_eq!(config.get_chance_sampling(), ChanceSampling::Limited(4));
+    assert_eq!(config.get_seed(), 3);
+
+    assert_eq!(MctsConfig::builder().exploration(-1.).build(), Err(MctsConfigError::InvalidExploration(-1.)));
+    assert_eq!(MctsConfig::builder().budget(MctsBudget::Iterations(0)).build(), Err(MctsConfigError::InvalidBudget(MctsBudget::Iterations(0))));
+    assert_eq!(MctsConfig::builder().chance_sampling(ChanceSampling::Limited(0)).build(), Err(MctsConfigError::InvalidChanceSampling(0)));
+  }
+
+
+  // Test mcts_move()
+
+  #[test]
+  pub fn test_mcts_move_game_over() {
+    let grid = Grid::from_decoded(&[
+      [32, 64, 8, 32],
+      [8, 16, 4, 16],
+      [2, 8, 16, 2],
+      [8, 4, 8, 4],
+    ]);
+
+    let mut rng = GameRng::seed_from_u64(0);
+
+    assert_eq!(mcts_move(&grid, &MctsConfig::default(), None, precomputed_moves(), &GameConfig::default(), &Evaluator::default(), &mut rng), None);
+  }
+
+  #[test]
+  pub fn test_mcts_move_takes_merges() {
+
+    // only moving horizontally merges the large tiles, moving vertically scores nothing
+    let grid = Grid::from_decoded(&[
+      [512, 512, 0, 0],
+      [256, 256, 0, 0],
+      [0, 0, 0, 0],
+      [0, 0, 0, 0],
+    ]);
+
+    for &policy in [RolloutPolicy::Random, RolloutPolicy::Greedy].iter() {
+      let mcts_config = MctsConfig::builder().budget(MctsBudget::Iterations(300)).rollout_policy(policy).rollout_moves(0).build().unwrap();
+      let mut rng = GameRng::seed_from_u64(1);
+      let optimal_move = mcts_move(&grid, &mcts_config, None, precomputed_moves(), &GameConfig::default(), &Evaluator::default(), &mut rng);
+
+      assert!(matches!(optimal_move, Some(PlayerMove::Left) | Some(PlayerMove::Right)), "{:?}: {:?}", policy, optimal_move);
+    }
+  }
+
+  #[test]
+  pub fn test_mcts_move_reproducible() {
+    let grid = Grid::from_decoded(&[
+      [0, 2, 4, 8],
+      [0, 0, 4, 16],
+      [2, 0, 8, 32],
+      [0, 4, 16, 64],
+    ]);
+
+    let mcts_config = test_config(MctsBudget::Iterations(200));
+    let search = |seed| mcts_move(&grid, &mcts_config, None, precomputed_moves(), &GameConfig::default(), &Evaluator::default(), &mut GameRng::seed_from_u64(seed));
+
+    assert!(search(5).is_some());
+    assert_eq!(search(5), search(5));
+  }
+
+  #[test]
+  pub fn test_mcts_move_limited_sampling() {
+    let grid = Grid::from_decoded(&[
+      [0, 2, 4, 8],
+      [0, 0, 4, 16],
+      [2, 0, 8, 32],
+      [0, 4, 16, 64],
+    ]);
+
+    let mcts_config = MctsConfig::builder().budget(MctsBudget::Iterations(200)).chance_sampling(ChanceSampling::Limited(2)).build().unwrap();
+    let (config, evaluator) = (GameConfig::default(), Evaluator::default());
+    let mut tree = SearchTree::new(&grid, &mcts_config, precomputed_moves(), &config, &evaluator);
+    let mut rng = GameRng::seed_from_u64(2);
+
+    for _ in 0..200 {
+      tree.iterate(&mut rng);
+    }
+
+    // no chance node has more spawns than the limit, and every iteration went through the root
+    for node in tree.nodes.iter() {
+      if let NodeKind::Chance { children, .. } = &node.kind {
+        assert!(children.len() <= 2);
+      }
+    }
+
+    assert_eq!(tree.nodes[0].visits, 200);
+  }
+
+  #[test]
+  pub fn test_mcts_move_time_budget() {
+    let grid = Grid::from_decoded(&[
+      [0, 2, 4, 8],
+      [0, 0, 4, 16],
+      [2, 0, 8, 32],
+      [0, 4, 16, 64],
+    ]);
+
+    // the search may overrun the budget by one step of its loop at most
+    const SEARCH_OVERHEAD: Duration = Duration::from_millis(10);
+    let budget = Duration::from_millis(20);
+    let mut rng = GameRng::seed_from_u64(3);
+
+    // the lazily built table of moves isn't part of the search time
+    precomputed_moves();
+    let start = Instant::now();
+
+    assert!(mcts_move(&grid, &test_config(MctsBudget::Time(budget)), None, precomputed_moves(), &GameConfig::default(), &Evaluator::default(), &mut rng).is_some());
+    assert!(start.elapsed() < budget + SEARCH_OVERHEAD, "took {:?}", start.elapsed());
+
+    // the earlier deadline wins
+    let start = Instant::now();
+    let mcts_config = test_config(MctsBudget::Iterations(usize::MAX));
+
+    assert!(mcts_move(&grid, &mcts_config, Some(start + budget), precomputed_moves(), &GameConfig::default(), &Evaluator::default(), &mut rng).is_some());
+    assert!(start.elapsed() < budget + SEARCH_OVERHEAD, "took {:?}", start.elapsed());
+  }
+
+}
@@ -0,0 +1,323 @@
+//! # `transposition` module
+//!
+//! Contains the transposition table caching the values of the grids evaluated by the AI searches.
+//! The same grid is reached by many orders of moves and spawns, so the values are stored by grid and remaining search depth
+//! in a fixed number of slots indexed by the Zobrist hash of the grid. Distinct grids can share a slot, and even a hash,
+//! so each entry keeps its encoded grid, which a lookup compares to tell a hit from a collision.
+//! A value only depends on the grid, the depth, the game rules and the evaluator, so the table stays valid across the moves of a game
+//! and is kept by the worker between searches. The worker starts a new generation at each move, and the entries of the older ones give way to the new values.
+
+use crate::game::core::*;
+
+
+//------------------------------------------------
+// Types and Definitions
+//------------------------------------------------
+
+/// Default number of slots of the table, 32 bytes each.
+pub const DEFAULT_TABLE_SIZE: usize = 1 << 16;
+
+/// Describes which entry is kept when a new value maps to an occupied slot.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub enum ReplacementPolicy {
+  /// The new entry always replaces the old one.
+  Always,
+  /// The new entry replaces an entry of the same search only if searched at least as deep, deeper values being costlier to recompute.
+  /// Entries of the previous searches are always replaced.
+  #[default]
+  DepthPreferred,
+}
+
+/// Size and replacement policy of a `TranspositionTable`, a size of 0 disables the table.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct TableConfig {
+  size: usize,
+  policy: ReplacementPolicy,
+}
+
+/// Counters of the lookups in a `TranspositionTable`.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub struct TableStats {
+  hits: u64,
+  misses: u64,
+  stored: usize,
+}
+
+/// Bounded cache of the values of grids, keyed by grid and remaining depth.
+pub struct TranspositionTable {
+  slots: Vec<TableEntry>,
+  policy: ReplacementPolicy,
+  generation: u8,
+  stats: TableStats,
+}
+
+// Value of the encoded grid `grid`, stored by the search of generation `generation`, 0 for an empty slot
+#[derive(Copy, Clone, PartialEq, Debug, Default)]
+struct TableEntry {
+  grid: EncodedGrid,
+  value: f64,
+  depth: u8,
+  generation: u8,
+}
+
+
+//------------------------------------------------
+// Implementations
+//------------------------------------------------
+
+// Inherent
+
+impl TableConfig {
+
+  /// Constructor, the size is rounded up to a power of two.
+  pub fn new(size: usize, policy: ReplacementPolicy) -> Self {
+    TableConfig {
+      size: if size == 0 { 0 } else { size.next_power_of_two() },
+      policy,
+    }
+  }
+
+  /// Constructor of a disabled table, which stores nothing.
+  pub fn disabled() -> Self {
+    TableConfig::new(0, ReplacementPolicy::default())
+  }
+
+  // Getters
+  pub fn get_size(&self) -> usize { self.size }
+  pub fn get_policy(&self) -> ReplacementPolicy { self.policy }
+
+}
+
+impl TableStats {
+
+  // Getters
+  pub fn get_hits(&self) -> u64 { self.hits }
+  pub fn get_misses(&self) -> u64 { self.misses }
+  pub fn get_stored(&self) -> usize { self.stored }
+
+  /// Returns the fraction of lookups that found a value, 0 before any lookup.
+  pub fn hit_rate(&self) -> f64 {
+    match self.hits + self.misses {
+      0 => 0.,
+      lookups => self.hits as f64 / lookups as f64,
+    }
+  }
+
+}
+
+impl TranspositionTable {
+
+  /// Constructor of an empty table.
+  pub fn new(config: TableConfig) -> Self {
+    TranspositionTable {
+      slots: vec![TableEntry::default(); config.get_size()],
+      policy: config.get_policy(),
+      generation: 1,
+      stats: TableStats::default(),
+    }
+  }
+
+  // Getters
+  pub fn get_stats(&self) -> TableStats { self.stats }
+
+  /// Returns the value stored for `grid` searched `depth` moves deep, if any.
+  pub fn get(&mut self, grid: &Grid<EncodedGrid>, depth: usize) -> Option<f64> {
+    if self.slots.is_empty() || depth > u8::MAX as usize {
+      return None;
+    }
+
+    let entry = self.slots[self.index(grid)];
+
+    if entry.generation != 0 && entry.grid == *grid.get_state() && entry.depth as usize == depth {
+      self.stats.hits += 1;
+      Some(entry.value)
+    } else {
+      self.stats.misses += 1;
+      None
+    }
+  }
+
+  /// Stores the value of `grid` searched `depth` moves deep, possibly evicting the entry in its slot according to the replacement policy.
+  /// Values searched more than 255 moves deep are not stored.
+  pub fn insert(&mut self, grid: &Grid<EncodedGrid>, depth: usize, value: f64) {
+    if self.slots.is_empty() || depth > u8::MAX as usize {
+      return;
+    }
+
+    let index = self.index(grid);
+    let entry = self.slots[index];
+
+    let replace = if entry.generation == 0 {
+      self.stats.stored += 1;
+      true
+    } else {
+      match self.policy {
+        ReplacementPolicy::Always => true,
+        ReplacementPolicy::DepthPreferred => entry.generation != self.generation || entry.grid == *grid.get_state() || depth >= entry.depth as usize,
+      }
+    };
+
+    if replace {
+      self.slots[index] = TableEntry { grid: *grid.get_state(), value, depth: depth as u8, generation: self.generation };
+    }
+  }
+
+  /// Starts a new search, whose values replace the ones of the previous searches regardless of their depth.
+  pub fn new_search(&mut self) {
+    // 0 marks the empty slots
+    self.generation = self.generation.checked_add(1).unwrap_or(1);
+  }
+
+  /// Removes every entry and resets the statistics.
+  pub fn clear(&mut self) {
+    for slot in self.slots.iter_mut() {
+      *slot = TableEntry::default();
+    }
+
+    self.generation = 1;
+    self.stats = TableStats::default();
+  }
+
+  fn index(&self, grid: &Grid<EncodedGrid>) -> usize {
+    grid.zobrist_hash().get_value() as usize & (self.slots.len() - 1)
+  }
+
+}
+
+
+// Default
+
+impl Default for TableConfig {
+
+  fn default() -> Self {
+    TableConfig::new(DEFAULT_TABLE_SIZE, ReplacementPolicy::default())
+  }
+
+}
+
+
+//------------------------------------------------
+// Unit tests
+//------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+
+  use super::*;
+
+  fn test_grid() -> Grid<EncodedGrid> {
+    Grid::from_decoded(&[
+      [2, 4, 0, 8],
+      [0, 0, 2, 0],
+      [0, 0, 0, 0],
+      [1024, 0, 0, 4],
+    ])
+  }
+
+  // Testing TableConfig::new()
+
+  #[test]
+  pub fn test_table_config_size() {
+    assert_eq!(TableConfig::new(1000, ReplacementPolicy::Always).get_size(), 1024);
+    assert_eq!(TableConfig::new(1024, ReplacementPolicy::Always).get_size(), 1024);
+    assert_eq!(TableConfig::disabled().get_size(), 0);
+  }
+
+
+  // Testing TranspositionTable::get() and TranspositionTable::insert()
+
+  #[test]
+  pub fn test_table_hits_and_misses() {
+    let mut table = TranspositionTable::new(TableConfig::new(64, ReplacementPolicy::Always));
+    let grid = test_grid();
+
+    assert_eq!(table.get(&grid, 2), None);
+
+    table.insert(&grid, 2, 1.5);
+
+    assert_eq!(table.get(&grid, 2), Some(1.5));
+    assert_eq!(table.get(&grid, 1), None);
+
+    let stats = table.get_stats();
+    assert_eq!((stats.get_hits(), stats.get_misses(), stats.get_stored()), (1, 2, 1));
+    assert!((stats.hit_rate() - 1. / 3.).abs() < 1e-12);
+
+    table.clear();
+    assert_eq!(table.get_stats(), TableStats::default());
+    assert_eq!(table.get(&grid, 2), None);
+  }
+
+  #[test]
+  pub fn test_table_replacement_policies() {
+    let grid = test_grid();
+    let mut other = test_grid();
+
+    // any two grids collide in a table of a single slot
+    other.set_tile(2, 2, 2);
+
+    for &(policy, expected) in [(ReplacementPolicy::Always, None), (ReplacementPolicy::DepthPreferred, Some(3.))].iter() {
+      let mut table = TranspositionTable::new(TableConfig::new(1, policy));
+
+      table.insert(&grid, 3, 3.);
+      table.insert(&other, 1, 1.);
+
+      assert_eq!(table.get(&grid, 3), expected, "{:?}", policy);
+      assert_eq!(table.get_stats().get_stored(), 1);
+
+      // a deeper search always takes the slot
+      table.insert(&other, 4, 4.);
+      assert_eq!(table.get(&other, 4), Some(4.), "{:?}", policy);
+    }
+  }
+
+  #[test]
+  pub fn test_table_new_search_ages_entries() {
+    let grid = test_grid();
+    let mut other = test_grid();
+    other.set_tile(2, 2, 2);
+
+    let mut table = TranspositionTable::new(TableConfig::new(1, ReplacementPolicy::DepthPreferred));
+
+    table.insert(&grid, 3, 3.);
+    table.new_search();
+
+    // the values of a previous search are still found, but give way to shallower values of the new one
+    assert_eq!(table.get(&grid, 3), Some(3.));
+
+    table.insert(&other, 1, 1.);
+    assert_eq!(table.get(&other, 1), Some(1.));
+    assert_eq!(table.get(&grid, 3), None);
+  }
+
+  #[test]
+  pub fn test_table_entry_identifies_grid() {
+    let grid = test_grid();
+    let mut other = test_grid();
+    other.set_tile(2, 2, 2);
+
+    let mut table = TranspositionTable::new(TableConfig::new(64, ReplacementPolicy::Always));
+    table.insert(&grid, 2, 2.);
+
+    // an entry found in the slot of a grid is only a hit if it holds that very grid, as for two grids of the same hash
+    let index = table.index(&grid);
+    table.slots[index].grid = *other.get_state();
+
+    assert_eq!(table.get(&grid, 2), None);
+  }
+
+  #[test]
+  pub fn test_table_entry_size() {
+    assert_eq!(std::mem::size_of::<TableEntry>(), 32);
+  }
+
+  #[test]
+  pub fn test_table_disabled() {
+    let mut table = TranspositionTable::new(TableConfig::disabled());
+
+    table.insert(&test_grid(), 0, 1.);
+
+    assert_eq!(table.get(&test_grid(), 0), None);
+    assert_eq!(table.get_stats(), TableStats::default());
+  }
+
+}
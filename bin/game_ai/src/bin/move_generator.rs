@@ -18,17 +18,17 @@ fn main() {
   let moves_table = moves::make_precomputed_hashmap();
 
   // Header 
-  file.write("
+  file.write_all("
 // contains only valid left stacking single row moves, all the others can be derived from these
 // the key in Map is the current encoded row, the value is an array with [new_encoded_row, score, destination_array]
 export const precomputed = new Map([\n".as_bytes()).expect("Error in writing header!");
 
   for (_, value) in moves_table.iter() {
-    file.write(value.format_js_array().as_bytes()).expect("Error in writing record!");
+    file.write_all(value.format_js_array().as_bytes()).expect("Error in writing record!");
   }
 
   // Footer
-  file.write("]);".as_bytes()).expect("Error in writing footer!");
+  file.write_all("]);".as_bytes()).expect("Error in writing footer!");
 
   file.flush().unwrap();
 
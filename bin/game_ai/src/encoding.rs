@@ -1,22 +1,18 @@
 //! # `encoding` module
 //! 
 //! This module allows for encoding and decoding a row state into and from a u32 number.
-//! No validation of the inputs is executed for maximum performance.
+//! All the math is done with integer bit operations.
+//! No validation of the inputs is executed by the plain functions for maximum performance,
+//! the `try_` functions check external input and return a `GridError` instead.
 
 use crate::game::core::*;
+use crate::error::GridError;
 
 
 //------------------------------------------------
 // Traits
 //------------------------------------------------
 
-/// A trait for an object that can return and encoded version if itself
-pub trait Encode {
-  type Output;
-
-  fn get_encoded(&self) -> Self::Output;
-}
-
 /// A trait for an object that can return a decoded version of itself
 pub trait Decode {
   type Output;
@@ -29,13 +25,25 @@ pub trait Decode {
 // Functions
 //------------------------------------------------
 
-/// Encodes a single tile and shifts it to the right position
+/// Encodes a single tile and shifts it to the right position.
+/// The exponent is the integer log2 of the tile, 0 for an empty tile.
+#[inline]
 pub fn encode_tile(num: EntryType, position: usize) -> EncodedEntryType {
-  ((num as f64).log2() as EncodedEntryType) << ENCODING_BITS * position
+  if num == 0 {
+    return 0;
+  }
+
+  (EntryType::BITS - 1 - num.leading_zeros()) << (ENCODING_BITS * position)
 }
 
-/// Encodes a single row of tiles to a number.
-pub fn encode_line(row: &Array1D<EntryType>) -> EncodedEntryType {
+/// Decodes the tile of an exponent extracted from an encoded row, 0 standing for an empty tile.
+#[inline]
+pub fn decode_tile(exponent: EncodedEntryType) -> EntryType {
+  if exponent == 0 { 0 } else { 1 << exponent }
+}
+
+/// Encodes a single row of tiles of any length up to `MAX_GRID_SIDE` to a number.
+pub fn encode_line(row: &[EntryType]) -> EncodedEntryType {
   let mut num = 0;
 
   for (i, &tile) in row.iter().enumerate() {
@@ -47,20 +55,20 @@ pub fn encode_line(row: &Array1D<EntryType>) -> EncodedEntryType {
 }
 
 /// Decodes a number to a single row of tiles.
-pub fn decode_line(mut num: EncodedEntryType) -> Array1D<EntryType> {
+pub fn decode_line(num: EncodedEntryType) -> Array1D<EntryType> {
   let mut row = [0; GRID_SIDE];
-  let mut tile;
 
-  for i in 0..GRID_SIDE {
-    tile = ((num % (ENCODING_BITS as f64).exp2() as EncodedEntryType) as f64).exp2() as EntryType;
-    num >>= ENCODING_BITS;
-    if tile > 1 {
-      row[i] = tile;
-    }
+  for (i, tile) in row.iter_mut().enumerate() {
+    *tile = decode_tile((num >> (ENCODING_BITS * i)) & ENCODING_TILE_MASK);
   }
   row
 }
 
+/// Decodes a number to a single row of `length` tiles, for rows of any length up to `MAX_GRID_SIDE`.
+pub fn decode_line_of_length(num: EncodedEntryType, length: usize) -> Vec<EntryType> {
+  (0..length).map(|i| decode_tile((num >> (ENCODING_BITS * i)) & ENCODING_TILE_MASK)).collect()
+}
+
 /// Encodes the entire grid
 pub fn encode_grid(decoded_grid: &Array2D<EntryType>) -> EncodedGrid {
   let mut grid: EncodedGrid = [0; GRID_SIDE];
@@ -83,6 +91,71 @@ pub fn decode_grid(encoded_grid: &EncodedGrid) -> Array2D<EntryType> {
   decoded_grid
 }
 
+/// Checks that a single tile is either empty or a power of 2 between 2 and `LARGEST_TILE`.
+pub fn validate_tile(num: EntryType) -> Result<(), GridError> {
+  if num == 1 {
+    return Err(GridError::TileOfOne);
+  }
+
+  if num != 0 && !num.is_power_of_two() {
+    return Err(GridError::NotPowerOfTwo(num));
+  }
+
+  if num > LARGEST_TILE {
+    return Err(GridError::TileTooLarge(num));
+  }
+
+  Ok(())
+}
+
+/// Encodes a single tile like `encode_tile()`, after checking it with `validate_tile()`.
+pub fn try_encode_tile(num: EntryType, position: usize) -> Result<EncodedEntryType, GridError> {
+  validate_tile(num)?;
+
+  Ok(if num == 0 { 0 } else { encode_tile(num, position) })
+}
+
+/// Encodes a single row of tiles like `encode_line()`, after checking every tile.
+pub fn try_encode_line(row: &[EntryType]) -> Result<EncodedEntryType, GridError> {
+  let mut num = 0;
+
+  for (i, &tile) in row.iter().enumerate() {
+    num |= try_encode_tile(tile, i)?;
+  }
+  Ok(num)
+}
+
+/// Decodes a number to a single row of tiles like `decode_line()`, rejecting rows with bits set beyond their `GRID_SIDE` tiles.
+pub fn try_decode_line(num: EncodedEntryType) -> Result<Array1D<EntryType>, GridError> {
+  if num >> (ENCODING_BITS * GRID_SIDE) != 0 {
+    return Err(GridError::CorruptRow(num));
+  }
+
+  Ok(decode_line(num))
+}
+
+/// Encodes the entire grid, after checking every tile.
+pub fn try_encode_grid(decoded_grid: &Array2D<EntryType>) -> Result<EncodedGrid, GridError> {
+  let mut grid: EncodedGrid = [0; GRID_SIDE];
+
+  for (i, decoded_line) in decoded_grid.iter().enumerate() {
+    grid[i] = try_encode_line(decoded_line)?;
+  }
+
+  Ok(grid)
+}
+
+/// Decodes the entire grid, rejecting corrupt rows.
+pub fn try_decode_grid(encoded_grid: &EncodedGrid) -> Result<Array2D<EntryType>, GridError> {
+  let mut decoded_grid: Array2D<EntryType> = [[0; GRID_SIDE]; GRID_SIDE];
+
+  for (i, &encoded_line) in encoded_grid.iter().enumerate() {
+    decoded_grid[i] = try_decode_line(encoded_line)?;
+  }
+
+  Ok(decoded_grid)
+}
+
 
 //------------------------------------------------
 // Unit tests
@@ -114,6 +187,13 @@ mod tests {
     assert_eq!(encode_line(&row), 541200);
   }
   
+  #[test]
+  fn correct_largest_encoding() {
+    let row = [LARGEST_TILE, 131072, 2, 0];
+    assert_eq!(encode_line(&row), 31 | (17 << 5) | (1 << 10));
+    assert_eq!(decode_line(encode_line(&row)), row);
+  }
+  
   #[test]
   fn correct_critical_encoding() {
     let row = [0, 2, 4, 8];
@@ -144,6 +224,20 @@ mod tests {
   }
 
 
+  // testing for decode_line_of_length()
+
+  #[test]
+  fn correct_short_decoding() {
+    assert_eq!(decode_line_of_length(encode_line(&[4, 0, 2]), 3), vec![4, 0, 2]);
+  }
+
+  #[test]
+  fn correct_long_decoding() {
+    let row = [2, 0, 1024, 8, 65536, 4];
+    assert_eq!(decode_line_of_length(encode_line(&row), 6), row.to_vec());
+  }
+
+
   // testing for encode_grid()
 
   #[test]
@@ -174,4 +268,99 @@ mod tests {
 
     assert_eq!(decode_grid(&encoded_grid), decoded_grid);
   }
+
+  // testing for validation
+
+  #[test]
+  pub fn test_validate_tile() {
+    assert_eq!(validate_tile(0), Ok(()));
+    assert_eq!(validate_tile(2), Ok(()));
+    assert_eq!(validate_tile(LARGEST_TILE), Ok(()));
+    assert_eq!(validate_tile(1), Err(GridError::TileOfOne));
+    assert_eq!(validate_tile(3), Err(GridError::NotPowerOfTwo(3)));
+    assert_eq!(validate_tile(6), Err(GridError::NotPowerOfTwo(6)));
+    assert_eq!(validate_tile(EntryType::MAX), Err(GridError::NotPowerOfTwo(EntryType::MAX)));
+  }
+
+  #[test]
+  pub fn test_try_encode_line() {
+    assert_eq!(try_encode_line(&[8, 4, 2, 0]), Ok(1091));
+    assert_eq!(try_encode_line(&[8, 6, 2, 0]), Err(GridError::NotPowerOfTwo(6)));
+    assert_eq!(try_encode_line(&[0, 0, 1, 0]), Err(GridError::TileOfOne));
+  }
+
+  #[test]
+  pub fn test_try_decode_line() {
+    assert_eq!(try_decode_line(1091), Ok([8, 4, 2, 0]));
+    assert_eq!(try_decode_line(1 << (ENCODING_BITS * GRID_SIDE)), Err(GridError::CorruptRow(1 << (ENCODING_BITS * GRID_SIDE))));
+  }
+
+  #[test]
+  pub fn test_try_grid_round_trip() {
+    let decoded_grid: Array2D<EntryType> = [
+      [0, 2, 4, 8],
+      [4, 4, 4, 4],
+      [8, 8, 4, 4],
+      [8, 4, 2, LARGEST_TILE],
+    ];
+
+    assert_eq!(try_decode_grid(&try_encode_grid(&decoded_grid).unwrap()), Ok(decoded_grid));
+    assert_eq!(try_decode_grid(&[0, 0, EncodedEntryType::MAX, 0]), Err(GridError::CorruptRow(EncodedEntryType::MAX)));
+  }
+
+  // testing against the float based reference
+
+  fn reference_encode_tile(num: EntryType, position: usize) -> EncodedEntryType {
+    ((num as f64).log2() as EncodedEntryType) << (ENCODING_BITS * position)
+  }
+
+  fn reference_decode_line(mut num: EncodedEntryType) -> Array1D<EntryType> {
+    let mut row = [0; GRID_SIDE];
+
+    for entry in row.iter_mut() {
+      let tile = ((num % (ENCODING_BITS as f64).exp2() as EncodedEntryType) as f64).exp2() as EntryType;
+      num >>= ENCODING_BITS;
+      if tile > 1 {
+        *entry = tile;
+      }
+    }
+    row
+  }
+
+  #[test]
+  pub fn test_decode_line_exhaustive() {
+    for num in 0..(1 << (ENCODING_BITS * GRID_SIDE)) {
+      let row = decode_line(num);
+
+      assert_eq!(row, reference_decode_line(num), "{:#x}", num);
+      assert_eq!(encode_line(&row), num, "{:#x}", num);
+      assert_eq!(decode_line_of_length(num, GRID_SIDE), row.to_vec(), "{:#x}", num);
+    }
+  }
+
+  #[test]
+  pub fn test_encode_line_exhaustive() {
+    for num in 0..(1 << (ENCODING_BITS * GRID_SIDE)) {
+      let row = reference_decode_line(num);
+      let reference = row.iter().enumerate().fold(0, |acc, (i, &tile)| if tile != 0 { acc | reference_encode_tile(tile, i) } else { acc });
+
+      assert_eq!(encode_line(&row), reference, "{:?}", row);
+    }
+  }
+
+  #[test]
+  pub fn test_encode_tile_matches_reference() {
+    for exponent in 1..TILE_EXPONENTS {
+      let tile = 1 << exponent;
+
+      for &num in [tile, tile + 1, tile | (tile - 1)].iter() {
+        assert_eq!(encode_tile(num, 1), reference_encode_tile(num, 1), "{}", num);
+      }
+    }
+
+    assert_eq!(encode_tile(0, 3), 0);
+    assert_eq!(encode_tile(1, 3), 0);
+    assert_eq!(decode_tile(0), 0);
+    assert_eq!(decode_tile(31), LARGEST_TILE);
+  }
 }
\ No newline at end of file
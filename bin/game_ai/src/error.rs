@@ -0,0 +1,52 @@
+//! # `error` module
+//!
+//! This module contains the error types shared across the crate.
+
+use std::{error, fmt, fmt::Display};
+
+use crate::game::core::*;
+
+
+//------------------------------------------------
+// Types and Definitions
+//------------------------------------------------
+
+/// Errors raised when building a grid from external input, either decoded tiles, encoded rows or text.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum GridError {
+  NotPowerOfTwo(EntryType),
+  TileOfOne,
+  TileTooLarge(EntryType),
+  CorruptRow(EncodedEntryType),
+  InvalidToken { row: usize, col: usize },
+  WrongRowLength { row: usize, length: usize },
+  WrongRowCount(usize),
+}
+
+
+//------------------------------------------------
+// Implementations
+//------------------------------------------------
+
+// Display
+
+impl Display for GridError {
+
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      GridError::NotPowerOfTwo(tile) => write!(f, "tile {} is not a power of 2", tile),
+      GridError::TileOfOne => write!(f, "tile 1 is not a valid tile, the smallest tile is 2"),
+      GridError::TileTooLarge(tile) => write!(f, "tile {} is larger than the largest encodable tile {}", tile, LARGEST_TILE),
+      GridError::CorruptRow(row) => write!(f, "encoded row {:#x} uses bits beyond its {} tiles", row, GRID_SIDE),
+      GridError::InvalidToken { row, col } => write!(f, "unreadable tile in row {}, column {}", row, col),
+      GridError::WrongRowLength { row, length } => write!(f, "row {} has {} tiles instead of {}", row, length, GRID_SIDE),
+      GridError::WrongRowCount(count) => write!(f, "grid has {} rows instead of {}", count, GRID_SIDE),
+    }
+  }
+
+}
+
+
+// Error
+
+impl error::Error for GridError {}
@@ -1,8 +1,18 @@
 //! # `game` module
 //!
 //! This module defines the fundamental grids of the game and their core behaviour.
-//! It is further divided in the `core`, `moves` and `engine` submodules.
+//! It is further divided in the `core`, `config`, `moves`, `symmetry`, `zobrist`, `notation`, `engine`, `board`, `record`, `stats` and `observer` submodules, plus the `save` submodule with the `serde` feature.
 
 pub mod core;
+pub mod config;
 pub mod moves;
-pub mod engine;
\ No newline at end of file
+pub mod symmetry;
+pub mod zobrist;
+pub mod notation;
+pub mod engine;
+pub mod board;
+pub mod record;
+pub mod stats;
+pub mod observer;
+#[cfg(feature = "serde")]
+pub mod save;
@@ -0,0 +1,808 @@
+//! # `board` module
+//!
+//! This module defines the boards a game is played on, through the `BoardView` and `GameBoard` traits.
+//! `Grid<EncodedGrid>` is the default square `GRID_SIDE` board, optimized with the precomputed moves, bitboards and transposition tables,
+//! while a `Board` is sized at runtime, from `MIN_BOARD_SIDE` to `MAX_GRID_SIDE` cells a side, rows being encoded like grid rows.
+//! A `BoardGame` and a `BoardAIEngine` play any board, `Game` and `AIEngine` being the ones playing the default grid.
+//! Board lines up to `GRID_SIDE` cells are stacked with the precomputed moves table, padded with empty cells,
+//! and longer lines are stacked on the fly, since precomputed tables grow exponentially with the line length.
+
+use std::{fmt, fmt::Display};
+
+use rand::Rng;
+
+use crate::encoding;
+use crate::game::core::*;
+use crate::game::config::GameConfig;
+use crate::game::moves::{self, PlayerMove, MoveSet, MergeEvent};
+
+
+//------------------------------------------------
+// Types and Definitions
+//------------------------------------------------
+
+pub const MIN_BOARD_SIDE: usize = 2;
+
+/// Runtime-sized grid of `rows` encoded lines of `cols` tiles each.
+#[derive(Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Board {
+  rows: usize,
+  cols: usize,
+  state: Vec<EncodedEntryType>,
+}
+
+/// Contains the information regarding the processing of a move on a board.
+#[derive(Clone, PartialEq, Debug)]
+pub struct BoardStackingResult<B: GameBoard = Board> {
+  prev_board: B,
+  new_board: B,
+  delta_score: u64,
+  destinations: B::Destinations,
+  merges: Vec<MergeEvent>,
+}
+
+
+//------------------------------------------------
+// Traits
+//------------------------------------------------
+
+/// Read access to the tiles of a board of any size, as needed to evaluate it.
+pub trait BoardView {
+
+  fn get_rows(&self) -> usize;
+
+  fn get_cols(&self) -> usize;
+
+  /// Returns the tile in row `row` and column `col`, 0 for an empty cell.
+  fn get_tile(&self, row: usize, col: usize) -> EntryType;
+
+  /// Returns the largest tile, 0 for an empty board.
+  fn max_tile(&self) -> EntryType {
+    (0..self.get_rows()).flat_map(|i| (0..self.get_cols()).map(move |j| (i, j))).map(|(i, j)| self.get_tile(i, j)).max().unwrap_or(0)
+  }
+
+}
+
+/// Trait for the boards a `BoardGame` is played on.
+pub trait GameBoard: BoardView + Clone + PartialEq + fmt::Debug + Send + 'static {
+
+  /// Displacements of the tiles in a move, laid out as the board.
+  type Destinations: Clone + PartialEq + fmt::Debug + Send;
+
+  /// Constructor of an empty board of `rows` x `cols` cells.
+  /// Panics if the board type can't hold that size.
+  fn empty(rows: usize, cols: usize) -> Self;
+
+  /// Checks that the board has `rows` x `cols` cells and valid tiles, e.g. after being read from a save.
+  fn fits(&self, rows: usize, cols: usize) -> bool;
+
+  /// Gets the number of zeros.
+  fn get_zeros(&self) -> usize;
+
+  /// Replaces the tile in row `row` and column `col` with `tile`, a power of 2 up to `LARGEST_TILE` or 0 to empty the cell.
+  fn set_tile(&mut self, row: usize, col: usize, tile: EntryType);
+
+  /// Adds a "new_tile" value to a certain "position" in the listed empty tiles in reading order, see `Grid::add_tile_to_position()`,
+  /// and returns the (row, col) coordinates of the cell written, `None` if there are not enough empty tiles.
+  fn add_tile_to_position(&mut self, new_tile: EntryType, position: isize) -> Option<(usize, usize)>;
+
+  /// Process the stacking of the board based on the player move.
+  fn stack(&self, player_move: PlayerMove) -> BoardStackingResult<Self>;
+
+  /// Returns the moves that would change the board.
+  fn legal_moves(&self) -> MoveSet;
+
+  /// Checks if the board is in a terminating state.
+  fn is_game_over(&self) -> bool {
+
+    // if at least one entry is zero you can always make a move
+    self.get_zeros() == 0 && self.legal_moves().is_empty()
+  }
+
+}
+
+
+//------------------------------------------------
+// Implementations
+//------------------------------------------------
+
+
+// Inherent
+
+impl Board {
+
+  /// Constructor of an empty board.
+  ///
+  /// # Panics
+  /// Panics if a side is smaller than `MIN_BOARD_SIDE` or larger than `MAX_GRID_SIDE`.
+  pub fn new(rows: usize, cols: usize) -> Self {
+    assert!(
+      (MIN_BOARD_SIDE..=MAX_GRID_SIDE).contains(&rows) && (MIN_BOARD_SIDE..=MAX_GRID_SIDE).contains(&cols),
+      "Board sides must be between {} and {}, got {}x{}", MIN_BOARD_SIDE, MAX_GRID_SIDE, rows, cols
+    );
+
+    Board {
+      rows,
+      cols,
+      state: vec![0; rows],
+    }
+  }
+
+  /// Constructor from decoded rows to be encoded.
+  ///
+  /// # Panics
+  /// Panics if the rows are not all of the same length, or if the sides are not admissible as in `Board::new()`.
+  pub fn from_decoded(grid: &[Vec<EntryType>]) -> Self {
+    let cols = grid.first().map_or(0, |row| row.len());
+    assert!(grid.iter().all(|row| row.len() == cols), "Board rows must all have the same length");
+
+    let mut board = Board::new(grid.len(), cols);
+
+    for (i, row) in grid.iter().enumerate() {
+      board.state[i] = encoding::encode_line(row);
+    }
+
+    board
+  }
+
+  // Getters
+  pub fn get_rows(&self) -> usize { self.rows }
+  pub fn get_cols(&self) -> usize { self.cols }
+  pub fn get_state(&self) -> &[EncodedEntryType] { &self.state }
+
+  /// Returns the decoded rows of the board.
+  pub fn get_decoded(&self) -> Vec<Vec<EntryType>> {
+    self.state.iter().map(|&line| encoding::decode_line_of_length(line, self.cols)).collect()
+  }
+
+  /// Returns the tile in row `row` and column `col`, 0 for an empty cell.
+  /// Panics if the cell is outside the board.
+  pub fn get_tile(&self, row: usize, col: usize) -> EntryType {
+    self.assert_cell(row, col);
+    encoding::decode_tile((self.state[row] >> (ENCODING_BITS * col)) & ENCODING_TILE_MASK)
+  }
+
+  /// Replaces the tile in row `row` and column `col` with `tile`, a power of 2 up to `LARGEST_TILE` or 0 to empty the cell.
+  /// Panics if the cell is outside the board.
+  pub fn set_tile(&mut self, row: usize, col: usize, tile: EntryType) -> &mut Self {
+    self.assert_cell(row, col);
+    debug_assert!(tile == 0 || tile.is_power_of_two(), "tile {} is not a power of 2", tile);
+
+    let exponent = match tile {
+      0 => 0,
+      _ => tile.trailing_zeros(),
+    };
+
+    self.state[row] = (self.state[row] & !(ENCODING_TILE_MASK << (ENCODING_BITS * col))) | (exponent << (ENCODING_BITS * col));
+
+    self
+  }
+
+  /// Gets the number of zeros.
+  pub fn get_zeros(&self) -> usize {
+    self.rows * self.cols - self.state.iter().map(|&line| occupied_tiles(line)).sum::<usize>()
+  }
+
+  /// Adds a "new_tile" value to a certain "position" in the listed empty tiles in reading order, see `Grid::add_tile_to_position()`,
+  /// and returns the (row, col) coordinates of the cell written, `None` if there are not enough empty tiles.
+  pub fn add_tile_to_position(&mut self, new_tile: EntryType, mut position: isize) -> Option<(usize, usize)> {
+    let base_mask = ENCODING_TILE_MASK;
+
+    for i in 0..self.rows {
+      for j in 0..self.cols {
+
+        // Decrement the position counter only when an entry is zero
+        if self.state[i] & (base_mask << (ENCODING_BITS * j)) == 0 {
+          position -= 1;
+
+          if position < 0 {
+            self.state[i] |= encoding::encode_tile(new_tile, j);
+            return Some((i, j));
+          }
+        }
+
+      }
+    }
+
+    None
+  }
+
+  /// Returns the sum of the elements in the `Board`.
+  pub fn get_sum(&self) -> u64 {
+    self.get_decoded().iter().flatten().map(|&tile| tile as u64).sum()
+  }
+
+  fn assert_cell(&self, row: usize, col: usize) {
+    assert!(row < self.rows && col < self.cols, "cell ({}, {}) is outside the {}x{} board", row, col, self.rows, self.cols);
+  }
+
+}
+
+impl<B: GameBoard> BoardStackingResult<B> {
+
+  // Getters
+  pub fn get_prev_board(&self) -> &B { &self.prev_board }
+  pub fn get_new_board(&self) -> &B { &self.new_board }
+  pub fn get_delta_score(&self) -> u64 { self.delta_score }
+  pub fn get_destinations(&self) -> &B::Destinations { &self.destinations }
+
+  /// Returns the merges of the move in board coordinates, see `MoveStackingResult::get_merges()`.
+  pub fn get_merges(&self) -> &[MergeEvent] { &self.merges }
+
+  /// Checks if the move changed the state of the board.
+  pub fn is_effective(&self) -> bool {
+    self.prev_board != self.new_board
+  }
+
+}
+
+
+// BoardView
+
+impl BoardView for Grid<EncodedGrid> {
+
+  fn get_rows(&self) -> usize { GRID_SIDE }
+
+  fn get_cols(&self) -> usize { GRID_SIDE }
+
+  fn get_tile(&self, row: usize, col: usize) -> EntryType { Grid::<EncodedGrid>::get_tile(self, row, col) }
+
+  fn max_tile(&self) -> EntryType { Grid::<EncodedGrid>::max_tile(self) }
+
+}
+
+impl BoardView for Board {
+
+  fn get_rows(&self) -> usize { self.rows }
+
+  fn get_cols(&self) -> usize { self.cols }
+
+  fn get_tile(&self, row: usize, col: usize) -> EntryType { Board::get_tile(self, row, col) }
+
+}
+
+
+// GameBoard
+
+impl GameBoard for Grid<EncodedGrid> {
+
+  type Destinations = Grid<DestinationsGrid>;
+
+  fn empty(rows: usize, cols: usize) -> Self {
+    assert!(rows == GRID_SIDE && cols == GRID_SIDE, "a grid is {}x{}, got {}x{}, play other sizes on a Board", GRID_SIDE, GRID_SIDE, rows, cols);
+
+    Grid::new(&[0; GRID_SIDE])
+  }
+
+  fn fits(&self, rows: usize, cols: usize) -> bool {
+    rows == GRID_SIDE && cols == GRID_SIDE && encoding::try_decode_grid(self.get_state()).is_ok()
+  }
+
+  fn get_zeros(&self) -> usize { Grid::<EncodedGrid>::get_zeros(self) }
+
+  fn set_tile(&mut self, row: usize, col: usize, tile: EntryType) { Grid::<EncodedGrid>::set_tile(self, row, col, tile); }
+
+  fn add_tile_to_position(&mut self, new_tile: EntryType, position: isize) -> Option<(usize, usize)> {
+    Grid::<EncodedGrid>::add_tile_to_position(self, new_tile, position)
+  }
+
+  fn stack(&self, player_move: PlayerMove) -> BoardStackingResult<Self> {
+    let move_result = moves::process_grid_stacking(player_move, self, moves::precomputed_moves());
+
+    BoardStackingResult {
+      prev_board: *self,
+      new_board: *move_result.get_new_grid(),
+      delta_score: move_result.get_delta_score(),
+      destinations: *move_result.get_destination_grid(),
+      merges: move_result.get_merges().to_vec(),
+    }
+  }
+
+  fn legal_moves(&self) -> MoveSet { moves::legal_moves(self) }
+
+}
+
+impl GameBoard for Board {
+
+  type Destinations = Vec<Vec<DestEntryType>>;
+
+  fn empty(rows: usize, cols: usize) -> Self { Board::new(rows, cols) }
+
+  fn fits(&self, rows: usize, cols: usize) -> bool {
+    self.rows == rows
+      && self.cols == cols
+      && self.state.len() == rows
+      && self.state.iter().all(|&line| line >> (ENCODING_BITS * cols) == 0)
+  }
+
+  fn get_zeros(&self) -> usize { Board::get_zeros(self) }
+
+  fn set_tile(&mut self, row: usize, col: usize, tile: EntryType) { Board::set_tile(self, row, col, tile); }
+
+  fn add_tile_to_position(&mut self, new_tile: EntryType, position: isize) -> Option<(usize, usize)> {
+    Board::add_tile_to_position(self, new_tile, position)
+  }
+
+  fn stack(&self, player_move: PlayerMove) -> BoardStackingResult<Self> { process_board_stacking(player_move, self) }
+
+  fn legal_moves(&self) -> MoveSet {
+    MoveSet::all().into_iter().filter(|&player_move| process_board_stacking(player_move, self).is_effective()).collect()
+  }
+
+}
+
+
+// Transpose
+
+impl Transpose for Board {
+
+  fn transpose(&mut self) -> &mut Self {
+    let base_mask = ENCODING_TILE_MASK;
+    let mut state = vec![0; self.cols];
+
+    for i in 0..self.rows {
+      for (j, column) in state.iter_mut().enumerate() {
+        let tile = (self.state[i] >> (ENCODING_BITS * j)) & base_mask;
+        *column |= tile << (ENCODING_BITS * i);
+      }
+    }
+
+    self.state = state;
+    std::mem::swap(&mut self.rows, &mut self.cols);
+
+    self
+  }
+
+}
+
+
+// Reverse
+
+impl Reverse for Board {
+
+  fn reverse(&mut self) -> &mut Self {
+    let base_mask = ENCODING_TILE_MASK;
+
+    for i in 0..self.rows {
+      let mut line = 0;
+
+      for j in 0..self.cols {
+        let tile = (self.state[i] >> (ENCODING_BITS * j)) & base_mask;
+        line |= tile << (ENCODING_BITS * (self.cols - 1 - j));
+      }
+
+      self.state[i] = line;
+    }
+
+    self
+  }
+
+}
+
+
+// Display
+
+impl Display for Board {
+
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+
+    writeln!(f, "Board::state = [")?;
+    for row in self.get_decoded() {
+      writeln!(f, "  {:?},", row)?;
+    }
+    writeln!(f, "]")?;
+
+    Ok(())
+  }
+}
+
+
+//------------------------------------------------
+// Functions
+//------------------------------------------------
+
+/// Transposes a matrix of destinations.
+fn transpose_destinations(destinations: &[Vec<DestEntryType>]) -> Vec<Vec<DestEntryType>> {
+  let cols = destinations.first().map_or(0, |row| row.len());
+
+  (0..cols).map(|j| destinations.iter().map(|row| row[j]).collect()).collect()
+}
+
+/// Horizontally reverses a matrix of destinations, optionally changing the sign of the displacements.
+fn reverse_destinations(destinations: &mut [Vec<DestEntryType>], change_sign: bool) {
+  for row in destinations.iter_mut() {
+    row.reverse();
+
+    if change_sign {
+      row.iter_mut().for_each(|dest| *dest = -*dest);
+    }
+  }
+}
+
+/// Process the stacking of the board based on the player move, see `moves::process_grid_stacking()`.
+/// Lines up to `GRID_SIDE` tiles are looked up in the precomputed moves table, longer ones are stacked on the fly.
+pub fn process_board_stacking(player_move: PlayerMove, board: &Board) -> BoardStackingResult {
+  let mut new_board = board.clone();
+  let mut delta_score = 0;
+  let mut merges = Vec::new();
+
+  // Transform board to conform to left move
+  match player_move {
+    PlayerMove::Up => { new_board.transpose(); },
+    PlayerMove::Left => (),
+    PlayerMove::Right => { new_board.reverse(); },
+    PlayerMove::Down => { new_board.transpose().reverse(); },
+  };
+
+  let (rows, cols) = (new_board.rows, new_board.cols);
+  let mut destinations = vec![vec![0; cols]; rows];
+
+  for (i, line_destinations) in destinations.iter_mut().enumerate() {
+    let prev_line = new_board.state[i];
+
+    // the cells past the end of a short line are empty, so they stay in place and the line stacks as in the grid
+    if cols <= GRID_SIDE {
+      let result = moves::precomputed_moves().get_left(prev_line);
+
+      line_destinations.copy_from_slice(&result.get_destinations()[..cols]);
+      new_board.state[i] = result.get_new_line();
+      delta_score += result.get_delta_score();
+    } else {
+      let line = encoding::decode_line_of_length(prev_line, cols);
+      let mut new_line = vec![0; cols];
+
+      delta_score += moves::stack_line(&line, &mut new_line, line_destinations);
+      new_board.state[i] = encoding::encode_line(&new_line);
+    }
+
+    // merges are found on the processed line, map them back to the board coordinates
+    let position = |k: usize| match player_move {
+      PlayerMove::Up => (k, i),
+      PlayerMove::Left => (i, k),
+      PlayerMove::Right => (i, cols - 1 - k),
+      PlayerMove::Down => (cols - 1 - k, i),
+    };
+    let mut line_merges: Vec<MergeEvent> = moves::line_merges(prev_line, line_destinations)
+      .map(|merge| {
+        let mut sources = merge.get_sources().map(position);
+        sources.sort_unstable();
+
+        MergeEvent::new(sources, position(merge.get_target()), merge.get_value())
+      })
+      .collect();
+
+    // reversed lines are processed from their last cell, list their merges from the first one as the grid does
+    if let PlayerMove::Right | PlayerMove::Down = player_move {
+      line_merges.reverse();
+    }
+    merges.extend(line_merges);
+  }
+
+  // Rebuild original move and destination
+  match player_move {
+    PlayerMove::Up => {
+      new_board.transpose();
+      destinations = transpose_destinations(&destinations);
+    },
+    PlayerMove::Left => (),
+    PlayerMove::Right => {
+      new_board.reverse();
+      reverse_destinations(&mut destinations, true);
+    },
+    PlayerMove::Down => {
+      new_board.reverse().transpose();
+      reverse_destinations(&mut destinations, true);
+      destinations = transpose_destinations(&destinations);
+    },
+  };
+
+  BoardStackingResult {
+    prev_board: board.clone(),
+    new_board,
+    delta_score,
+    destinations,
+    merges,
+  }
+}
+
+/// Adds a random tile to the board drawing from the given generator, and returns the tile and the (row, col) coordinates of its cell,
+/// `None` if the board is full. The tile is a 2 with the probability set in `config`, a 4 otherwise.
+pub fn add_random_tile<R: Rng>(board: &mut Board, rng: &mut R, config: &GameConfig) -> Option<(EntryType, (usize, usize))> {
+
+  let new_tile: EntryType = if rng.gen::<f64>() >= config.get_prob_tile2() { 4 } else { 2 };
+  let position = (rng.gen::<f64>() * board.get_zeros() as f64) as isize;
+
+  board.add_tile_to_position(new_tile, position).map(|cell| (new_tile, cell))
+}
+
+
+//------------------------------------------------
+// Unit tests
+//------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+
+  use super::*;
+  use rand::SeedableRng;
+  use crate::game::engine::GameRng;
+
+
+  // Test Board
+
+  #[test]
+  pub fn test_board_decoding_round_trip() {
+    let rows = vec![
+      vec![2, 0, 4, 8, 16],
+      vec![0, 0, 0, 0, 2],
+      vec![4, 4, 0, 0, 0],
+    ];
+
+    let board = Board::from_decoded(&rows);
+
+    assert_eq!(board.get_rows(), 3);
+    assert_eq!(board.get_cols(), 5);
+    assert_eq!(board.get_decoded(), rows);
+    assert_eq!(board.get_zeros(), 8);
+    assert_eq!(board.get_sum(), 40);
+  }
+
+  #[test]
+  #[should_panic]
+  pub fn test_board_too_large() {
+    Board::new(4, MAX_GRID_SIDE + 1);
+  }
+
+  #[test]
+  pub fn test_board_transpose_rectangular() {
+    let mut board = Board::from_decoded(&[
+      vec![2, 4, 8],
+      vec![16, 0, 32],
+    ]);
+
+    board.transpose();
+
+    assert_eq!(board.get_rows(), 3);
+    assert_eq!(board.get_cols(), 2);
+    assert_eq!(board.get_decoded(), vec![
+      vec![2, 16],
+      vec![4, 0],
+      vec![8, 32],
+    ]);
+  }
+
+  #[test]
+  pub fn test_board_reverse() {
+    let mut board = Board::from_decoded(&[
+      vec![2, 4, 8, 0, 0],
+      vec![16, 0, 32, 0, 2],
+      vec![0, 0, 0, 0, 4],
+    ]);
+
+    assert_eq!(board.reverse().get_decoded(), vec![
+      vec![0, 0, 8, 4, 2],
+      vec![2, 0, 32, 0, 16],
+      vec![4, 0, 0, 0, 0],
+    ]);
+  }
+
+  #[test]
+  pub fn test_board_add_tile_to_position() {
+    let mut board = Board::from_decoded(&[
+      vec![2, 0, 4],
+      vec![0, 8, 0],
+      vec![2, 2, 2],
+    ]);
+
+    assert_eq!(board.add_tile_to_position(4, 2), Some((1, 2)));
+    assert_eq!(board.get_decoded(), vec![
+      vec![2, 0, 4],
+      vec![0, 8, 4],
+      vec![2, 2, 2],
+    ]);
+    assert_eq!(board.add_tile_to_position(2, 2), None);
+  }
+
+
+  // Test add_random_tile()
+
+  #[test]
+  pub fn test_board_add_random_tile() {
+    let mut board = Board::from_decoded(&[
+      vec![2, 4, 8, 16, 32],
+      vec![64, 0, 128, 256, 0],
+    ]);
+    let config = GameConfig::builder().prob_tile2(0.).build().unwrap();
+    let mut rng = GameRng::seed_from_u64(1);
+
+    // the coordinates are those of the board cell written, whatever its rank among the empty cells
+    let (tile, (i, j)) = add_random_tile(&mut board, &mut rng, &config).unwrap();
+    assert_eq!(tile, 4);
+    assert_eq!(board.get_decoded()[i][j], 4);
+    assert!((i, j) == (1, 1) || (i, j) == (1, 4));
+
+    add_random_tile(&mut board, &mut rng, &config).unwrap();
+    assert_eq!(board.get_zeros(), 0);
+    assert_eq!(add_random_tile(&mut board, &mut rng, &config), None);
+  }
+
+  #[test]
+  pub fn test_board_matches_grid_on_default_size() {
+    let rows = [
+      [0, 2, 2, 0],
+      [2, 2, 2, 2],
+      [0, 0, 4, 0],
+      [8, 0, 4, 2],
+    ];
+
+    let precomputed_moves = moves::precomputed_moves();
+    let grid = Grid::from_decoded(&rows);
+    let board = Board::from_decoded(&rows.iter().map(|row| row.to_vec()).collect::<Vec<_>>());
+
+    use PlayerMove::{Up, Left, Right, Down};
+    for &player_move in [Up, Left, Right, Down].iter() {
+      let grid_result = moves::process_grid_stacking(player_move, &grid, precomputed_moves);
+      let board_result = process_board_stacking(player_move, &board);
+
+      assert_eq!(board_result.get_new_board().get_state(), &grid_result.get_new_grid().get_state()[..]);
+      assert_eq!(board_result.get_delta_score(), grid_result.get_delta_score());
+      assert_eq!(board_result.get_merges(), grid_result.get_merges());
+      for i in 0..GRID_SIDE {
+        assert_eq!(board_result.get_destinations()[i], grid_result.get_destination_grid()[i].to_vec());
+      }
+    }
+  }
+
+
+  // Test process_board_stacking()
+
+  #[test]
+  pub fn test_board_up_move_rectangular() {
+    let board = Board::from_decoded(&[
+      vec![2, 0, 4, 8, 2],
+      vec![2, 4, 0, 8, 0],
+      vec![4, 4, 4, 0, 2],
+    ]);
+
+    let result = process_board_stacking(PlayerMove::Up, &board);
+
+    assert_eq!(result.get_new_board().get_decoded(), vec![
+      vec![4, 8, 8, 16, 4],
+      vec![4, 0, 0, 0, 0],
+      vec![0, 0, 0, 0, 0],
+    ]);
+    assert_eq!(result.get_delta_score(), 40);
+    assert_eq!(*result.get_destinations(), vec![
+      vec![0, 0, 0, 0, 0],
+      vec![-1, -1, 0, -1, 0],
+      vec![-1, -2, -2, 0, -2],
+    ]);
+  }
+
+  #[test]
+  pub fn test_board_right_move_small() {
+    let board = Board::from_decoded(&[
+      vec![2, 2, 2],
+      vec![0, 4, 0],
+      vec![8, 0, 8],
+    ]);
+
+    let result = process_board_stacking(PlayerMove::Right, &board);
+
+    assert_eq!(result.get_new_board().get_decoded(), vec![
+      vec![0, 2, 4],
+      vec![0, 0, 4],
+      vec![0, 0, 16],
+    ]);
+    assert_eq!(result.get_delta_score(), 20);
+    assert_eq!(*result.get_destinations(), vec![
+      vec![1, 1, 0],
+      vec![0, 1, 0],
+      vec![2, 0, 0],
+    ]);
+  }
+
+  #[test]
+  pub fn test_board_down_move_large() {
+    let mut rows = vec![vec![0; 6]; 6];
+    rows[0][5] = 2;
+    rows[3][5] = 2;
+    rows[1][0] = 1024;
+
+    let result = process_board_stacking(PlayerMove::Down, &Board::from_decoded(&rows));
+
+    let mut expected = vec![vec![0; 6]; 6];
+    expected[5][5] = 4;
+    expected[5][0] = 1024;
+
+    assert_eq!(result.get_new_board().get_decoded(), expected);
+    assert_eq!(result.get_delta_score(), 4);
+    assert_eq!(result.get_destinations()[0][5], 5);
+    assert_eq!(result.get_destinations()[3][5], 2);
+    assert_eq!(result.get_destinations()[1][0], 4);
+    assert_eq!(result.get_merges(), &[MergeEvent::new([(0, 5), (3, 5)], (5, 5), 4)]);
+  }
+
+  #[test]
+  pub fn test_board_merges_rectangular() {
+    let board = Board::from_decoded(&[
+      vec![2, 2, 2, 2, 4, 4],
+      vec![0, 8, 0, 8, 0, 0],
+    ]);
+
+    let left = process_board_stacking(PlayerMove::Left, &board);
+    assert_eq!(left.get_merges(), &[
+      MergeEvent::new([(0, 0), (0, 1)], (0, 0), 4),
+      MergeEvent::new([(0, 2), (0, 3)], (0, 1), 4),
+      MergeEvent::new([(0, 4), (0, 5)], (0, 2), 8),
+      MergeEvent::new([(1, 1), (1, 3)], (1, 0), 16),
+    ]);
+
+    // the sources are listed from the first cell of the line whatever the direction
+    let right = process_board_stacking(PlayerMove::Right, &board);
+    assert_eq!(right.get_merges(), &[
+      MergeEvent::new([(0, 0), (0, 1)], (0, 3), 4),
+      MergeEvent::new([(0, 2), (0, 3)], (0, 4), 4),
+      MergeEvent::new([(0, 4), (0, 5)], (0, 5), 8),
+      MergeEvent::new([(1, 1), (1, 3)], (1, 5), 16),
+    ]);
+    assert_eq!(right.get_delta_score(), right.get_merges().iter().map(|merge| merge.get_score()).sum::<u64>());
+  }
+
+
+  // Test GameBoard
+
+  #[test]
+  pub fn test_board_legal_moves() {
+    let board = Board::from_decoded(&[
+      vec![2, 4, 8],
+      vec![0, 0, 0],
+    ]);
+
+    use PlayerMove::{Up, Down};
+    assert_eq!(board.legal_moves().into_iter().collect::<Vec<_>>(), vec![Down]);
+    assert_eq!(board.stack(Up).get_new_board(), &board);
+    assert!(!board.stack(Up).is_effective());
+  }
+
+  #[test]
+  pub fn test_board_fits() {
+    let board = Board::from_decoded(&[
+      vec![2, 4, 8],
+      vec![0, 0, 0],
+    ]);
+    let grid = Grid::from_decoded(&[[2, 0, 0, 0]; GRID_SIDE]);
+
+    assert!(board.fits(2, 3));
+    assert!(!board.fits(3, 2));
+    assert!(grid.fits(GRID_SIDE, GRID_SIDE));
+    assert!(!grid.fits(3, 3));
+    assert!(!Grid::new(&[1 << (ENCODING_BITS * GRID_SIDE); GRID_SIDE]).fits(GRID_SIDE, GRID_SIDE));
+  }
+
+  #[test]
+  #[should_panic]
+  pub fn test_grid_empty_other_size() {
+    <Grid<EncodedGrid> as GameBoard>::empty(3, 4);
+  }
+
+
+  // Test Board::is_game_over()
+
+  #[test]
+  pub fn test_board_is_game_over() {
+    let over = Board::from_decoded(&[
+      vec![2, 4, 2],
+      vec![4, 2, 4],
+    ]);
+    let not_over = Board::from_decoded(&[
+      vec![2, 4, 2],
+      vec![4, 2, 2],
+    ]);
+
+    assert!(over.is_game_over());
+    assert!(!not_over.is_game_over());
+  }
+
+}
@@ -0,0 +1,243 @@
+//! # `config` module
+//!
+//! This module defines the rule parameters of a `Game`, set per game through a `GameConfig`.
+//! The defaults are the compile-time constants in `game::core`, which the rest of the crate is optimized for.
+//! Games on a `Grid<EncodedGrid>` only play the default `GRID_SIDE` x `GRID_SIDE` size, the other board sizes are played on a `Board`, see `game::board`.
+
+use std::{error, fmt, fmt::Display};
+
+use crate::game::core::*;
+use crate::game::board::MIN_BOARD_SIDE;
+
+
+//------------------------------------------------
+// Types and Definitions
+//------------------------------------------------
+
+/// The rules of a game: board size, spawn probability of a 2 tile, victory tile, undo depth and number of tiles on the starting grid.
+/// Built with `GameConfig::builder()`, any parameter not set keeps its default.
+#[derive(Copy, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GameConfig {
+  rows: usize,
+  cols: usize,
+  prob_tile2: f64,
+  victory_threshold: EntryType,
+  history_length: usize,
+  starting_tiles: usize,
+}
+
+/// Builder of a `GameConfig`, validated by `build()`.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct GameConfigBuilder {
+  config: GameConfig,
+}
+
+/// Errors raised when building an invalid `GameConfig`.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum ConfigError {
+  InvalidBoardSize(usize, usize),
+  InvalidProbability(f64),
+  InvalidVictoryThreshold(EntryType),
+  InvalidStartingTiles(usize),
+}
+
+
+//------------------------------------------------
+// Implementations
+//------------------------------------------------
+
+// Inherent
+
+impl GameConfig {
+
+  /// Returns a builder starting from the default rules.
+  pub fn builder() -> GameConfigBuilder {
+    GameConfigBuilder {
+      config: GameConfig::default(),
+    }
+  }
+
+  // Getters
+  pub fn get_rows(&self) -> usize { self.rows }
+  pub fn get_cols(&self) -> usize { self.cols }
+  pub fn get_prob_tile2(&self) -> f64 { self.prob_tile2 }
+  pub fn get_victory_threshold(&self) -> EntryType { self.victory_threshold }
+  pub fn get_history_length(&self) -> usize { self.history_length }
+  pub fn get_starting_tiles(&self) -> usize { self.starting_tiles }
+
+  /// Returns the exponent of the victory tile, as stored in encoded grids.
+  pub fn get_log2_victory_threshold(&self) -> usize { self.victory_threshold.trailing_zeros() as usize }
+
+  /// Checks that the rules describe a playable game.
+  pub fn validate(&self) -> Result<(), ConfigError> {
+    if !(MIN_BOARD_SIDE..=MAX_GRID_SIDE).contains(&self.rows) || !(MIN_BOARD_SIDE..=MAX_GRID_SIDE).contains(&self.cols) {
+      return Err(ConfigError::InvalidBoardSize(self.rows, self.cols));
+    }
+
+    if !(0.0..=1.0).contains(&self.prob_tile2) {
+      return Err(ConfigError::InvalidProbability(self.prob_tile2));
+    }
+
+    // the victory tile must be reachable by merging tiles and fit the precomputed moves
+    if !self.victory_threshold.is_power_of_two() || self.victory_threshold < 4 || self.victory_threshold > LARGEST_TILE {
+      return Err(ConfigError::InvalidVictoryThreshold(self.victory_threshold));
+    }
+
+    if self.starting_tiles == 0 || self.starting_tiles >= self.rows * self.cols {
+      return Err(ConfigError::InvalidStartingTiles(self.starting_tiles));
+    }
+
+    Ok(())
+  }
+
+}
+
+impl GameConfigBuilder {
+
+  /// Sets the number of rows and columns of the board, each between `MIN_BOARD_SIDE` and `MAX_GRID_SIDE`.
+  pub fn board_size(mut self, rows: usize, cols: usize) -> Self {
+    self.config.rows = rows;
+    self.config.cols = cols;
+    self
+  }
+
+  /// Sets the probability that a spawned tile is a 2 rather than a 4.
+  pub fn prob_tile2(mut self, prob_tile2: f64) -> Self {
+    self.config.prob_tile2 = prob_tile2;
+    self
+  }
+
+  /// Sets the tile to reach for victory, a power of 2 between 4 and `LARGEST_TILE`.
+  pub fn victory_threshold(mut self, victory_threshold: EntryType) -> Self {
+    self.config.victory_threshold = victory_threshold;
+    self
+  }
+
+  /// Sets how many moves can be undone.
+  pub fn history_length(mut self, history_length: usize) -> Self {
+    self.config.history_length = history_length;
+    self
+  }
+
+  /// Sets how many random tiles are spawned on the starting grid.
+  pub fn starting_tiles(mut self, starting_tiles: usize) -> Self {
+    self.config.starting_tiles = starting_tiles;
+    self
+  }
+
+  /// Builds the config, checking its parameters.
+  pub fn build(self) -> Result<GameConfig, ConfigError> {
+    self.config.validate()?;
+
+    Ok(self.config)
+  }
+
+}
+
+
+// Default
+
+impl Default for GameConfig {
+
+  fn default() -> Self {
+    GameConfig {
+      rows: GRID_SIDE,
+      cols: GRID_SIDE,
+      prob_tile2: PROB_TILE2,
+      victory_threshold: VICTORY_THRESHOLD,
+      history_length: HISTORY_LENGTH,
+      starting_tiles: 1,
+    }
+  }
+
+}
+
+
+// Display
+
+impl Display for ConfigError {
+
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      ConfigError::InvalidBoardSize(rows, cols) => write!(f, "board size {}x{} has a side not between {} and {}", rows, cols, MIN_BOARD_SIDE, MAX_GRID_SIDE),
+      ConfigError::InvalidProbability(prob) => write!(f, "tile spawn probability {} is not between 0 and 1", prob),
+      ConfigError::InvalidVictoryThreshold(tile) => write!(f, "victory tile {} is not a power of 2 between 4 and {}", tile, LARGEST_TILE),
+      ConfigError::InvalidStartingTiles(count) => write!(f, "{} starting tiles don't fit the board", count),
+    }
+  }
+
+}
+
+
+// Error
+
+impl error::Error for ConfigError {}
+
+
+//------------------------------------------------
+// Unit tests
+//------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+
+  use super::*;
+
+  // Test GameConfig::default()
+
+  #[test]
+  pub fn test_default_config() {
+    let config = GameConfig::default();
+
+    assert_eq!((config.get_rows(), config.get_cols()), (GRID_SIDE, GRID_SIDE));
+    assert_eq!(config.get_prob_tile2(), PROB_TILE2);
+    assert_eq!(config.get_victory_threshold(), VICTORY_THRESHOLD);
+    assert_eq!(config.get_history_length(), HISTORY_LENGTH);
+    assert_eq!(config.get_starting_tiles(), 1);
+    assert_eq!(config.get_log2_victory_threshold(), 11);
+    assert_eq!(GameConfig::builder().build(), Ok(config));
+  }
+
+
+  // Test GameConfigBuilder
+
+  #[test]
+  pub fn test_builder() {
+    let config = GameConfig::builder()
+      .prob_tile2(0.5)
+      .victory_threshold(512)
+      .history_length(0)
+      .starting_tiles(2)
+      .build()
+      .unwrap();
+
+    assert_eq!(config.get_prob_tile2(), 0.5);
+    assert_eq!(config.get_victory_threshold(), 512);
+    assert_eq!(config.get_log2_victory_threshold(), 9);
+    assert_eq!(config.get_history_length(), 0);
+    assert_eq!(config.get_starting_tiles(), 2);
+  }
+
+  #[test]
+  pub fn test_builder_invalid() {
+    assert_eq!(GameConfig::builder().prob_tile2(1.5).build(), Err(ConfigError::InvalidProbability(1.5)));
+    assert_eq!(GameConfig::builder().victory_threshold(1000).build(), Err(ConfigError::InvalidVictoryThreshold(1000)));
+    assert_eq!(GameConfig::builder().victory_threshold(2).build(), Err(ConfigError::InvalidVictoryThreshold(2)));
+    assert_eq!(GameConfig::builder().victory_threshold(0).build(), Err(ConfigError::InvalidVictoryThreshold(0)));
+    assert_eq!(GameConfig::builder().victory_threshold(LARGEST_TILE).build().unwrap().get_log2_victory_threshold(), 31);
+    assert_eq!(GameConfig::builder().starting_tiles(0).build(), Err(ConfigError::InvalidStartingTiles(0)));
+    assert_eq!(GameConfig::builder().starting_tiles(GRID_SIDE * GRID_SIDE).build(), Err(ConfigError::InvalidStartingTiles(GRID_SIDE * GRID_SIDE)));
+  }
+
+  #[test]
+  pub fn test_builder_board_size() {
+    let config = GameConfig::builder().board_size(3, 5).starting_tiles(14).build().unwrap();
+
+    assert_eq!((config.get_rows(), config.get_cols()), (3, 5));
+    assert_eq!(GameConfig::builder().board_size(3, 3).starting_tiles(9).build(), Err(ConfigError::InvalidStartingTiles(9)));
+    assert_eq!(GameConfig::builder().board_size(1, 4).build(), Err(ConfigError::InvalidBoardSize(1, 4)));
+    assert_eq!(GameConfig::builder().board_size(4, MAX_GRID_SIDE + 1).build(), Err(ConfigError::InvalidBoardSize(4, MAX_GRID_SIDE + 1)));
+  }
+
+}
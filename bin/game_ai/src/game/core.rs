@@ -3,9 +3,13 @@
 //! This module contains the core types and definitions for the entire project.
 
 use std::{fmt, fmt::Display};
+use std::convert::TryFrom;
+use std::hash::{Hash, Hasher};
 use std::ops::{Index, IndexMut};
 
 use crate::encoding;
+use crate::encoding::Decode;
+use crate::error::GridError;
 
 
 //------------------------------------------------
@@ -15,11 +19,16 @@ use crate::encoding;
 // These parameters are kept const and never meant to be changed.
 // Changing it would possibly break the encoding and other parts of the code that were optimized for these parameters.
 pub const GRID_SIDE: usize = 4;
-pub const LARGEST_TILE: EntryType = 65536;
+pub const MAX_GRID_SIDE: usize = 6; // largest row that fits ENCODING_BITS per tile in an EncodedEntryType
+pub const LARGEST_TILE: EntryType = 1 << 31; // largest tile ENCODING_BITS can hold, tiles of this value don't merge any further
 pub const ENCODING_BITS: usize = 5;
-pub const PROB_TILE2: f64 = 0.9;
+pub const ENCODING_TILE_MASK: EncodedEntryType = (1 << ENCODING_BITS) - 1; // mask of the exponent of a single tile in an encoded row
+pub const TILE_EXPONENTS: usize = 1 << ENCODING_BITS; // number of tile exponents representable by the encoding, 0 for empty cells included
+pub const PROB_TILE2: f64 = 0.9; // default rules, set per game through GameConfig
 pub const VICTORY_THRESHOLD: EntryType = 2048;
 pub const HISTORY_LENGTH: usize = 20;
+pub const BITBOARD_BITS: usize = 4;
+pub const BITBOARD_LARGEST_TILE: EntryType = 32768;
 
 
 // TYPES
@@ -33,12 +42,23 @@ pub type Array2D<T> = [Array1D<T>; GRID_SIDE];
 
 pub type EncodedGrid = Array1D<EncodedEntryType>;
 pub type DestinationsGrid = Array2D<DestEntryType>;
+pub type BitboardGrid = u64;
+
+// Bitboard masks, the tile in row i and column j is stored in the BITBOARD_BITS starting at bit BITBOARD_BITS * (GRID_SIDE * i + j)
+const BITBOARD_ROW_BITS: usize = BITBOARD_BITS * GRID_SIDE;
+const BITBOARD_ROW_MASK: BitboardGrid = 0xFFFF;
+const BITBOARD_COLUMN_MASK: BitboardGrid = 0x000F_000F_000F_000F;
+const BITBOARD_TILE_LOW_BITS: BitboardGrid = 0x1111_1111_1111_1111;
+
+// Lowest bit of each tile of an encoded row, used to count the tiles of a row in parallel
+const ENCODING_TILE_LOW_BITS: EncodedEntryType = low_bits_mask();
 
 
 // DATA STRUCTURES
 
 /// Generic grid type encapsulating it's state.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(transparent))]
 pub struct Grid<T: GridState> {
   state: T,
 }
@@ -49,7 +69,7 @@ pub struct Grid<T: GridState> {
 //------------------------------------------------
 
 /// Marker trait to label the types allowed for `Grid<T: GridState>`.
-pub trait GridState: Copy + IndexMut<usize> + Eq {}
+pub trait GridState: Copy + Eq {}
 
 /// Trait for transposing the grid
 pub trait Transpose {
@@ -67,6 +87,17 @@ pub trait Reverse {
 
 }
 
+/// Trait for grids whose rows can be read and written as lines encoded with `ENCODING_BITS` per tile, as in the precomputed moves.
+pub trait EncodedLines {
+
+  /// Returns row `i` as an encoded line.
+  fn get_line(&self, i: usize) -> EncodedEntryType;
+
+  /// Writes the encoded `line` to row `i`. Returns `false`, leaving the row untouched, if the grid can't represent the line.
+  fn set_line(&mut self, i: usize, line: EncodedEntryType) -> bool;
+
+}
+
 
 //------------------------------------------------
 // Implementations
@@ -99,99 +130,292 @@ impl Grid<EncodedGrid> {
     }
   }
 
+  /// Constructor from decoded tiles coming from external input, rejecting tiles that aren't valid.
+  pub fn try_from_decoded(grid: &Array2D<EntryType>) -> Result<Self, GridError> {
+    Ok(Self {
+      state: encoding::try_encode_grid(grid)?,
+    })
+  }
+
+  /// Returns the exponent of the tile in row `row` and column `col`, as stored in the encoding, 0 for an empty cell.
+  /// Panics if the cell is outside the grid.
+  pub fn get_exponent(&self, row: usize, col: usize) -> EncodedEntryType {
+    assert_cell(row, col);
+    (self.state[row] >> (ENCODING_BITS * col)) & ENCODING_TILE_MASK
+  }
+
+  /// Returns the tile in row `row` and column `col`, 0 for an empty cell.
+  /// Panics if the cell is outside the grid.
+  pub fn get_tile(&self, row: usize, col: usize) -> EntryType {
+    encoding::decode_tile(self.get_exponent(row, col))
+  }
+
+  /// Replaces the tile in row `row` and column `col` with `tile`, a power of 2 up to `LARGEST_TILE` or 0 to empty the cell.
+  /// Panics if the cell is outside the grid.
+  pub fn set_tile(&mut self, row: usize, col: usize, tile: EntryType) -> &mut Self {
+    assert_cell(row, col);
+    debug_assert!(tile == 0 || tile.is_power_of_two(), "tile {} is not a power of 2", tile);
+
+    let exponent = match tile {
+      0 => 0,
+      _ => tile.trailing_zeros(),
+    };
+
+    self.state[row] = (self.state[row] & !(ENCODING_TILE_MASK << (ENCODING_BITS * col))) | (exponent << (ENCODING_BITS * col));
+
+    self
+  }
+
+  /// Returns an iterator over the tiles as `(row, col, tile)` in reading order, empty cells included as 0.
+  pub fn tiles(&self) -> impl Iterator<Item = (usize, usize, EntryType)> + '_ {
+    (0..GRID_SIDE * GRID_SIDE).map(move |k| (k / GRID_SIDE, k % GRID_SIDE, self.get_tile(k / GRID_SIDE, k % GRID_SIDE)))
+  }
+
+  /// Returns an iterator over the coordinates `(row, col)` of the empty cells in reading order.
+  pub fn empty_cells(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+    (0..GRID_SIDE * GRID_SIDE)
+      .map(|k| (k / GRID_SIDE, k % GRID_SIDE))
+      .filter(move |&(i, j)| self.get_exponent(i, j) == 0)
+  }
+
+  /// Returns the tiles of row `row` from left to right.
+  pub fn get_row_tiles(&self, row: usize) -> Array1D<EntryType> {
+    let mut tiles = [0; GRID_SIDE];
+
+    for (j, tile) in tiles.iter_mut().enumerate() {
+      *tile = self.get_tile(row, j);
+    }
+
+    tiles
+  }
+
+  /// Returns the tiles of column `col` from top to bottom.
+  pub fn get_column_tiles(&self, col: usize) -> Array1D<EntryType> {
+    let mut tiles = [0; GRID_SIDE];
+
+    for (i, tile) in tiles.iter_mut().enumerate() {
+      *tile = self.get_tile(i, col);
+    }
+
+    tiles
+  }
+
+  /// Returns the largest tile, 0 for an empty grid.
+  pub fn max_tile(&self) -> EntryType {
+    self.tiles().map(|(_, _, tile)| tile).max().unwrap_or(0)
+  }
+
+  /// Returns how many cells hold `tile`, so `count_of(0)` is the number of empty cells.
+  pub fn count_of(&self, tile: EntryType) -> usize {
+    self.tiles().filter(|&(_, _, t)| t == tile).count()
+  }
+
   /// Gets the number of zeros.
   pub fn get_zeros(&self) -> usize {
-    let mut count: usize = 0;
-    let mut bit_mask: EncodedEntryType;
+    let mut occupied = 0;
 
     for i in 0..GRID_SIDE {
+      occupied += occupied_tiles(self.state[i]);
+    }
 
-      // initial bit mask for the first encoded tile starting from the least significant ENCODING_BITS number of bits
-      bit_mask = (ENCODING_BITS as f64).exp2() as EncodedEntryType - 1;
+    GRID_SIDE * GRID_SIDE - occupied
+  }
 
-      // for each encoded tile in a single EncodedEntryType (i.e. u32)
-      for _ in 0..GRID_SIDE {
+  /// adds a "new_tile" value to a certain "position" in the listed empty tiles in reading order within the grid starting from 0 as the first index
+  /// and returns the (row, col) coordinates of the cell written, `None` if there are not enough empty tiles.
+  pub fn add_tile_to_position(&mut self, new_tile: EntryType, position: isize) -> Option<(usize, usize)> {
 
-        // check if the bits for the encoded number are zero by masking all the other bits to 0
-        if self.state[i] & bit_mask == 0 {
-          count += 1;
-        }
+    let cell = self.empty_cells().nth(position.max(0) as usize);
 
-        // shift bit mask to the next tile
-        bit_mask <<= ENCODING_BITS;
-      }
+    if let Some((i, j)) = cell {
+      self.set_tile(i, j, new_tile);
     }
 
-    count
+    cell
   }
 
-  /// adds a "new_tile" value to a certain "position" in the listed empty tiles in reading order within the grid starting from 0 as the first index
-  pub fn add_tile_to_position(&mut self, new_tile: EntryType, mut position: isize) -> &mut Self {
-  
-    // TODO: OPTIMIZE ADDITION TROUGH DIRECT ENCODING
-    let base_mask = (ENCODING_BITS as f64).exp2() as EncodedEntryType - 1;
-    let mut mask_j: EncodedEntryType;
-  
-    // Loop through all the empty tiles 
+  /// Returns the sum of the elements in the `Grid`, wide enough for grids full of `LARGEST_TILE`s.
+  pub fn get_sum(&self) -> u64 {
+    self.tiles().map(|(_, _, tile)| tile as u64).sum()
+  }
+
+}
+
+impl Grid<DestinationsGrid> {
+
+  /// Changes the sign of the entries in place and returns the mutable reference to itself for chaining.
+  pub fn change_sign(&mut self) -> &mut Self {
+
     for i in 0..GRID_SIDE {
       for j in 0..GRID_SIDE {
-  
-        mask_j = base_mask << ENCODING_BITS * j;
-  
-        // Decrement the position counter only when an entry is zero
-        if self[i] & mask_j == 0 {
-          position -= 1;
-  
-          // When we exhaust the position counter we found the tile and break out of the loop
-          if position < 0 {
-            self[i] |= encoding::encode_tile(new_tile, j);
-            break;
-          }
-  
-        }
-  
+        self.state[i][j] *= -1;
       }
-  
-      if position < 0 { break; }
-  
     }
-  
+
     self
   }
 
-  /// Returns the sum of the elements in the `Grid`.
-  pub fn get_sum(&self) -> EntryType {
-    let mut sum: EntryType = 0;
+}
+
+
+impl Grid<BitboardGrid> {
+
+  /// Constructor from an encoded grid. Returns `None` if a tile is larger than `BITBOARD_LARGEST_TILE`.
+  pub fn from_encoded(grid: &Grid<EncodedGrid>) -> Option<Self> {
+    let mut bitboard = Grid::<BitboardGrid>::new(&0);
+
+    for i in 0..GRID_SIDE {
+      if !bitboard.set_line(i, grid[i]) {
+        return None;
+      }
+    }
+
+    Some(bitboard)
+  }
+
+  /// Returns the same grid encoded with `ENCODING_BITS` per tile.
+  pub fn to_encoded(&self) -> Grid<EncodedGrid> {
+    let mut state = [0; GRID_SIDE];
+
+    for (i, line) in state.iter_mut().enumerate() {
+      *line = self.get_line(i);
+    }
+
+    Grid::new(&state)
+  }
+
+  /// Returns row `i` packed with `BITBOARD_BITS` per tile.
+  pub fn get_row(&self, i: usize) -> u16 {
+    ((self.state >> (BITBOARD_ROW_BITS * i)) & BITBOARD_ROW_MASK) as u16
+  }
+
+  /// Replaces row `i` with a row packed with `BITBOARD_BITS` per tile.
+  pub fn set_row(&mut self, i: usize, row: u16) -> &mut Self {
+    self.state = (self.state & !(BITBOARD_ROW_MASK << (BITBOARD_ROW_BITS * i))) | ((row as BitboardGrid) << (BITBOARD_ROW_BITS * i));
+
+    self
+  }
+
+  /// Returns the exponent of the tile in row `row` and column `col`, 0 for an empty cell.
+  /// Panics if the cell is outside the grid.
+  pub fn get_exponent(&self, row: usize, col: usize) -> EncodedEntryType {
+    assert_cell(row, col);
+    ((self.state >> (BITBOARD_BITS * (GRID_SIDE * row + col))) & 0xF) as EncodedEntryType
+  }
+
+  /// Replaces the tile in row `row` and column `col` with `tile`, a power of 2 up to `BITBOARD_LARGEST_TILE` or 0 to empty the cell.
+  /// Panics if the cell is outside the grid.
+  pub fn set_tile(&mut self, row: usize, col: usize, tile: EntryType) -> &mut Self {
+    assert_cell(row, col);
+    debug_assert!(tile == 0 || (tile.is_power_of_two() && tile <= BITBOARD_LARGEST_TILE), "tile {} can't be held by a bitboard", tile);
+
+    let shift = BITBOARD_BITS * (GRID_SIDE * row + col);
+    let exponent = match tile {
+      0 => 0,
+      _ => tile.trailing_zeros() as BitboardGrid,
+    };
 
-    let mut mask_j = (ENCODING_BITS as f64).exp2() as EncodedEntryType - 1;
+    self.state = (self.state & !(0xF << shift)) | (exponent << shift);
 
-    for j in 0..GRID_SIDE {
-      for i in 0..GRID_SIDE {
-        if self.state[i] & mask_j != 0 {
-          sum += (((self.state[i] & mask_j) >> ENCODING_BITS * j) as f64).exp2() as EntryType;
+    self
+  }
+
+  /// Returns column `j` packed like a row, with the tile in row `i` at the `i`-th position.
+  pub fn get_column(&self, j: usize) -> u16 {
+    let column = (self.state >> (BITBOARD_BITS * j)) & BITBOARD_COLUMN_MASK;
+
+    // gather the tiles at bits 0, 16, 32, 48 into bits 0, 4, 8, 12
+    ((column | (column >> 12) | (column >> 24) | (column >> 36)) & BITBOARD_ROW_MASK) as u16
+  }
+
+  /// Gets the number of zeros.
+  pub fn get_zeros(&self) -> usize {
+
+    // fold each tile onto its lowest bit, which is then set only for non-empty tiles
+    let mut occupied = self.state | (self.state >> 2);
+    occupied |= occupied >> 1;
+
+    (!occupied & BITBOARD_TILE_LOW_BITS).count_ones() as usize
+  }
+
+  /// adds a "new_tile" value to a certain "position" in the listed empty tiles in reading order within the grid starting from 0 as the first index
+  /// and returns the (row, col) coordinates of the cell written, `None` if there are not enough empty tiles.
+  /// Panics if `new_tile` is not a tile a bitboard can hold, from 2 to `BITBOARD_LARGEST_TILE`.
+  pub fn add_tile_to_position(&mut self, new_tile: EntryType, mut position: isize) -> Option<(usize, usize)> {
+    assert!(new_tile > 1 && new_tile.is_power_of_two() && new_tile <= BITBOARD_LARGEST_TILE, "tile {} can't be added to a bitboard", new_tile);
+
+    for k in 0..(GRID_SIDE * GRID_SIDE) {
+      let (i, j) = (k / GRID_SIDE, k % GRID_SIDE);
+
+      // Decrement the position counter only when an entry is zero
+      if self.get_exponent(i, j) == 0 {
+        position -= 1;
+
+        if position < 0 {
+          self.set_tile(i, j, new_tile);
+          return Some((i, j));
         }
       }
 
-      mask_j <<= ENCODING_BITS;
     }
-    
+
+    None
+  }
+
+  /// Returns the sum of the elements in the `Grid`.
+  pub fn get_sum(&self) -> u64 {
+    let mut sum = 0;
+
+    for k in 0..(GRID_SIDE * GRID_SIDE) {
+      let exponent = (self.state >> (BITBOARD_BITS * k)) & 0xF;
+
+      if exponent != 0 {
+        sum += 1 << exponent;
+      }
+    }
+
     sum
   }
 
 }
 
-impl Grid<DestinationsGrid> {
 
-  /// Changes the sign of the entries in place and returns the mutable reference to itself for chaining.
-  pub fn change_sign(&mut self) -> &mut Self {
+// EncodedLines
 
-    for i in 0..GRID_SIDE {
-      for j in 0..GRID_SIDE {
-        self.state[i][j] *= -1;
-      }
+impl EncodedLines for Grid<EncodedGrid> {
+
+  fn get_line(&self, i: usize) -> EncodedEntryType {
+    self.state[i]
+  }
+
+  fn set_line(&mut self, i: usize, line: EncodedEntryType) -> bool {
+    self.state[i] = line;
+
+    true
+  }
+
+}
+
+impl EncodedLines for Grid<BitboardGrid> {
+
+  fn get_line(&self, i: usize) -> EncodedEntryType {
+    let row = self.get_row(i) as EncodedEntryType;
+
+    // spread each tile from BITBOARD_BITS to ENCODING_BITS
+    (row & 0xF) | ((row & 0xF0) << 1) | ((row & 0xF00) << 2) | ((row & 0xF000) << 3)
+  }
+
+  fn set_line(&mut self, i: usize, line: EncodedEntryType) -> bool {
+
+    // the most significant bit of each encoded tile must be zero to fit BITBOARD_BITS
+    if line & !0x7BDEF != 0 {
+      return false;
     }
 
-    self
+    let row = (line & 0xF) | ((line >> 1) & 0xF0) | ((line >> 2) & 0xF00) | ((line >> 3) & 0xF000);
+    self.set_row(i, row as u16);
+
+    true
   }
 
 }
@@ -208,6 +432,55 @@ impl encoding::Decode for Grid<EncodedGrid> {
 
 }
 
+impl encoding::Decode for Grid<BitboardGrid> {
+  type Output = Array2D<EntryType>;
+
+  fn get_decoded(&self) -> Self::Output {
+    encoding::decode_grid(self.to_encoded().get_state())
+  }
+
+}
+
+
+// From
+
+impl From<Grid<BitboardGrid>> for Grid<EncodedGrid> {
+
+  fn from(grid: Grid<BitboardGrid>) -> Self {
+    grid.to_encoded()
+  }
+
+}
+
+
+// TryFrom
+
+impl TryFrom<Array2D<EntryType>> for Grid<EncodedGrid> {
+  type Error = GridError;
+
+  fn try_from(grid: Array2D<EntryType>) -> Result<Self, Self::Error> {
+    Grid::try_from_decoded(&grid)
+  }
+}
+
+impl TryFrom<EncodedGrid> for Grid<EncodedGrid> {
+  type Error = GridError;
+
+  fn try_from(state: EncodedGrid) -> Result<Self, Self::Error> {
+    encoding::try_decode_grid(&state)?;
+
+    Ok(Grid::new(&state))
+  }
+}
+
+impl TryFrom<Grid<EncodedGrid>> for Grid<BitboardGrid> {
+  type Error = GridError;
+
+  fn try_from(grid: Grid<EncodedGrid>) -> Result<Self, Self::Error> {
+    Grid::<BitboardGrid>::from_encoded(&grid).ok_or_else(|| GridError::TileTooLarge(grid.max_tile()))
+  }
+}
+
 
 // Transpose
 
@@ -215,7 +488,7 @@ impl Transpose for Grid<EncodedGrid> {
 
   fn transpose(&mut self) -> &mut Self {
 
-    let base_mask = (ENCODING_BITS as f64).exp2() as EncodedEntryType - 1;
+    let base_mask = ENCODING_TILE_MASK;
 
     let mut mask_i: EncodedEntryType;
     let mut mask_j: EncodedEntryType;
@@ -223,15 +496,15 @@ impl Transpose for Grid<EncodedGrid> {
     let mut tmp: EncodedEntryType;
 
     for i in 0..GRID_SIDE {
-      mask_i = base_mask << ENCODING_BITS * i;
+      mask_i = base_mask << (ENCODING_BITS * i);
 
       for j in (i + 1)..GRID_SIDE {
-        mask_j = base_mask << ENCODING_BITS * j;
+        mask_j = base_mask << (ENCODING_BITS * j);
         delta_pos = ENCODING_BITS * (j - i);
 
         tmp = (self.state[i] & mask_j) >> delta_pos;
-        self.state[i] = self.state[i] & !mask_j | (self.state[j] & mask_i) << delta_pos;
-        self.state[j] = self.state[j] & !mask_i | tmp;
+        self.state[i] = (self.state[i] & !mask_j) | ((self.state[j] & mask_i) << delta_pos);
+        self.state[j] = (self.state[j] & !mask_i) | tmp;
       }
     }
 
@@ -240,6 +513,20 @@ impl Transpose for Grid<EncodedGrid> {
 
 }
 
+impl Transpose for Grid<BitboardGrid> {
+
+  // Branch-free transpose swapping the tiles mirrored by the main diagonal by groups at the same distance
+  fn transpose(&mut self) -> &mut Self {
+    let x = self.state;
+
+    let a = (x & 0xF0F0_0F0F_F0F0_0F0F) | ((x & 0x0000_F0F0_0000_F0F0) << 12) | ((x & 0x0F0F_0000_0F0F_0000) >> 12);
+    self.state = (a & 0xFF00_FF00_00FF_00FF) | ((a & 0x00FF_00FF_0000_0000) >> 24) | ((a & 0x0000_0000_FF00_FF00) << 24);
+
+    self
+  }
+
+}
+
 impl Transpose for Grid<DestinationsGrid> {
 
   fn transpose(&mut self) -> &mut Self {
@@ -265,7 +552,7 @@ impl Reverse for Grid<EncodedGrid> {
 
   fn reverse(&mut self) -> &mut Self {
 
-    let base_mask = (ENCODING_BITS as f64).exp2() as EncodedEntryType - 1;
+    let base_mask = ENCODING_TILE_MASK;
 
     let mut mask_j: EncodedEntryType;
     let mut mask_n_j: EncodedEntryType;
@@ -275,13 +562,13 @@ impl Reverse for Grid<EncodedGrid> {
     for i in 0..GRID_SIDE {
 
       for j in 0..(GRID_SIDE / 2) {
-        mask_j = base_mask << ENCODING_BITS * j;
-        mask_n_j = base_mask << ENCODING_BITS * (GRID_SIDE - 1 - j);
+        mask_j = base_mask << (ENCODING_BITS * j);
+        mask_n_j = base_mask << (ENCODING_BITS * (GRID_SIDE - 1 - j));
         delta_pos = ENCODING_BITS * (GRID_SIDE - 1 - 2 * j);
 
         tmp = (self.state[i] & mask_j) << delta_pos;
-        self.state[i] = self.state[i] & !mask_j | (self.state[i] & mask_n_j) >> delta_pos;
-        self.state[i] = self.state[i] & !mask_n_j | tmp;
+        self.state[i] = (self.state[i] & !mask_j) | ((self.state[i] & mask_n_j) >> delta_pos);
+        self.state[i] = (self.state[i] & !mask_n_j) | tmp;
       }
     }
 
@@ -290,6 +577,20 @@ impl Reverse for Grid<EncodedGrid> {
 
 }
 
+impl Reverse for Grid<BitboardGrid> {
+
+  // Branch-free reverse swapping adjacent tiles and then adjacent pairs of tiles within each row
+  fn reverse(&mut self) -> &mut Self {
+    let x = self.state;
+
+    let a = ((x & 0x0F0F_0F0F_0F0F_0F0F) << 4) | ((x >> 4) & 0x0F0F_0F0F_0F0F_0F0F);
+    self.state = ((a & 0x00FF_00FF_00FF_00FF) << 8) | ((a >> 8) & 0x00FF_00FF_00FF_00FF);
+
+    self
+  }
+
+}
+
 impl Reverse for Grid<DestinationsGrid> {
 
   fn reverse(&mut self) -> &mut Self {
@@ -315,25 +616,32 @@ impl Display for Grid<EncodedGrid> {
 
   fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
 
-    write!(f, "GameGrid::state = [\n")?;
-    for i in 0..GRID_SIDE {
-      write!(f, "  {:?},\n", encoding::decode_line(self.state[i]))?;
+    writeln!(f, "GameGrid::state = [")?;
+    for row in self.get_decoded().iter() {
+      writeln!(f, "  {:?},", row)?;
     }
-    write!(f, "]\n")?;
+    writeln!(f, "]")?;
 
     Ok(())
   }
 }
 
+impl Display for Grid<BitboardGrid> {
+
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}", self.to_encoded())
+  }
+}
+
 impl Display for Grid<DestinationsGrid> {
 
   fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
 
-    write!(f, "GameGrid::state = [\n")?;
+    writeln!(f, "GameGrid::state = [")?;
     for i in 0..GRID_SIDE {
-      write!(f, "  {:?},\n", self.state[i])?;
+      writeln!(f, "  {:?},", self.state[i])?;
     }
-    write!(f, "]\n")?;
+    writeln!(f, "]")?;
 
     Ok(())
   }
@@ -403,10 +711,55 @@ impl<T: GridState> PartialEq for Grid<T> {
 impl<T: GridState> Eq for Grid<T> {}
 
 
+// Hash
+
+impl<T: GridState + Hash> Hash for Grid<T> {
+
+  fn hash<H: Hasher>(&self, state: &mut H) {
+    self.state.hash(state);
+  }
+
+}
+
+
 // GridState
 
 impl GridState for EncodedGrid {}
 impl GridState for DestinationsGrid {}
+impl GridState for BitboardGrid {}
+
+
+//------------------------------------------------
+// Functions
+//------------------------------------------------
+
+/// Counts the non-empty tiles of an encoded row, folding each tile onto its lowest bit so all tiles are checked at once.
+#[inline]
+pub fn occupied_tiles(line: EncodedEntryType) -> usize {
+  let mut folded = line | (line >> 1);
+  folded |= folded >> 2;
+  folded |= line >> 4;
+
+  (folded & ENCODING_TILE_LOW_BITS).count_ones() as usize
+}
+
+/// Panics unless `row` and `col` address a cell of the grid, since out of range indices would silently read or write the neighbouring tiles.
+fn assert_cell(row: usize, col: usize) {
+  assert!(row < GRID_SIDE && col < GRID_SIDE, "cell ({}, {}) is outside the grid", row, col);
+}
+
+/// Builds the mask with the lowest bit of each of the `MAX_GRID_SIDE` tiles of an encoded row set.
+const fn low_bits_mask() -> EncodedEntryType {
+  let mut mask = 0;
+  let mut j = 0;
+
+  while j < MAX_GRID_SIDE {
+    mask |= 1 << (ENCODING_BITS * j);
+    j += 1;
+  }
+
+  mask
+}
 
 
 //------------------------------------------------
@@ -445,6 +798,155 @@ mod tests {
     assert_eq!(grid.get_zeros(), 0);
   }
 
+  #[test]
+  pub fn test_gamegrid_get_set_tile() {
+    let mut grid = Grid::from_decoded(&[
+      [0, 0, 0, 2],
+      [0, 0, 0, 0],
+      [2, 4, 8, 16],
+      [4, 4, 4, LARGEST_TILE],
+    ]);
+
+    assert_eq!(grid.get_tile(0, 3), 2);
+    assert_eq!(grid.get_tile(1, 1), 0);
+    assert_eq!(grid.get_tile(2, 3), 16);
+    assert_eq!(grid.get_exponent(2, 3), 4);
+    assert_eq!(grid.get_tile(3, 3), LARGEST_TILE);
+
+    grid.set_tile(1, 1, 64).set_tile(0, 3, 0).set_tile(2, 0, 1024);
+
+    assert_eq!(grid, Grid::from_decoded(&[
+      [0, 0, 0, 0],
+      [0, 64, 0, 0],
+      [1024, 4, 8, 16],
+      [4, 4, 4, LARGEST_TILE],
+    ]));
+  }
+
+  #[test]
+  #[should_panic(expected = "outside the grid")]
+  pub fn test_gamegrid_get_tile_out_of_bounds() {
+    Grid::<EncodedGrid>::new(&[0; GRID_SIDE]).get_tile(0, GRID_SIDE);
+  }
+
+  #[test]
+  #[should_panic(expected = "outside the grid")]
+  pub fn test_gamegrid_set_tile_out_of_bounds() {
+    Grid::<EncodedGrid>::new(&[0; GRID_SIDE]).set_tile(GRID_SIDE, 0, 2);
+  }
+
+  #[test]
+  pub fn test_gamegrid_tiles_and_empty_cells() {
+    let grid = Grid::from_decoded(&[
+      [0, 0, 0, 2],
+      [0, 8, 0, 0],
+      [2, 2, 2, 2],
+      [4, 4, 4, 4],
+    ]);
+
+    let tiles: Vec<(usize, usize, EntryType)> = grid.tiles().collect();
+    assert_eq!(tiles.len(), GRID_SIDE * GRID_SIDE);
+    assert_eq!(tiles[3], (0, 3, 2));
+    assert_eq!(tiles[5], (1, 1, 8));
+    assert_eq!(tiles[15], (3, 3, 4));
+
+    let empty: Vec<(usize, usize)> = grid.empty_cells().collect();
+    assert_eq!(empty, vec![(0, 0), (0, 1), (0, 2), (1, 0), (1, 2), (1, 3)]);
+  }
+
+  #[test]
+  pub fn test_gamegrid_tile_statistics() {
+    let grid = Grid::from_decoded(&[
+      [0, 0, 0, 2],
+      [0, 8, 0, 0],
+      [2, 2, 2, 2],
+      [4, 4, 4, 128],
+    ]);
+
+    assert_eq!(grid.max_tile(), 128);
+    assert_eq!(grid.count_of(2), 5);
+    assert_eq!(grid.count_of(0), grid.get_zeros());
+    assert_eq!(grid.count_of(16), 0);
+    assert_eq!(grid.get_row_tiles(3), [4, 4, 4, 128]);
+    assert_eq!(grid.get_column_tiles(1), [0, 8, 2, 4]);
+    assert_eq!(Grid::<EncodedGrid>::new(&[0; GRID_SIDE]).max_tile(), 0);
+  }
+
+  #[test]
+  pub fn test_gamegrid_try_from_decoded() {
+    let decoded = [
+      [0, 2, 4, 8],
+      [4, 4, 4, 4],
+      [8, 8, 4, 4],
+      [8, 4, 2, LARGEST_TILE],
+    ];
+
+    assert_eq!(Grid::try_from_decoded(&decoded), Ok(Grid::from_decoded(&decoded)));
+    assert_eq!(Grid::<EncodedGrid>::try_from(decoded), Ok(Grid::from_decoded(&decoded)));
+
+    let mut invalid = decoded;
+    invalid[1][2] = 3;
+    assert_eq!(Grid::try_from_decoded(&invalid), Err(GridError::NotPowerOfTwo(3)));
+
+    invalid[1][2] = 1;
+    assert_eq!(Grid::<EncodedGrid>::try_from(invalid), Err(GridError::TileOfOne));
+  }
+
+  #[test]
+  pub fn test_gamegrid_try_from_encoded() {
+    let state = [100384, 67650, 67683, 33859];
+
+    assert_eq!(Grid::<EncodedGrid>::try_from(state), Ok(Grid::new(&state)));
+    assert_eq!(Grid::<EncodedGrid>::try_from([0, 1 << 20, 0, 0]), Err(GridError::CorruptRow(1 << 20)));
+  }
+
+  #[test]
+  pub fn test_bitboard_try_from() {
+    let grid = Grid::from_decoded(&[
+      [0, 2, 4, 8],
+      [4, 4, 4, 4],
+      [8, 8, 4, 4],
+      [8, 4, 2, 65536],
+    ]);
+
+    assert_eq!(Grid::<BitboardGrid>::try_from(grid), Err(GridError::TileTooLarge(65536)));
+
+    let mut grid = grid;
+    grid.set_tile(3, 3, BITBOARD_LARGEST_TILE);
+    assert_eq!(Grid::<BitboardGrid>::try_from(grid).unwrap().to_encoded(), grid);
+  }
+
+  #[test]
+  pub fn test_gamegrid_hash_map_key() {
+    use std::collections::HashMap;
+
+    let grid = Grid::from_decoded(&[
+      [0, 2, 4, 8],
+      [4, 4, 4, 4],
+      [8, 8, 4, 4],
+      [8, 4, 2, 2],
+    ]);
+    let mut visits = HashMap::new();
+
+    *visits.entry(grid).or_insert(0) += 1;
+    *visits.entry(Grid::new(grid.get_state())).or_insert(0) += 1;
+    *visits.entry(*grid.clone().transpose()).or_insert(0) += 1;
+
+    assert_eq!(visits.len(), 2);
+    assert_eq!(visits[&grid], 2);
+  }
+
+  #[test]
+  pub fn test_occupied_tiles_exhaustive() {
+    for line in 0..(1 << (ENCODING_BITS * GRID_SIDE)) {
+      let naive = (0..GRID_SIDE).filter(|&j| (line >> (ENCODING_BITS * j)) & ENCODING_TILE_MASK != 0).count();
+
+      assert_eq!(occupied_tiles(line), naive, "{:#x}", line);
+    }
+
+    assert_eq!(occupied_tiles(encoding::encode_line(&[2, 0, 4, 0, 0, LARGEST_TILE])), 3);
+  }
+
   #[test]
   pub fn test_gamegrid_transpose() {
     let mut grid = Grid::from_decoded(&[
@@ -499,7 +1001,26 @@ mod tests {
       [0, 0, 4, 2],
     ]);
 
-    assert_eq!(*grid.add_tile_to_position(2, 2).get_state(), *res.get_state());
+    assert_eq!(grid.add_tile_to_position(2, 2), Some((1, 3)));
+    assert_eq!(*grid.get_state(), *res.get_state());
+  }
+
+  #[test]
+  pub fn test_gamegrid_add_tile_to_position_sparse() {
+    let mut grid = Grid::from_decoded(&[
+      [2, 0, 0, 0],
+      [0, 0, 0, 0],
+      [0, 0, 0, 0],
+      [0, 0, 0, 0],
+    ]);
+
+    // the tile in (0, 0) shifts the 5th empty tile past (1, 0)
+    assert_eq!(grid.add_tile_to_position(4, 4), Some((1, 1)));
+    assert_eq!(grid.get_tile(1, 1), 4);
+
+    assert_eq!(grid.add_tile_to_position(2, 13), Some((3, 3)));
+    assert_eq!(grid.add_tile_to_position(2, 13), None);
+    assert_eq!(grid.get_zeros(), 13);
   }
 
   #[test]
@@ -514,6 +1035,132 @@ mod tests {
     assert_eq!(grid.get_sum(), 44)
   }
 
+  #[test]
+  pub fn test_gamegrid_get_sum_largest_tiles() {
+    let grid = Grid::from_decoded(&[[LARGEST_TILE; GRID_SIDE]; GRID_SIDE]);
+
+    assert_eq!(grid.get_sum(), (GRID_SIDE * GRID_SIDE) as u64 * LARGEST_TILE as u64)
+  }
+
+
+  // Grid<BitboardGrid>
+
+  #[test]
+  pub fn test_bitboard_encoding_round_trip() {
+    let grid = Grid::from_decoded(&[
+      [0, 2, 4, 8],
+      [16, 32, 64, 128],
+      [256, 512, 1024, 2048],
+      [4096, 8192, 16384, 32768],
+    ]);
+
+    let bitboard = Grid::<BitboardGrid>::from_encoded(&grid).unwrap();
+
+    assert_eq!(*bitboard.get_state(), 0xFEDC_BA98_7654_3210);
+    assert_eq!(Grid::<EncodedGrid>::from(bitboard), grid);
+  }
+
+  #[test]
+  pub fn test_bitboard_too_large_tile() {
+    assert!(Grid::<BitboardGrid>::from_encoded(&Grid::from_decoded(&[
+      [0, 0, 0, 0],
+      [0, 65536, 0, 0],
+      [0, 0, 0, 0],
+      [0, 0, 0, 0],
+    ])).is_none());
+  }
+
+  #[test]
+  pub fn test_bitboard_rows_and_columns() {
+    let mut bitboard = Grid::<BitboardGrid>::from_encoded(&Grid::from_decoded(&[
+      [0, 2, 4, 8],
+      [4, 4, 4, 4],
+      [8, 8, 4, 4],
+      [8, 4, 2, 2],
+    ])).unwrap();
+
+    assert_eq!(bitboard.get_row(0), 0x3210);
+    assert_eq!(bitboard.get_row(3), 0x1123);
+    assert_eq!(bitboard.get_column(0), 0x3320);
+    assert_eq!(bitboard.get_column(3), 0x1223);
+
+    bitboard.set_row(1, 0x0001);
+
+    assert_eq!(bitboard.to_encoded(), Grid::from_decoded(&[
+      [0, 2, 4, 8],
+      [2, 0, 0, 0],
+      [8, 8, 4, 4],
+      [8, 4, 2, 2],
+    ]));
+  }
+
+  #[test]
+  #[should_panic(expected = "outside the grid")]
+  pub fn test_bitboard_get_exponent_out_of_bounds() {
+    Grid::<BitboardGrid>::from_encoded(&Grid::new(&[0; GRID_SIDE])).unwrap().get_exponent(0, GRID_SIDE);
+  }
+
+  #[test]
+  #[should_panic(expected = "outside the grid")]
+  pub fn test_bitboard_set_tile_out_of_bounds() {
+    Grid::<BitboardGrid>::from_encoded(&Grid::new(&[0; GRID_SIDE])).unwrap().set_tile(GRID_SIDE, 0, 2);
+  }
+
+  #[test]
+  pub fn test_bitboard_get_zeros() {
+    let bitboard = Grid::<BitboardGrid>::from_encoded(&Grid::from_decoded(&[
+      [0, 0, 0, 2],
+      [0, 0, 0, 0],
+      [2, 32768, 2, 2],
+      [4, 4, 4, 4],
+    ])).unwrap();
+
+    assert_eq!(bitboard.get_zeros(), 7);
+    assert_eq!(Grid::<BitboardGrid>::new(&0).get_zeros(), 16);
+  }
+
+  #[test]
+  pub fn test_bitboard_transpose_and_reverse() {
+    let decoded = [
+      [0, 2, 4, 8],
+      [4, 16, 4, 4],
+      [8, 8, 1024, 4],
+      [8, 4, 2, 32768],
+    ];
+
+    let mut grid = Grid::from_decoded(&decoded);
+    let mut bitboard = Grid::<BitboardGrid>::from_encoded(&grid).unwrap();
+
+    assert_eq!(bitboard.transpose().to_encoded(), *grid.transpose());
+    assert_eq!(bitboard.reverse().to_encoded(), *grid.reverse());
+  }
+
+  #[test]
+  pub fn test_bitboard_add_tile_and_sum() {
+    let mut bitboard = Grid::<BitboardGrid>::from_encoded(&Grid::from_decoded(&[
+      [2, 4, 4, 0],
+      [4, 2, 0, 0],
+      [8, 8, 2, 2],
+      [0, 0, 4, 2],
+    ])).unwrap();
+
+    assert_eq!(bitboard.add_tile_to_position(4, 2), Some((1, 3)));
+
+    assert_eq!(bitboard.to_encoded(), Grid::from_decoded(&[
+      [2, 4, 4, 0],
+      [4, 2, 0, 4],
+      [8, 8, 2, 2],
+      [0, 0, 4, 2],
+    ]));
+    assert_eq!(bitboard.get_sum(), 46);
+  }
+
+  #[test]
+  #[should_panic]
+  pub fn test_bitboard_add_empty_tile() {
+    Grid::<BitboardGrid>::new(&0).add_tile_to_position(0, 0);
+  }
+
 
   // Grid::<DestinationsGrid>
 
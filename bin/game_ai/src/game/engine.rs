@@ -3,9 +3,19 @@
 //! This module defines the game logic functionalities.
 //! Exposes the Game API to the user.
 
-use std::collections::{VecDeque, HashMap};
+use std::collections::VecDeque;
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
 
 use crate::game::core::*;
+use crate::game::board::{BoardView, GameBoard};
+use crate::game::config::GameConfig;
+use crate::game::record::{GameRecord, RecordedMove};
+use crate::game::stats::GameStats;
+use crate::game::observer::GameObserver;
+#[cfg(feature = "serde")]
+use crate::game::save::{SavedGame, SavedHistoryItem, SavedRedoItem, SaveError, SAVE_FORMAT_VERSION};
 
 use super::*;
 use moves::*;
@@ -15,45 +25,74 @@ use moves::*;
 // Types and Definitions
 //------------------------------------------------
 
+/// Seedable pseudo-random number generator driving the tile spawns of a `Game`.
+pub type GameRng = ChaCha8Rng;
+
 /// `enum` encoding the status of the `Game`, values are: {`New`, `Playing`, `Over`}.
 #[derive(Copy, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum GameStatus {
   New,
   Playing,
   Over,
 }
 
-/// The `Game` object that implements the public API.
+/// The game played on the default `GRID_SIDE` x `GRID_SIDE` grid.
+pub type Game = BoardGame<Grid<EncodedGrid>>;
+
+/// The game object that implements the public API, played on a board of type `B` sized by its `GameConfig`, see `game::board`.
 /// The grid history is a double ended list where states are added to the front and popped from the back when the limit is reached. 
-pub struct Game {
-  grid: Grid<EncodedGrid>,
+/// Undone states are kept in the redo history until a new move overwrites the forward branch.
+/// Every random tile is drawn from the game's own seeded generator, so the seed and the list of moves fully determine the game.
+/// Every move is also recorded along with its spawned tile, see `get_record()`, and counted in the statistics, see `get_stats()`.
+/// Registered observers are notified of the events of the game, see `GameObserver`.
+pub struct BoardGame<B: GameBoard> {
+  grid: B,
   state: GameState,
-  history: VecDeque<HistoryItem>,
-  precomputed_moves: HashMap<EncodedEntryType, LineStackingResult>,
+  history: VecDeque<HistoryItem<B>>,
+  redo_history: Vec<RedoItem<B>>,
+  initial_grid: B,
+  recorded_moves: Vec<RecordedMove>,
+  stats: GameStats,
+  observers: Vec<Box<dyn GameObserver + Send>>,
+  config: GameConfig,
+  seed: u64,
+  rng: GameRng,
 }
 
 /// The object containing the state of the game. Returned at each move processed.
 #[derive(Copy, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct GameState {
   status: GameStatus,
   move_count: u32,
-  score: u32,
+  score: u64,
   victory: bool,
 }
 
 /// A struct containing the information necessary to restore the game to a given state
-#[derive(Copy, Clone, PartialEq, Debug)]
-struct HistoryItem {
-  grid: Grid<EncodedGrid>,
+#[derive(Clone, PartialEq, Debug)]
+struct HistoryItem<B> {
+  grid: B,
+  state: GameState,
+}
+
+/// An undone state that can be restored by redoing its move, the last redo item is the first to be restored.
+#[derive(Clone, PartialEq, Debug)]
+struct RedoItem<B> {
+  grid: B,
   state: GameState,
+  recorded_move: Option<RecordedMove>,
 }
 
-/// This struct contains the animation data returned by each process_move()
-pub struct AnimationData {
-  stacked_grid: Grid<EncodedGrid>,
-  destinations_grid: Grid<DestinationsGrid>,
+/// This struct contains the animation data returned by each process_move(), along with the final statistics when the move ends the game.
+pub struct AnimationData<B: GameBoard = Grid<EncodedGrid>> {
+  stacked_grid: B,
+  destinations_grid: B::Destinations,
+  merges: Vec<MergeEvent>,
   tile: EntryType,
   tile_position: (usize, usize),
+  final_stats: Option<GameStats>,
 }
 
 
@@ -64,10 +103,13 @@ pub struct AnimationData {
 /// This trait defines the public API available to the user to interact with the game.
 pub trait GameAPI {
 
+  /// The board the game is played on.
+  type Board: GameBoard;
+
   // State interaction
   
   /// Get game grid.
-  fn get_grid(&self) -> &Grid<EncodedGrid>;
+  fn get_grid(&self) -> &Self::Board;
 
   /// Get game state.
   fn get_state(&self) -> &GameState;
@@ -80,11 +122,24 @@ pub trait GameAPI {
 
   /// Process the possibly null move and return animation data if valid move or valid state.
   /// Accepts `None` values because if can be called with nothing when the AI is active to just process the optimal move.
-  fn process_move(&mut self, player_move: Option<PlayerMove>) -> Option<AnimationData>;
+  fn process_move(&mut self, player_move: Option<PlayerMove>) -> Option<AnimationData<Self::Board>>;
 
   /// Undo the last move if possible.
   fn undo_last_move(&mut self);
 
+  /// Redo the last undone move if possible.
+  fn redo_move(&mut self);
+
+  /// Get the number of moves that can be navigated, undone and redone ones together.
+  /// This is a number of moves, not a move index: the history covers the move indices from the move count less the moves that can be undone
+  /// to the move count plus the moves that can be redone, and only starts at 0 while no more than `HISTORY_LENGTH` moves can be undone.
+  fn history_len(&self) -> usize;
+
+  /// Jump to the state reached after `move_index` moves, undoing or redoing moves as needed.
+  /// `move_index` is an absolute move count, as given by `GameState::get_move_count()`, not an offset in the history.
+  /// Returns `false` and leaves the game untouched if that state is not in the history.
+  fn jump_to(&mut self, move_index: usize) -> bool;
+
 }
 
 
@@ -94,25 +149,140 @@ pub trait GameAPI {
 
 // Inherent
 
-impl Game {
+impl<B: GameBoard> BoardGame<B> {
 
   /// Constructor.
+  /// The game is seeded from system entropy, the seed can be retrieved with `get_seed()` to replay it.
   pub fn new() -> Self {
+    BoardGame::with_config_and_seed(GameConfig::default(), rand::random())
+  }
 
-    let mut grid = Grid::new(&[0; GRID_SIDE]);
-    add_random_tile(&mut grid);
+  /// Constructor with a given seed for the random tiles generator.
+  pub fn with_seed(seed: u64) -> Self {
+    BoardGame::with_config_and_seed(GameConfig::default(), seed)
+  }
+
+  /// Constructor with the given rules, seeded from system entropy.
+  pub fn with_config(config: GameConfig) -> Self {
+    BoardGame::with_config_and_seed(config, rand::random())
+  }
 
-    Game {
-      grid,
+  /// Constructor with the given rules and seed for the random tiles generator.
+  /// Panics if the board type can't hold the board size of the rules, a `Game` only holding the default size.
+  pub fn with_config_and_seed(config: GameConfig, seed: u64) -> Self {
+
+    let mut rng = GameRng::seed_from_u64(seed);
+    let mut grid = B::empty(config.get_rows(), config.get_cols());
+
+    for _ in 0..config.get_starting_tiles() {
+      add_random_tile(&mut grid, &mut rng, config.get_prob_tile2());
+    }
+
+    BoardGame {
+      grid: grid.clone(),
       state: GameState::new(),
-      history: VecDeque::with_capacity(HISTORY_LENGTH),
-      precomputed_moves: moves::make_precomputed_hashmap(),
+      history: VecDeque::with_capacity(config.get_history_length()),
+      redo_history: Vec::new(),
+      initial_grid: grid.clone(),
+      recorded_moves: Vec::new(),
+      stats: GameStats::from_initial_grid(&grid),
+      observers: Vec::new(),
+      config,
+      seed,
+      rng,
     }
 
   }
 
   // Getters
-  pub fn get_precomputed_moves(&self) -> &HashMap<EncodedEntryType, LineStackingResult> { &self.precomputed_moves }
+  pub fn get_precomputed_moves(&self) -> &'static MovesTable { moves::precomputed_moves() }
+  pub fn get_seed(&self) -> u64 { self.seed }
+  pub fn get_config(&self) -> &GameConfig { &self.config }
+  pub fn get_stats(&self) -> &GameStats { &self.stats }
+
+  /// Registers an observer notified of the events of the game from now on, kept across resets.
+  pub fn add_observer(&mut self, observer: Box<dyn GameObserver + Send>) {
+    self.observers.push(observer);
+  }
+
+  /// Unregisters all the observers.
+  pub fn clear_observers(&mut self) {
+    self.observers.clear();
+  }
+
+  /// Returns the moves that would change the grid, none once the game is over.
+  pub fn legal_moves(&self) -> MoveSet {
+    match self.state.get_status() {
+      GameStatus::Over => MoveSet::empty(),
+      _ => self.grid.legal_moves(),
+    }
+  }
+
+  /// Appends the current grid and state to the history, dropping the oldest one when the limit is reached.
+  fn push_history(&mut self) {
+    if self.config.get_history_length() == 0 {
+      return;
+    }
+
+    if self.history.len() >= self.config.get_history_length() {
+      self.history.pop_back();
+    }
+
+    self.history.push_front(HistoryItem {
+      grid: self.grid.clone(),
+      state: self.state,
+    });
+  }
+
+  /// Returns the record of the moves leading to the current state, undone moves excluded.
+  pub fn get_record(&self) -> GameRecord<B> {
+    GameRecord::new(self.seed, &self.config, &self.initial_grid, self.recorded_moves.clone(), &self.grid, &self.state)
+  }
+
+  /// Takes a snapshot of the game that can be written to JSON or binary and resumed with `load()`.
+  #[cfg(feature = "serde")]
+  pub fn save(&self) -> SavedGame<B> {
+    SavedGame {
+      version: SAVE_FORMAT_VERSION,
+      grid: self.grid.clone(),
+      state: self.state,
+      history: self.history.iter().map(|item| SavedHistoryItem { grid: item.grid.clone(), state: item.state }).collect(),
+      redo_history: self.redo_history.iter()
+        .map(|item| SavedRedoItem { grid: item.grid.clone(), state: item.state, recorded_move: item.recorded_move })
+        .collect(),
+      initial_grid: self.initial_grid.clone(),
+      moves: self.recorded_moves.clone(),
+      stats: self.stats.clone(),
+      config: self.config,
+      seed: self.seed,
+      word_pos: self.rng.get_word_pos(),
+    }
+  }
+
+  /// Rebuilds a game from a snapshot, continuing exactly where it was saved.
+  #[cfg(feature = "serde")]
+  pub fn load(saved: &SavedGame<B>) -> Result<Self, SaveError> {
+    saved.validate()?;
+
+    let mut rng = GameRng::seed_from_u64(saved.seed);
+    rng.set_word_pos(saved.word_pos);
+
+    Ok(BoardGame {
+      grid: saved.grid.clone(),
+      state: saved.state,
+      history: saved.history.iter().map(|item| HistoryItem { grid: item.grid.clone(), state: item.state }).collect(),
+      redo_history: saved.redo_history.iter()
+        .map(|item| RedoItem { grid: item.grid.clone(), state: item.state, recorded_move: item.recorded_move })
+        .collect(),
+      initial_grid: saved.initial_grid.clone(),
+      recorded_moves: saved.moves.clone(),
+      stats: saved.stats.clone(),
+      observers: Vec::new(),
+      config: saved.config,
+      seed: saved.seed,
+      rng,
+    })
+  }
   
 }
 
@@ -131,7 +301,7 @@ impl GameState {
   // Getters.
   pub fn get_status(&self) -> GameStatus { self.status }
   pub fn get_move_count(&self) -> u32 { self.move_count }
-  pub fn get_score(&self) -> u32 { self.score }
+  pub fn get_score(&self) -> u64 { self.score }
   pub fn get_victory(&self) -> bool { self.victory }
 
   // Setters.
@@ -140,104 +310,138 @@ impl GameState {
 
   // "Increasers"
   fn inc_move_count(&mut self) { self.move_count += 1; }
-  fn inc_score(&mut self, delta: u32) { self.score += delta; }
+  fn inc_score(&mut self, delta: u64) { self.score += delta; }
 
 }
 
-impl AnimationData {
+impl<B: GameBoard> AnimationData<B> {
 
   /// Constructor.
-  pub fn new(stacked_grid: &Grid<EncodedGrid>, destinations_grid: &Grid<DestinationsGrid>, tile: EntryType, tile_position: (usize, usize)) -> Self {
+  pub fn new(
+    stacked_grid: &B,
+    destinations_grid: &B::Destinations,
+    merges: Vec<MergeEvent>,
+    tile: EntryType,
+    tile_position: (usize, usize),
+  ) -> Self {
     AnimationData {
-      stacked_grid: *stacked_grid,
-      destinations_grid: *destinations_grid,
+      stacked_grid: stacked_grid.clone(),
+      destinations_grid: destinations_grid.clone(),
+      merges,
       tile,
       tile_position,
+      final_stats: None,
     }
   }
 
   // Getters
-  pub fn get_stacked_grid(&self) -> &Grid<EncodedGrid> { &self.stacked_grid }
-  pub fn get_destinations_grid(&self) -> &Grid<DestinationsGrid> {&self.destinations_grid }
+  pub fn get_stacked_grid(&self) -> &B { &self.stacked_grid }
+  pub fn get_destinations_grid(&self) -> &B::Destinations {&self.destinations_grid }
+  pub fn get_merges(&self) -> &[MergeEvent] { &self.merges }
   pub fn get_tile(&self) -> EntryType { self.tile }
   pub fn get_tile_position(&self) -> (usize, usize) { self.tile_position }
+  pub fn get_final_stats(&self) -> Option<&GameStats> { self.final_stats.as_ref() }
 
 }
 
 
 // GameAPI
 
-impl GameAPI for Game {
+impl<B: GameBoard> GameAPI for BoardGame<B> {
+
+  type Board = B;
   
-  fn get_grid(&self) -> &Grid<EncodedGrid> { &self.grid }
+  fn get_grid(&self) -> &B { &self.grid }
 
   fn get_state(&self) -> &GameState { &self.state }
 
-  fn reset(&mut self) { *self = Game::new() }
+  // The new game is seeded from the current generator so that a seeded session stays reproducible across resets
+  fn reset(&mut self) {
+    let seed = self.rng.gen();
+    let observers = std::mem::take(&mut self.observers);
+
+    *self = BoardGame::with_config_and_seed(self.config, seed);
+    self.observers = observers;
+
+    for observer in self.observers.iter_mut() {
+      observer.on_reset(&self.state);
+    }
+  }
   
-  fn process_move(&mut self, player_move: Option<PlayerMove>) -> Option<AnimationData> {
+  fn process_move(&mut self, player_move: Option<PlayerMove>) -> Option<AnimationData<B>> {
 
-    let player_move = match player_move {
-      Some(value) => value,
-      None => return None,
-    };
+    let player_move = player_move?;
 
     match self.state.get_status() {
 
       // Process move only if not in a terminating state of the game
       GameStatus::New | GameStatus::Playing => {
 
-        let move_result = moves::process_grid_stacking(player_move, &self.grid, &self.precomputed_moves);
+        let move_result = self.grid.stack(player_move);
 
         // Process the move only if it produced effects, otherwise it's null and ignored
-        if is_effective_move(&move_result) {
-
-          // Append old grid to history 
-          if self.history.len() >= HISTORY_LENGTH {
-            self.history.pop_back();
-          }
+        if move_result.is_effective() {
 
-          self.history.push_front(HistoryItem {
-            grid: self.grid,
-            state: self.state,
-          });
+          // Append old grid to history, the forward branch is overwritten by the new move
+          self.push_history();
+          self.redo_history.clear();
 
           // Update grid
-          self.grid = *move_result.get_new_grid();
-
-          // Update score
-          self.state.inc_score(move_result.get_delta_score());
-
-          // Update move count
-          self.state.inc_move_count();
+          self.grid = move_result.get_new_board().clone();
 
           // Add new random tile. There's always an empty tile after a valid move so no check needed
-          let (tile, tile_position) = add_random_tile(&mut self.grid);
+          let (tile, tile_position) = add_random_tile(&mut self.grid, &mut self.rng, self.config.get_prob_tile2());
 
-          // Update victory. Executed only the first time victory is achieved
-          if !self.state.get_victory() && is_victory(&self.grid) {
-            self.state.set_victory(true);
-          }
+          // Update score, move count, victory and status
+          let was_victory = self.state.get_victory();
+          update_game_state(&mut self.state, &self.grid, move_result.get_delta_score(), self.config.get_victory_threshold());
 
-          // After adding a tile check if game over
-          if is_game_over(&self.grid , &self.precomputed_moves) {
-            self.state.set_status(GameStatus::Over);
-          
-          // otherwise if it's the first valid move of the game set state to playing
-          } else if let GameStatus::New = self.state.get_status() {
-            self.state.set_status(GameStatus::Playing);
-          }
-          
-          return Some(AnimationData::new(
-            move_result.get_new_grid(),
-            move_result.get_destination_grid(),
+          self.recorded_moves.push(RecordedMove::new(
+            player_move,
             tile,
             tile_position,
+            self.state.get_score(),
           ));
 
+          let merges = move_result.get_merges();
+          self.stats.record_move(player_move, merges, tile, &self.grid, self.state.get_move_count(), self.state.get_score());
+
+          let mut animation_data = AnimationData::new(
+            move_result.get_new_board(),
+            move_result.get_destinations(),
+            merges.to_vec(),
+            tile,
+            tile_position,
+          );
+
+          if let GameStatus::Over = self.state.get_status() {
+            animation_data.final_stats = Some(self.stats.clone());
+          }
+
+          for observer in self.observers.iter_mut() {
+            observer.on_move(player_move, &self.state);
+
+            for merge in animation_data.get_merges() {
+              observer.on_merge(merge);
+            }
+
+            observer.on_spawn(tile, tile_position);
+
+            if !was_victory && self.state.get_victory() {
+              observer.on_victory(&self.state);
+            }
+
+            if let GameStatus::Over = self.state.get_status() {
+              observer.on_game_over(&self.state, &self.stats);
+            }
+          }
+
+          return Some(animation_data);
+
         }
 
-        // If the move is not effective return None
+        // If the move is not effective count the attempt and return None
+        self.stats.record_ineffective_move();
         None
 
       },
@@ -250,17 +454,101 @@ impl GameAPI for Game {
   
   fn undo_last_move(&mut self) {
 
-    if self.history.len() > 0 {
+    if !self.history.is_empty() {
 
       let restored = self.history.pop_front().unwrap();
+      let recorded_move = self.recorded_moves.pop();
+
+      // Take the move back out of the statistics, its merges are found again by replaying it on the restored grid
+      if let Some(recorded_move) = recorded_move {
+        let player_move = recorded_move.get_player_move();
+        let move_result = restored.grid.stack(player_move);
+        self.stats.undo_move(player_move, move_result.get_merges(), recorded_move.get_tile(), self.state.get_move_count());
+      }
+
+      self.redo_history.push(RedoItem {
+        grid: std::mem::replace(&mut self.grid, restored.grid),
+        state: self.state,
+        recorded_move,
+      });
+
+      self.state = restored.state;
+
+      for observer in self.observers.iter_mut() {
+        observer.on_undo(&self.state);
+      }
+
+    }
+
+  }
+
+  fn redo_move(&mut self) {
+
+    if let Some(restored) = self.redo_history.pop() {
+
+      self.push_history();
+
+      // Count the move again in the statistics, its merges are found again by replaying it on the current grid
+      if let Some(recorded_move) = restored.recorded_move {
+        let player_move = recorded_move.get_player_move();
+        let move_result = self.grid.stack(player_move);
+        self.stats.record_move(player_move, move_result.get_merges(), recorded_move.get_tile(), &restored.grid, restored.state.get_move_count(), restored.state.get_score());
+      }
 
       self.grid = restored.grid;
       self.state = restored.state;
+      self.recorded_moves.extend(restored.recorded_move);
+
+      for observer in self.observers.iter_mut() {
+        observer.on_redo(&self.state);
+      }
 
     }
 
   }
 
+  fn history_len(&self) -> usize { self.history.len() + self.redo_history.len() }
+
+  fn jump_to(&mut self, move_index: usize) -> bool {
+
+    let move_count = self.state.get_move_count() as usize;
+
+    if move_index < move_count {
+      if move_count - move_index > self.history.len() { return false; }
+
+      for _ in move_index..move_count {
+        self.undo_last_move();
+      }
+    } else {
+      if move_index - move_count > self.redo_history.len() { return false; }
+
+      for _ in move_count..move_index {
+        self.redo_move();
+      }
+    }
+
+    true
+  }
+
+}
+
+
+// Default
+
+impl<B: GameBoard> Default for BoardGame<B> {
+
+  fn default() -> Self {
+    BoardGame::new()
+  }
+
+}
+
+impl Default for GameState {
+
+  fn default() -> Self {
+    GameState::new()
+  }
+
 }
 
 
@@ -268,61 +556,61 @@ impl GameAPI for Game {
 // Functions
 //------------------------------------------------
 
-/// Adds a random tile to the grid (as an out parameter) and returns the tile and position coordinates.
-fn add_random_tile(grid: &mut Grid<EncodedGrid>) -> (EntryType, (usize, usize)) {
+/// Adds a random tile to the grid (as an out parameter) drawing from the given generator, and returns the tile and position coordinates.
+/// The tile is a 2 with probability `prob_tile2`, a 4 otherwise.
+/// Panics if the grid is full: callers only add tiles to the starting grid, whose tile count is validated, or after an effective move.
+fn add_random_tile<B: GameBoard, R: Rng>(grid: &mut B, rng: &mut R, prob_tile2: f64) -> (EntryType, (usize, usize)) {
 
   // Generate random tile according to the probability of spawning a 2 or a 4
   let mut new_tile: EntryType = 2;
-  if rand::random::<f64>() >= PROB_TILE2 {
+  if rng.gen::<f64>() >= prob_tile2 {
     new_tile = 4;
   }
 
   // Get a position among the empty tiles in the grid in "reading order" where we place the new tile
-  let position: isize = (rand::random::<f64>() * grid.get_zeros() as f64) as isize;
+  let position: isize = (rng.gen::<f64>() * grid.get_zeros() as f64) as isize;
 
-  grid.add_tile_to_position(new_tile, position);
+  let tile_position = grid.add_tile_to_position(new_tile, position).expect("empty cell exists");
 
-  (new_tile, (position as usize / GRID_SIDE, position as usize % GRID_SIDE))
+  (new_tile, tile_position)
 
 }
 
-/// Checks if a game grid is in a victory state 
-pub fn is_victory(grid: &Grid<EncodedGrid>) -> bool {
+/// Updates the game state after an effective move scoring `delta_score` and its tile spawn, which produced `grid`.
+pub(crate) fn update_game_state<B: GameBoard>(state: &mut GameState, grid: &B, delta_score: u64, victory_threshold: EntryType) {
 
-  let mut bit_mask = (ENCODING_BITS as f64).exp2() as EncodedEntryType - 1;
+  state.inc_score(delta_score);
+  state.inc_move_count();
 
-  // Run through each column first to change the bit_mask in O(n) time rather than O(n^2)
-  for j in 0..GRID_SIDE {
+  // Update victory. Executed only the first time victory is achieved
+  if !state.get_victory() && is_victory(grid, victory_threshold) {
+    state.set_victory(true);
+  }
 
-    for i in 0..GRID_SIDE {
+  // After adding a tile check if game over
+  if grid.is_game_over() {
+    state.set_status(GameStatus::Over);
 
-      // check if the masked number shifted back to the least significant bit is greater than or equal to the log2 of the victory threshold
-      if (grid.get_state()[i] & bit_mask) >> ENCODING_BITS * j >= (VICTORY_THRESHOLD as f64).log2() as EncodedEntryType {
-        return true;
-      }
-    }
-    
-    // shift to new column
-    bit_mask <<= ENCODING_BITS;
+  // otherwise if it's the first valid move of the game set state to playing
+  } else if let GameStatus::New = state.get_status() {
+    state.set_status(GameStatus::Playing);
   }
 
-  false
+}
+
+/// Checks if a game grid is in a victory state, i.e. it contains a tile of at least `victory_threshold`.
+pub fn is_victory<B: BoardView>(grid: &B, victory_threshold: EntryType) -> bool {
+  grid.max_tile() >= victory_threshold
 }
 
 /// Checks if the given grid is a terminating state
-pub fn is_game_over(grid: &Grid<EncodedGrid>, moves_table: &HashMap<EncodedEntryType, LineStackingResult>) -> bool {
+pub fn is_game_over(grid: &Grid<EncodedGrid>, moves_table: &MovesTable) -> bool {
 
   // Progressive optimization, if at least one entry is zero you can always make a move
   if grid.get_zeros() > 0 { return false; }
 
-  // When the grid is full, if at least one move is possible then it's not game over
-  if is_effective_move(&moves::process_grid_stacking(PlayerMove::Up, grid, moves_table)) { return false; }
-  if is_effective_move(&moves::process_grid_stacking(PlayerMove::Left, grid, moves_table)) { return false; }
-  if is_effective_move(&moves::process_grid_stacking(PlayerMove::Right, grid, moves_table)) { return false; }
-  if is_effective_move(&moves::process_grid_stacking(PlayerMove::Down, grid, moves_table)) { return false; }
-
-  // No move is possible, game over
-  true
+  // When the grid is full, it's game over only if no move is possible
+  moves_table.legal_moves(grid).is_empty()
 }
 
 /// Checks if the result of a move (not the possible effect of a `PlayerMove`) describes a change in the state of the grid
@@ -350,6 +638,7 @@ pub fn is_effective_move(move_result: &MoveStackingResult) -> bool {
 mod tests {
 
   use super::*;
+  use crate::game::board::Board;
 
 
   // Test is_victory()
@@ -363,7 +652,7 @@ mod tests {
       [0, 1024, 4, 4],
     ]);
 
-    assert_eq!(is_victory(&grid), false);
+    assert!(!is_victory(&grid, VICTORY_THRESHOLD));
   }
 
   #[test]
@@ -375,7 +664,21 @@ mod tests {
       [0, 4, 4, 2048],
     ]);
 
-    assert_eq!(is_victory(&grid), true);
+    assert!(is_victory(&grid, VICTORY_THRESHOLD));
+  }
+
+  #[test]
+  pub fn test_is_victory_custom_threshold() {
+    let grid = Grid::from_decoded(&[
+      [0, 0, 0, 0],
+      [0, 0, 0, 0],
+      [0, 2, 0, 0],
+      [0, 0, 128, 0],
+    ]);
+
+    assert!(is_victory(&grid, 128));
+    assert!(is_victory(&grid, 64));
+    assert!(!is_victory(&grid, 256));
   }
 
   #[test]
@@ -387,7 +690,7 @@ mod tests {
       [4, 2, 2048, 2],
     ]);
 
-    assert_eq!(is_victory(&grid), true);
+    assert!(is_victory(&grid, VICTORY_THRESHOLD));
   }
 
 
@@ -396,18 +699,21 @@ mod tests {
   #[test]
   pub fn test_is_effective_move_false() {
     let move_result = MoveStackingResult::new(
+      PlayerMove::Left,
       &Grid::new(&[0; GRID_SIDE]),
       &Grid::new(&[0; GRID_SIDE]),
       0,
       &Grid::new(&[[0; GRID_SIDE]; GRID_SIDE]),
+      &[],
     );
 
-    assert_eq!(is_effective_move(&move_result), false);
+    assert!(!is_effective_move(&move_result));
   }
 
   #[test]
   pub fn test_is_effective_move_true() {
     let move_result = MoveStackingResult::new(
+      PlayerMove::Left,
       &Grid::new(&[0; GRID_SIDE]),
       &Grid::new(&[0; GRID_SIDE]),
       0,
@@ -417,9 +723,10 @@ mod tests {
         [0; GRID_SIDE],
         [0; GRID_SIDE],
       ]),
+      &[],
     );
 
-    assert_eq!(is_effective_move(&move_result), true);
+    assert!(is_effective_move(&move_result));
   }
 
 
@@ -434,7 +741,7 @@ mod tests {
       [2, 2, 8, 8],
     ]);
 
-    assert_eq!(is_game_over(&grid, &moves::make_precomputed_hashmap()), false);
+    assert!(!is_game_over(&grid, moves::precomputed_moves()));
   }
 
   #[test]
@@ -446,7 +753,7 @@ mod tests {
       [4, 2, 4, 2],
     ]);
 
-    assert_eq!(is_game_over(&grid, &moves::make_precomputed_hashmap()), false);
+    assert!(!is_game_over(&grid, moves::precomputed_moves()));
   }
 
   #[test]
@@ -458,7 +765,7 @@ mod tests {
       [4, 2, 4, 2],
     ]);
 
-    assert_eq!(is_game_over(&grid, &moves::make_precomputed_hashmap()), true);
+    assert!(is_game_over(&grid, moves::precomputed_moves()));
   }
 
   #[test]
@@ -470,7 +777,7 @@ mod tests {
       [4, 2, 2048, 2],
     ]);
 
-    assert_eq!(is_game_over(&grid, &make_precomputed_hashmap()), true);
+    assert!(is_game_over(&grid, precomputed_moves()));
   }
 
 
@@ -492,7 +799,14 @@ mod tests {
         victory: true,
       },
       history: VecDeque::with_capacity(HISTORY_LENGTH),
-      precomputed_moves: make_precomputed_hashmap(),
+      redo_history: Vec::new(),
+      initial_grid: Grid::new(&[0; GRID_SIDE]),
+      recorded_moves: Vec::new(),
+      stats: GameStats::new(),
+      observers: Vec::new(),
+      config: GameConfig::default(),
+      seed: 0,
+      rng: GameRng::seed_from_u64(0),
     };
 
     game.reset();
@@ -500,11 +814,93 @@ mod tests {
     assert_eq!(game.get_state().get_status(), GameStatus::New);
     assert_eq!(game.get_state().get_move_count(), 0);
     assert_eq!(game.get_state().get_score(), 0);
-    assert_eq!(game.get_state().get_victory(), false);
+    assert!(!game.get_state().get_victory());
     assert_eq!(game.history.len(), 0);
 
   }
 
+  #[test]
+  pub fn test_game_reset_seeded() {
+    let mut game = Game::with_seed(42);
+    let mut other = Game::with_seed(42);
+
+    game.process_move(Some(PlayerMove::Left));
+    game.reset();
+    other.process_move(Some(PlayerMove::Left));
+    other.reset();
+
+    assert_eq!(game.get_seed(), other.get_seed());
+    assert_eq!(*game.get_grid(), *other.get_grid());
+
+  }
+
+
+  // Test Game::with_seed()
+
+  #[test]
+  pub fn test_game_with_seed_reproducible() {
+    let mut game = Game::with_seed(2048);
+    let mut other = Game::with_seed(2048);
+
+    use PlayerMove::{Up, Left, Right, Down};
+    let player_move = [Up, Left, Down, Right, Left, Down];
+
+    assert_eq!(game.get_seed(), 2048);
+    assert_eq!(*game.get_grid(), *other.get_grid());
+
+    for k in 0..60 {
+      game.process_move(Some(player_move[k % player_move.len()]));
+      other.process_move(Some(player_move[k % player_move.len()]));
+
+      assert_eq!(*game.get_grid(), *other.get_grid());
+      assert_eq!(*game.get_state(), *other.get_state());
+    }
+
+  }
+
+
+  // Test Game::with_config()
+
+  #[test]
+  pub fn test_game_with_config() {
+    let config = GameConfig::builder().starting_tiles(3).history_length(2).build().unwrap();
+    let mut game = Game::with_config_and_seed(config, 9);
+
+    assert_eq!(game.grid.get_zeros(), GRID_SIDE * GRID_SIDE - 3);
+    assert_eq!(*game.get_config(), config);
+
+    use PlayerMove::{Up, Left, Right, Down};
+    for &player_move in [Left, Up, Right, Down, Left, Up].iter() {
+      game.process_move(Some(player_move));
+    }
+
+    assert_eq!(game.history.len(), 2);
+
+    game.reset();
+
+    assert_eq!(*game.get_config(), config);
+    assert_eq!(game.grid.get_zeros(), GRID_SIDE * GRID_SIDE - 3);
+  }
+
+  #[test]
+  pub fn test_game_with_config_victory() {
+    let config = GameConfig::builder().victory_threshold(8).build().unwrap();
+    let mut game = Game::with_config_and_seed(config, 10);
+
+    use PlayerMove::{Up, Left, Right, Down};
+    let player_move = [Up, Left, Right, Down];
+    let mut count = 0;
+
+    while !game.state.get_victory() && game.state.get_status() != GameStatus::Over {
+      game.process_move(Some(player_move[count % 4]));
+      count += 1;
+    }
+
+    assert!(game.state.get_victory());
+    assert!(is_victory(&game.grid, 8));
+    assert!(!is_victory(&game.grid, VICTORY_THRESHOLD));
+  }
+
 
   // Test Game::process_move()
 
@@ -524,7 +920,14 @@ mod tests {
         victory: false,
       },
       history: VecDeque::with_capacity(HISTORY_LENGTH),
-      precomputed_moves: make_precomputed_hashmap(),
+      redo_history: Vec::new(),
+      initial_grid: Grid::new(&[0; GRID_SIDE]),
+      recorded_moves: Vec::new(),
+      stats: GameStats::new(),
+      observers: Vec::new(),
+      config: GameConfig::default(),
+      seed: 0,
+      rng: GameRng::seed_from_u64(0),
     };
 
     game.process_move(Some(PlayerMove::Left));
@@ -532,11 +935,45 @@ mod tests {
     assert_eq!(game.get_state().get_status(), GameStatus::Playing);
     assert_eq!(game.get_state().get_move_count(), 6);
     assert_eq!(game.get_state().get_score(), 5004);
-    assert_eq!(game.get_state().get_victory(), false);
+    assert!(!game.get_state().get_victory());
     assert_eq!(game.history.len(), 1);
 
   }
 
+  #[test]
+  pub fn test_game_process_move_merges() {
+    let mut game = Game::with_seed(0);
+    game.grid = Grid::from_decoded(&[
+      [2, 4, 2, 2],
+      [4, 2, 4, 2],
+      [2, 4, 2, 4],
+      [4, 2, 4, 2],
+    ]);
+
+    let animation = game.process_move(Some(PlayerMove::Left)).unwrap();
+
+    assert_eq!(animation.get_merges().len(), 1);
+    assert_eq!(animation.get_merges()[0].get_sources(), [(0, 2), (0, 3)]);
+    assert_eq!(animation.get_merges()[0].get_target(), (0, 2));
+    assert_eq!(animation.get_merges()[0].get_value(), 4);
+  }
+
+  #[test]
+  pub fn test_game_process_move_score_beyond_u32() {
+    let mut game = Game::with_seed(0);
+    game.grid = Grid::from_decoded(&[
+      [2, 4, 2, 2],
+      [4, 2, 4, 2],
+      [2, 4, 2, 4],
+      [4, 2, 4, 2],
+    ]);
+    game.state.score = u32::MAX as u64;
+
+    game.process_move(Some(PlayerMove::Left));
+
+    assert_eq!(game.get_state().get_score(), u32::MAX as u64 + 4);
+  }
+
   #[test]
   pub fn test_game_process_move_terminating_victory() {
     let mut game = Game {
@@ -553,7 +990,14 @@ mod tests {
         victory: false,
       },
       history: VecDeque::with_capacity(HISTORY_LENGTH),
-      precomputed_moves: make_precomputed_hashmap(),
+      redo_history: Vec::new(),
+      initial_grid: Grid::new(&[0; GRID_SIDE]),
+      recorded_moves: Vec::new(),
+      stats: GameStats::new(),
+      observers: Vec::new(),
+      config: GameConfig::default(),
+      seed: 0,
+      rng: GameRng::seed_from_u64(0),
     };
 
     game.process_move(Some(PlayerMove::Left));
@@ -561,11 +1005,31 @@ mod tests {
     assert_eq!(game.get_state().get_status(), GameStatus::Over);
     assert_eq!(game.get_state().get_move_count(), 6);
     assert_eq!(game.get_state().get_score(), 12048);
-    assert_eq!(game.get_state().get_victory(), true);
+    assert!(game.get_state().get_victory());
     assert_eq!(game.history.len(), 1);
 
   }
 
+  #[test]
+  pub fn test_game_process_move_spawn_position() {
+    let mut game = Game::with_seed(11);
+
+    use PlayerMove::{Up, Left, Right, Down};
+    let player_move = [Up, Left, Right, Down];
+
+    for count in 0..30 {
+      let stacked = *moves::process_grid_stacking(player_move[count % 4], &game.grid, moves::precomputed_moves()).get_new_grid();
+
+      if let Some(animation) = game.process_move(Some(player_move[count % 4])) {
+        let (row, col) = animation.get_tile_position();
+
+        assert_eq!(stacked.get_tile(row, col), 0);
+        assert_eq!(game.grid.get_tile(row, col), animation.get_tile());
+        assert_eq!(game.get_record().get_moves().last().unwrap().get_tile_position(), (row, col));
+      }
+    }
+  }
+
   #[test]
   pub fn test_game_process_move_history_overflow() {
     let mut game = Game::new();
@@ -593,6 +1057,102 @@ mod tests {
   }
 
 
+  #[test]
+  pub fn test_game_process_move_stats() {
+    let mut game = Game::with_seed(21);
+
+    use PlayerMove::{Up, Left, Right, Down};
+    let player_move = [Up, Left, Right, Down];
+    let mut count = 0;
+    let mut final_stats = None;
+
+    while game.state.get_status() != GameStatus::Over {
+      if let Some(animation_data) = game.process_move(Some(player_move[count % 4])) {
+        final_stats = animation_data.get_final_stats().cloned();
+      }
+      count += 1;
+    }
+
+    let stats = game.get_stats();
+    let move_count = game.state.get_move_count();
+
+    assert_eq!(final_stats.as_ref(), Some(stats));
+    assert_eq!(player_move.iter().map(|&m| stats.get_move_count(m)).sum::<u32>(), move_count);
+    assert_eq!(stats.get_ineffective_moves(), count as u32 - move_count);
+    assert_eq!(stats.get_spawned_2s() + stats.get_spawned_4s(), move_count + 1);
+    assert_eq!(stats.get_score_timeline().len(), move_count as usize + 1);
+    assert_eq!(*stats.get_score_timeline().last().unwrap(), game.state.get_score());
+    assert_eq!(stats.get_first_reached(2).or(stats.get_first_reached(4)), Some(0));
+    assert!(stats.get_first_reached(game.grid.max_tile()).is_some());
+    assert!(stats.get_merge_count(4) > 0);
+
+    // no more statistics once the game is over
+    game.process_move(Some(Up));
+    assert_eq!(game.get_stats().get_ineffective_moves(), count as u32 - move_count);
+  }
+
+  #[test]
+  pub fn test_game_stats_follow_undo_and_redo() {
+    let mut game = Game::with_seed(24);
+
+    // statistics after each move count, only legal moves are played so no ineffective attempt is counted
+    let mut snapshots = vec![game.get_stats().clone()];
+
+    for _ in 0..20 {
+      let player_move = game.legal_moves().iter().next();
+      game.process_move(player_move);
+      snapshots.push(game.get_stats().clone());
+    }
+
+    for _ in 0..5 {
+      game.undo_last_move();
+    }
+    assert_eq!(game.get_stats(), &snapshots[15]);
+
+    game.redo_move();
+    game.redo_move();
+    assert_eq!(game.get_stats(), &snapshots[17]);
+
+    assert!(game.jump_to(12));
+    assert_eq!(game.get_stats(), &snapshots[12]);
+
+    assert!(game.jump_to(20));
+    assert_eq!(game.get_stats(), &snapshots[20]);
+
+    // a new move after an undo replaces the undone one in the timeline
+    game.undo_last_move();
+    let player_move = game.legal_moves().iter().next();
+    game.process_move(player_move);
+
+    let timeline = game.get_stats().get_score_timeline();
+    assert_eq!(timeline.len(), 21);
+    assert_eq!(timeline[..20], snapshots[19].get_score_timeline()[..]);
+
+    for (i, recorded_move) in game.get_record().get_moves().iter().enumerate() {
+      assert_eq!(timeline[i + 1], recorded_move.get_score());
+    }
+  }
+
+
+  #[test]
+  pub fn test_game_legal_moves() {
+    let mut game = Game::with_seed(22);
+
+    while game.state.get_status() != GameStatus::Over {
+      let legal = game.legal_moves();
+
+      for &player_move in [PlayerMove::Up, PlayerMove::Left, PlayerMove::Right, PlayerMove::Down].iter() {
+        let effective = is_effective_move(&moves::process_grid_stacking(player_move, &game.grid, moves::precomputed_moves()));
+        assert_eq!(legal.contains(player_move), effective);
+      }
+
+      game.process_move(legal.iter().next());
+    }
+
+    assert!(game.legal_moves().is_empty());
+  }
+
+
   // Test Game::undo_last_move()
 
   #[test]
@@ -637,8 +1197,8 @@ mod tests {
     let move_count = game.state.get_move_count();
 
     // Add one extra move, whichever is feasible
-    for i in 0..4 {
-      game.process_move(Some(player_move[i]));
+    for &direction in player_move.iter() {
+      game.process_move(Some(direction));
       if game.state.get_move_count() > move_count { break; }
     }
 
@@ -652,4 +1212,256 @@ mod tests {
 
   }
 
-}
\ No newline at end of file
+
+  // Test Game::redo_move()
+
+  #[test]
+  pub fn test_redo_move() {
+    let mut game = Game::with_seed(3);
+
+    use PlayerMove::{Up, Left, Right, Down};
+    for &player_move in [Left, Up, Right, Down, Left, Up].iter() {
+      game.process_move(Some(player_move));
+    }
+
+    let grid = game.grid;
+    let state = game.state;
+    let record = game.get_record();
+
+    game.undo_last_move();
+    game.undo_last_move();
+
+    assert_eq!(game.history_len(), game.history.len() + 2);
+
+    game.redo_move();
+    game.redo_move();
+
+    assert_eq!(game.grid, grid);
+    assert_eq!(game.state, state);
+    assert_eq!(game.get_record(), record);
+    assert!(game.redo_history.is_empty());
+
+    // nothing left to redo
+    game.redo_move();
+
+    assert_eq!(game.grid, grid);
+  }
+
+  #[test]
+  pub fn test_redo_move_overwritten() {
+    let mut game = Game::with_seed(4);
+
+    use PlayerMove::{Up, Left, Right, Down};
+    for &player_move in [Left, Up, Right, Down].iter() {
+      game.process_move(Some(player_move));
+    }
+
+    game.undo_last_move();
+    assert_eq!(game.redo_history.len(), 1);
+
+    // a new effective move overwrites the forward branch
+    for &player_move in [Left, Up, Right, Down].iter() {
+      if game.process_move(Some(player_move)).is_some() { break; }
+    }
+
+    assert!(game.redo_history.is_empty());
+    assert_eq!(game.history_len(), game.history.len());
+  }
+
+
+  // Test Game::jump_to()
+
+  #[test]
+  pub fn test_jump_to() {
+    let mut game = Game::with_seed(5);
+    let mut grids = vec![game.grid];
+
+    use PlayerMove::{Up, Left, Right, Down};
+    let player_move = [Up, Left, Right, Down];
+    let mut count = 0;
+
+    while grids.len() <= 10 {
+      if game.process_move(Some(player_move[count % 4])).is_some() {
+        grids.push(game.grid);
+      }
+      count += 1;
+    }
+
+    assert_eq!(game.history_len(), 10);
+
+    assert!(game.jump_to(3));
+    assert_eq!(game.grid, grids[3]);
+    assert_eq!(game.state.get_move_count(), 3);
+    assert_eq!(game.history_len(), 10);
+
+    assert!(game.jump_to(8));
+    assert_eq!(game.grid, grids[8]);
+
+    assert!(game.jump_to(0));
+    assert_eq!(game.grid, grids[0]);
+    assert_eq!(game.state.get_status(), GameStatus::New);
+
+    assert!(!game.jump_to(11));
+    assert_eq!(game.grid, grids[0]);
+
+    assert!(game.jump_to(10));
+    assert_eq!(game.grid, grids[10]);
+    assert_eq!(game.get_record().verify(), Ok(game.state));
+  }
+
+  #[test]
+  pub fn test_jump_to_history_bounds() {
+    let mut game = Game::with_seed(7);
+    let mut grids = vec![game.grid];
+
+    use PlayerMove::{Up, Left, Right, Down};
+    let player_move = [Up, Left, Right, Down];
+    let mut count = 0;
+
+    while grids.len() <= 5 {
+      if game.process_move(Some(player_move[count % 4])).is_some() {
+        grids.push(game.grid);
+      }
+      count += 1;
+    }
+
+    game.undo_last_move();
+    game.undo_last_move();
+    assert_eq!(game.history_len(), 5);
+
+    // while the history starts at the first move, its length is the index of the last state
+    assert!(game.jump_to(game.history_len()));
+    assert_eq!(game.grid, grids[5]);
+    assert_eq!(game.state.get_move_count(), 5);
+
+    assert!(game.jump_to(0));
+    assert_eq!(game.grid, grids[0]);
+    assert_eq!(game.state.get_move_count(), 0);
+
+    assert!(!game.jump_to(game.history_len() + 1));
+    assert_eq!(game.grid, grids[0]);
+  }
+
+  #[test]
+  pub fn test_jump_to_beyond_history() {
+    let mut game = Game::with_seed(6);
+
+    use PlayerMove::{Up, Left, Right, Down};
+    let player_move = [Up, Left, Right, Down];
+    let mut count = 0;
+
+    while game.state.get_move_count() as usize <= HISTORY_LENGTH + 2 {
+      game.process_move(Some(player_move[count % 4]));
+      count += 1;
+    }
+
+    let grid = game.grid;
+
+    assert!(!game.jump_to(1));
+    assert!(!game.jump_to(0));
+    assert_eq!(game.grid, grid);
+    assert_eq!(game.history_len(), HISTORY_LENGTH);
+
+    // once moves fell out of the history, its length is no longer the index of the last state
+    let move_count = game.state.get_move_count() as usize;
+    assert!(move_count > HISTORY_LENGTH);
+    assert!(game.jump_to(game.history_len()));
+    assert_eq!(game.state.get_move_count() as usize, HISTORY_LENGTH);
+    assert!(game.jump_to(move_count));
+    assert_eq!(game.grid, grid);
+  }
+
+
+  // Test BoardGame on runtime-sized boards
+
+  // Plays the moves in turn until the game is over, checking the animation data of each move against the board
+  fn play_board_game(rows: usize, cols: usize, seed: u64) -> BoardGame<Board> {
+    let config = GameConfig::builder().board_size(rows, cols).starting_tiles(2).build().unwrap();
+    let mut game = BoardGame::<Board>::with_config_and_seed(config, seed);
+
+    assert_eq!((game.grid.get_rows(), game.grid.get_cols()), (rows, cols));
+    assert_eq!(game.grid.get_zeros(), rows * cols - 2);
+
+    use PlayerMove::{Up, Left, Right, Down};
+    let player_move = [Up, Left, Down, Right];
+    let mut count = 0;
+
+    while game.state.get_status() != GameStatus::Over && count < 10000 {
+      let prev_grid = game.grid.clone();
+
+      if let Some(animation_data) = game.process_move(Some(player_move[count % 4])) {
+        let mut stacked_grid = animation_data.get_stacked_grid().clone();
+        let (i, j) = animation_data.get_tile_position();
+
+        stacked_grid.set_tile(i, j, animation_data.get_tile());
+        assert_eq!(stacked_grid, game.grid);
+        assert_eq!(animation_data.get_destinations_grid().len(), rows);
+        assert_eq!(animation_data.get_merges(), prev_grid.stack(player_move[count % 4]).get_merges());
+      }
+
+      count += 1;
+    }
+
+    game
+  }
+
+  #[test]
+  pub fn test_board_game_small() {
+    let game = play_board_game(3, 3, 1);
+
+    assert_eq!(game.state.get_status(), GameStatus::Over);
+    assert!(game.grid.is_game_over());
+    assert!(game.legal_moves().is_empty());
+    assert_eq!(game.get_record().verify(), Ok(game.state));
+  }
+
+  #[test]
+  pub fn test_board_game_large() {
+    let game = play_board_game(5, 5, 2);
+
+    assert_eq!(game.state.get_status(), GameStatus::Over);
+    assert_eq!(game.get_record().verify(), Ok(game.state));
+    assert_eq!(game.get_stats().get_score_timeline().last(), Some(&game.state.get_score()));
+  }
+
+  #[test]
+  pub fn test_board_game_rectangular_undo_redo() {
+    let mut game = play_board_game(2, 5, 3);
+    let (grid, state) = (game.grid.clone(), game.state);
+
+    game.undo_last_move();
+    game.undo_last_move();
+    assert_eq!(game.state.get_move_count(), state.get_move_count() - 2);
+
+    game.redo_move();
+    game.redo_move();
+    assert_eq!(game.grid, grid);
+    assert_eq!(game.state, state);
+    assert_eq!(game.get_record().verify(), Ok(state));
+  }
+
+  #[test]
+  pub fn test_board_game_matches_game_on_default_size() {
+    let mut game = Game::with_seed(4);
+    let mut board_game = BoardGame::<Board>::with_seed(4);
+
+    use PlayerMove::{Up, Left, Right, Down};
+    let player_move = [Up, Left, Right, Down];
+
+    for count in 0..50 {
+      let animation_data = game.process_move(Some(player_move[count % 4]));
+      let board_animation_data = board_game.process_move(Some(player_move[count % 4]));
+
+      assert_eq!(animation_data.is_some(), board_animation_data.is_some());
+      assert_eq!(board_game.grid.get_state(), &game.grid.get_state()[..]);
+      assert_eq!(board_game.state, game.state);
+    }
+  }
+
+  #[test]
+  #[should_panic]
+  pub fn test_game_other_size() {
+    Game::with_config(GameConfig::builder().board_size(5, 5).build().unwrap());
+  }
+
+}
@@ -4,9 +4,12 @@
 //! It exposes an API to allow precomputation of partial moves for optimization.
 
 use std::collections::HashMap;
+use std::iter::FromIterator;
+use std::sync::OnceLock;
 
 use crate::game::core::*;
 use crate::encoding;
+use crate::error::GridError;
 
 
 //------------------------------------------------
@@ -15,6 +18,7 @@ use crate::encoding;
 
 /// Player move `enum`.
 #[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum PlayerMove {
   Up = 0,
   Left = 1,
@@ -22,28 +26,105 @@ pub enum PlayerMove {
   Down = 3,
 }
 
-/// Struct used as an iterable object to provide all the allowed values (powers of 2) for a tile from 0 to `LARGEST_TILE`.
+// Layout of a packed stacking result: the stacked row in the low bits, then the length of the displacement of each tile,
+// then the number of merges, then each merge as its two source cells, its target cell and the exponent of the merged tile
+const PACKED_LINE_BITS: usize = ENCODING_BITS * GRID_SIDE;
+const PACKED_DESTINATION_BITS: usize = 2;
+const PACKED_DESTINATION_MASK: u64 = (1 << PACKED_DESTINATION_BITS) - 1;
+const PACKED_MERGE_COUNT_SHIFT: usize = PACKED_LINE_BITS + PACKED_DESTINATION_BITS * GRID_SIDE;
+const PACKED_MERGE_COUNT_BITS: usize = 2;
+const PACKED_MERGES_SHIFT: usize = PACKED_MERGE_COUNT_SHIFT + PACKED_MERGE_COUNT_BITS;
+const PACKED_CELL_BITS: usize = 2;
+const PACKED_CELL_MASK: u64 = (1 << PACKED_CELL_BITS) - 1;
+const PACKED_MERGE_BITS: usize = 3 * PACKED_CELL_BITS + ENCODING_BITS;
+const _: () = assert!(PACKED_MERGES_SHIFT + PACKED_MERGE_BITS * MAX_LINE_MERGES <= u64::BITS as usize, "packed results overflow 64 bits");
+
+// Left stacked bitboard row standing for the rows that merge two BITBOARD_LARGEST_TILE tiles, which can't be represented.
+// It is never a genuine left stacked row, whose empty cells all follow its tiles
+const BITBOARD_OVERFLOW: u16 = 0xF000;
+
+// Largest tile of the rows exported to the legacy JavaScript front end, which predates the full exponent range
+const JS_LARGEST_TILE: EntryType = 65536;
+
+// Largest number of merges of a single row and of the whole grid in one move
+const MAX_LINE_MERGES: usize = GRID_SIDE / 2;
+const MAX_GRID_MERGES: usize = GRID_SIDE * MAX_LINE_MERGES;
+
+/// Struct used as an iterable object to provide all the allowed values (powers of 2) for a tile from 0 to `largest`, at most `LARGEST_TILE`.
 struct AdmissibleTileValue {
-  value: EntryType,
-  prev: EntryType,
+  value: Option<EntryType>,
+  largest: EntryType,
 }
 
 /// Contains the information regarding the encoded processing of the move for a single row in the grid.
 /// All the values are encoded where possible.
+#[derive(Copy, Clone, PartialEq, Debug)]
 pub struct LineStackingResult {
   prev_line: EncodedEntryType,
   new_line: EncodedEntryType,
-  delta_score: u32,
+  delta_score: u64,
   destinations: Array1D<DestEntryType>,
+  merges: [LineMerge; MAX_LINE_MERGES],
+  merge_count: u8,
+}
+
+/// A merge of two equal tiles in a single row, positions are indices in the row.
+/// The merged tile has value `value`, which is also the score contribution of the merge.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub struct LineMerge {
+  sources: [u8; 2],
+  target: u8,
+  value: EntryType,
+}
+
+/// A merge of two equal tiles in the grid, positions are (row, column) coordinates.
+/// The merged tile has value `value`, which is also the score contribution of the merge.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub struct MergeEvent {
+  sources: [(u8, u8); 2],
+  target: (u8, u8),
+  value: EntryType,
+}
+
+/// Dense tables of the precomputed left and right moves of every encoded row, directly indexed by the encoded row.
+/// Each result, merges included, is packed in 64 bits, so the two tables take 16 MB instead of the 48 MB of tables of `LineStackingResult`s.
+pub struct MovesTable {
+  left: Vec<PackedLine>,
+  right: Vec<PackedLine>,
+}
+
+// Stacking result of a row packed in 64 bits, see PACKED_LINE_BITS and following for the layout
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+struct PackedLine(u64);
+
+/// Dense table of the left moves of every bitboard row, packed with `BITBOARD_BITS` per tile and directly indexed by the row.
+/// Only the stacked rows are stored, for the searches that don't need the score or the animation, and the right moves are derived
+/// from the left moves of the reversed rows, so the table takes 128 kB.
+pub struct BitboardMovesTable {
+  left: Vec<u16>,
+}
+
+/// Set of player moves, e.g. the legal moves of a grid. Iterates in the order `Up`, `Left`, `Right`, `Down`.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Default)]
+pub struct MoveSet {
+  bits: u8,
+}
+
+/// Iterator over the moves of a `MoveSet`.
+pub struct MoveSetIter {
+  bits: u8,
 }
 
 /// Contains the information regarding the encoded processing of the move for the entire grid.
 /// All the values are encoded where possible.
 pub struct MoveStackingResult {
+  player_move: PlayerMove,
   prev_grid: Grid<EncodedGrid>,
   new_grid: Grid<EncodedGrid>,
-  delta_score: u32,
+  delta_score: u64,
   destination_grid: Grid<DestinationsGrid>,
+  merges: [MergeEvent; MAX_GRID_MERGES],
+  merge_count: u8,
 }
 
 
@@ -56,10 +137,10 @@ pub struct MoveStackingResult {
 
 impl AdmissibleTileValue {
 
-  fn new(value: EntryType) -> AdmissibleTileValue {
+  fn new(value: EntryType, largest: EntryType) -> AdmissibleTileValue {
     AdmissibleTileValue {
-      value,
-      prev: 0,
+      value: Some(value),
+      largest,
     }
   }
 
@@ -67,12 +148,23 @@ impl AdmissibleTileValue {
 
 impl LineStackingResult {
 
-  pub fn new(prev_line: &Array1D<EntryType>, new_line: &Array1D<EntryType>, delta_score: u32, destinations: &Array1D<DestEntryType>) -> Self {
+  pub fn new(prev_line: &Array1D<EntryType>, new_line: &Array1D<EntryType>, delta_score: u64, destinations: &Array1D<DestEntryType>) -> Self {
+    let prev_line = encoding::encode_line(prev_line);
+    let mut merges = [LineMerge::default(); MAX_LINE_MERGES];
+    let mut merge_count = 0;
+
+    for merge in line_merges(prev_line, destinations) {
+      merges[merge_count as usize] = merge;
+      merge_count += 1;
+    }
+
     LineStackingResult {
-      prev_line: encoding::encode_line(prev_line),
+      prev_line,
       new_line: encoding::encode_line(new_line),
       delta_score,
       destinations: *destinations,
+      merges,
+      merge_count,
     }
   }
 
@@ -80,9 +172,12 @@ impl LineStackingResult {
 
   pub fn get_prev_line(&self) -> EncodedEntryType { self.prev_line }
   pub fn get_new_line(&self) -> EncodedEntryType { self.new_line }
-  pub fn get_delta_score(&self) -> u32 { self.delta_score }
+  pub fn get_delta_score(&self) -> u64 { self.delta_score }
   pub fn get_destinations(& self) -> Array1D<DestEntryType> { self.destinations }
 
+  /// Returns the merges of the move, from the first cell of the line.
+  pub fn get_merges(&self) -> &[LineMerge] { &self.merges[..self.merge_count as usize] }
+
   #[allow(dead_code)]
   /// Formats stacking result into a valid JavaScript array declaration, to insert into `Map()` API.
   pub fn format_js_array(&self) -> String {
@@ -92,21 +187,288 @@ impl LineStackingResult {
 
 impl MoveStackingResult {
 
-  pub fn new(prev: &Grid<EncodedGrid>, new: &Grid<EncodedGrid>, delta: u32, dest: &Grid<DestinationsGrid>) -> Self {
+  /// Constructor, `merges` holds at most one merge for every two cells of the grid.
+  pub fn new(player_move: PlayerMove, prev: &Grid<EncodedGrid>, new: &Grid<EncodedGrid>, delta: u64, dest: &Grid<DestinationsGrid>, merges: &[MergeEvent]) -> Self {
+    let mut stored = [MergeEvent::default(); MAX_GRID_MERGES];
+    stored[..merges.len()].copy_from_slice(merges);
+
     MoveStackingResult {
+      player_move,
       prev_grid: *prev,
       new_grid: *new,
       delta_score: delta,
       destination_grid: *dest,
+      merges: stored,
+      merge_count: merges.len() as u8,
     }
   }
 
   // Getters
+  pub fn get_player_move(&self) -> PlayerMove { self.player_move }
   pub fn get_prev_grid(&self) -> &Grid<EncodedGrid> { &self.prev_grid }
   pub fn get_new_grid(&self) -> &Grid<EncodedGrid> { &self.new_grid }
-  pub fn get_delta_score(&self) -> u32 { self.delta_score }
+  pub fn get_delta_score(&self) -> u64 { self.delta_score }
   pub fn get_destination_grid(&self) -> &Grid<DestinationsGrid> { &self.destination_grid }
 
+  /// Returns the merges of the move in grid coordinates.
+  /// Rows, or columns for vertical moves, are listed in order, merges within a line from its first cell.
+  pub fn get_merges(&self) -> &[MergeEvent] { &self.merges[..self.merge_count as usize] }
+
+}
+
+impl LineMerge {
+
+  // Getters
+  pub fn get_sources(&self) -> [usize; 2] { [self.sources[0] as usize, self.sources[1] as usize] }
+  pub fn get_target(&self) -> usize { self.target as usize }
+  pub fn get_value(&self) -> EntryType { self.value }
+  pub fn get_score(&self) -> u64 { self.value as u64 }
+
+}
+
+impl MergeEvent {
+
+  /// Constructor from grid coordinates, which are below `MAX_GRID_SIDE`.
+  pub(crate) fn new(sources: [(usize, usize); 2], target: (usize, usize), value: EntryType) -> Self {
+    let position = |(row, column): (usize, usize)| (row as u8, column as u8);
+
+    MergeEvent {
+      sources: [position(sources[0]), position(sources[1])],
+      target: position(target),
+      value,
+    }
+  }
+
+  // Getters
+  pub fn get_sources(&self) -> [(usize, usize); 2] { [to_position(self.sources[0]), to_position(self.sources[1])] }
+  pub fn get_target(&self) -> (usize, usize) { to_position(self.target) }
+  pub fn get_value(&self) -> EntryType { self.value }
+  pub fn get_score(&self) -> u64 { self.value as u64 }
+
+}
+
+impl PackedLine {
+
+  fn new(result: &LineStackingResult) -> Self {
+    let mut packed = result.new_line as u64;
+
+    for (k, &dest) in result.destinations.iter().enumerate() {
+      packed |= (dest.unsigned_abs() as u64 & PACKED_DESTINATION_MASK) << (PACKED_LINE_BITS + PACKED_DESTINATION_BITS * k);
+    }
+
+    packed |= (result.merge_count as u64) << PACKED_MERGE_COUNT_SHIFT;
+    for (k, merge) in result.get_merges().iter().enumerate() {
+      let fields = merge.sources[0] as u64
+        | (merge.sources[1] as u64) << PACKED_CELL_BITS
+        | (merge.target as u64) << (2 * PACKED_CELL_BITS)
+        | (merge.value.trailing_zeros() as u64) << (3 * PACKED_CELL_BITS);
+
+      packed |= fields << (PACKED_MERGES_SHIFT + PACKED_MERGE_BITS * k);
+    }
+
+    PackedLine(packed)
+  }
+
+  /// Unpacks the result of stacking `prev_line`, whose displacements have sign `sign`, the score being the sum of the merged tiles.
+  fn unpack(self, prev_line: EncodedEntryType, sign: DestEntryType) -> LineStackingResult {
+    let mut destinations = [0; GRID_SIDE];
+    let mut merges = [LineMerge::default(); MAX_LINE_MERGES];
+    let mut delta_score = 0;
+
+    for (k, dest) in destinations.iter_mut().enumerate() {
+      *dest = sign * ((self.0 >> (PACKED_LINE_BITS + PACKED_DESTINATION_BITS * k)) & PACKED_DESTINATION_MASK) as DestEntryType;
+    }
+
+    let merge_count = ((self.0 >> PACKED_MERGE_COUNT_SHIFT) & ((1 << PACKED_MERGE_COUNT_BITS) - 1)) as u8;
+    for (k, merge) in merges[..merge_count as usize].iter_mut().enumerate() {
+      let fields = self.0 >> (PACKED_MERGES_SHIFT + PACKED_MERGE_BITS * k);
+
+      *merge = LineMerge {
+        sources: [(fields & PACKED_CELL_MASK) as u8, ((fields >> PACKED_CELL_BITS) & PACKED_CELL_MASK) as u8],
+        target: ((fields >> (2 * PACKED_CELL_BITS)) & PACKED_CELL_MASK) as u8,
+        value: 1 << ((fields >> (3 * PACKED_CELL_BITS)) & ENCODING_TILE_MASK as u64),
+      };
+      delta_score += merge.get_score();
+    }
+
+    LineStackingResult {
+      prev_line,
+      new_line: self.get_new_line(),
+      delta_score,
+      destinations,
+      merges,
+      merge_count,
+    }
+  }
+
+  fn get_new_line(self) -> EncodedEntryType {
+    (self.0 & ((1 << PACKED_LINE_BITS) - 1)) as EncodedEntryType
+  }
+
+}
+
+impl MovesTable {
+
+  /// Constructor, processes every encodable row.
+  /// Prefer the shared table returned by `precomputed_moves()`, which is built only once.
+  pub fn new() -> Self {
+    let size = 1 << PACKED_LINE_BITS;
+    let mut left = Vec::with_capacity(size);
+    let mut right = Vec::with_capacity(size);
+
+    for code in 0..size {
+      let line = encoding::decode_line(code as EncodedEntryType);
+
+      left.push(PackedLine::new(&process_line(&line)));
+      right.push(PackedLine::new(&process_right_line(&line)));
+    }
+
+    MovesTable {
+      left,
+      right,
+    }
+  }
+
+  /// Gets the result of stacking the encoded `line` to the left.
+  #[inline]
+  pub fn get_left(&self, line: EncodedEntryType) -> LineStackingResult { self.left[line as usize].unpack(line, -1) }
+
+  /// Gets the result of stacking the encoded `line` to the right.
+  #[inline]
+  pub fn get_right(&self, line: EncodedEntryType) -> LineStackingResult { self.right[line as usize].unpack(line, 1) }
+
+  /// Checks if the encoded `line` changes when stacked to the left.
+  #[inline]
+  pub fn can_move_left(&self, line: EncodedEntryType) -> bool { self.left[line as usize].get_new_line() != line }
+
+  /// Checks if the encoded `line` changes when stacked to the right.
+  #[inline]
+  pub fn can_move_right(&self, line: EncodedEntryType) -> bool { self.right[line as usize].get_new_line() != line }
+
+  /// Returns the moves changing the grid, looking up the stacked rows and columns only.
+  pub fn legal_moves(&self, grid: &Grid<EncodedGrid>) -> MoveSet {
+    let mut legal = MoveSet::empty();
+    let mut transposed = *grid;
+    transposed.transpose();
+
+    // rows give the horizontal moves, columns the vertical ones
+    for i in 0..GRID_SIDE {
+      if self.can_move_left(grid[i]) { legal.insert(PlayerMove::Left); }
+      if self.can_move_right(grid[i]) { legal.insert(PlayerMove::Right); }
+      if self.can_move_left(transposed[i]) { legal.insert(PlayerMove::Up); }
+      if self.can_move_right(transposed[i]) { legal.insert(PlayerMove::Down); }
+    }
+
+    legal
+  }
+
+}
+
+impl BitboardMovesTable {
+
+  /// Constructor, processes every bitboard row.
+  /// Prefer the shared table returned by `precomputed_bitboard_moves()`, which is built only once.
+  pub fn new() -> Self {
+    BitboardMovesTable {
+      left: (0..=u16::MAX).map(stack_bitboard_row).collect(),
+    }
+  }
+
+  /// Gets the bitboard `row` stacked to the left, `None` if it merges two `BITBOARD_LARGEST_TILE` tiles.
+  #[inline]
+  pub fn get_left(&self, row: u16) -> Option<u16> {
+    match self.left[row as usize] {
+      BITBOARD_OVERFLOW => None,
+      stacked => Some(stacked),
+    }
+  }
+
+  /// Gets the bitboard `row` stacked to the right, `None` if it merges two `BITBOARD_LARGEST_TILE` tiles.
+  #[inline]
+  pub fn get_right(&self, row: u16) -> Option<u16> { self.get_left(reverse_row(row)).map(reverse_row) }
+
+  /// Checks if the bitboard `row` changes when stacked to the left.
+  #[inline]
+  pub fn can_move_left(&self, row: u16) -> bool { self.left[row as usize] != row }
+
+  /// Checks if the bitboard `row` changes when stacked to the right.
+  #[inline]
+  pub fn can_move_right(&self, row: u16) -> bool { self.can_move_left(reverse_row(row)) }
+
+  /// Returns the moves changing the grid, including the moves merging past `BITBOARD_LARGEST_TILE`.
+  pub fn legal_moves(&self, grid: &Grid<BitboardGrid>) -> MoveSet {
+    let mut legal = MoveSet::empty();
+
+    // rows give the horizontal moves, columns the vertical ones
+    for i in 0..GRID_SIDE {
+      let (row, column) = (grid.get_row(i), grid.get_column(i));
+
+      if self.can_move_left(row) { legal.insert(PlayerMove::Left); }
+      if self.can_move_right(row) { legal.insert(PlayerMove::Right); }
+      if self.can_move_left(column) { legal.insert(PlayerMove::Up); }
+      if self.can_move_right(column) { legal.insert(PlayerMove::Down); }
+    }
+
+    legal
+  }
+
+}
+
+impl MoveSet {
+
+  /// Constructor of the empty set.
+  pub fn empty() -> Self {
+    MoveSet { bits: 0 }
+  }
+
+  /// Constructor of the set of all the moves.
+  pub fn all() -> Self {
+    MoveSet { bits: 0b1111 }
+  }
+
+  /// Adds a move to the set.
+  pub fn insert(&mut self, player_move: PlayerMove) -> &mut Self {
+    self.bits |= 1 << player_move as u8;
+    self
+  }
+
+  /// Removes a move from the set.
+  pub fn remove(&mut self, player_move: PlayerMove) -> &mut Self {
+    self.bits &= !(1 << player_move as u8);
+    self
+  }
+
+  /// Checks if the move is in the set.
+  pub fn contains(&self, player_move: PlayerMove) -> bool { self.bits & (1 << player_move as u8) != 0 }
+
+  /// Gets the number of moves in the set.
+  pub fn len(&self) -> usize { self.bits.count_ones() as usize }
+
+  /// Checks if the set has no moves.
+  pub fn is_empty(&self) -> bool { self.bits == 0 }
+
+  /// Returns an iterator over the moves of the set.
+  pub fn iter(&self) -> MoveSetIter { MoveSetIter { bits: self.bits } }
+
+}
+
+
+// Default
+
+impl Default for MovesTable {
+
+  fn default() -> Self {
+    MovesTable::new()
+  }
+
+}
+
+impl Default for BitboardMovesTable {
+
+  fn default() -> Self {
+    BitboardMovesTable::new()
+  }
+
 }
 
 
@@ -116,18 +478,60 @@ impl Iterator for AdmissibleTileValue {
   type Item = EntryType;
 
   fn next(&mut self) -> Option<Self::Item> {
+    let current = self.value?;
 
-    if self.value <= LARGEST_TILE {
-      self.prev = self.value;
-      if self.value == 0 {
-        self.value = 2;
-      } else {
-        self.value *= 2;
-      }
-      Some(self.prev)
-    } else {
-      None
+    // the largest value is the last one, doubling LARGEST_TILE would overflow
+    self.value = match current {
+      0 => Some(2),
+      _ if current >= self.largest => None,
+      _ => Some(current * 2),
+    };
+
+    Some(current)
+  }
+}
+
+impl Iterator for MoveSetIter {
+  type Item = PlayerMove;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    if self.bits == 0 {
+      return None;
     }
+
+    let player_move = match self.bits.trailing_zeros() {
+      0 => PlayerMove::Up,
+      1 => PlayerMove::Left,
+      2 => PlayerMove::Right,
+      _ => PlayerMove::Down,
+    };
+
+    // clear the lowest set bit
+    self.bits &= self.bits - 1;
+
+    Some(player_move)
+  }
+}
+
+impl IntoIterator for MoveSet {
+  type Item = PlayerMove;
+  type IntoIter = MoveSetIter;
+
+  fn into_iter(self) -> Self::IntoIter {
+    self.iter()
+  }
+}
+
+impl FromIterator<PlayerMove> for MoveSet {
+
+  fn from_iter<I: IntoIterator<Item = PlayerMove>>(iter: I) -> Self {
+    let mut set = MoveSet::empty();
+
+    for player_move in iter {
+      set.insert(player_move);
+    }
+
+    set
   }
 }
 
@@ -140,18 +544,114 @@ impl Iterator for AdmissibleTileValue {
 fn process_line(line: &Array1D<EntryType>) -> LineStackingResult {
   let mut new_line: Array1D<EntryType> = [0; GRID_SIDE];
   let mut destinations: Array1D<DestEntryType> = [0; GRID_SIDE];
+
+  let delta_score = stack_line(line, &mut new_line, &mut destinations);
+
+  LineStackingResult::new(line, &new_line, delta_score, &destinations)
+}
+
+/// Processes a line stacking it to the right, mirroring the left stacking of the reversed line.
+fn process_right_line(line: &Array1D<EntryType>) -> LineStackingResult {
+  let mut reversed = *line;
+  let mut new_line: Array1D<EntryType> = [0; GRID_SIDE];
+  let mut destinations: Array1D<DestEntryType> = [0; GRID_SIDE];
+
+  reversed.reverse();
+  let delta_score = stack_line(&reversed, &mut new_line, &mut destinations);
+
+  // displacements to the right are positive
+  new_line.reverse();
+  destinations.reverse();
+  for dest in destinations.iter_mut() {
+    *dest = -*dest;
+  }
+
+  LineStackingResult::new(line, &new_line, delta_score, &destinations)
+}
+
+/// Stacks a bitboard row to the left, `BITBOARD_OVERFLOW` if a merged tile is larger than `BITBOARD_LARGEST_TILE`.
+fn stack_bitboard_row(row: u16) -> u16 {
+  let mut line: Array1D<EntryType> = [0; GRID_SIDE];
+  let mut new_line: Array1D<EntryType> = [0; GRID_SIDE];
+  let mut destinations: Array1D<DestEntryType> = [0; GRID_SIDE];
+
+  for (k, tile) in line.iter_mut().enumerate() {
+    let exponent = (row >> (BITBOARD_BITS * k)) & 0xF;
+
+    if exponent != 0 {
+      *tile = 1 << exponent;
+    }
+  }
+
+  stack_line(&line, &mut new_line, &mut destinations);
+
+  let mut stacked = 0;
+
+  for (k, &tile) in new_line.iter().enumerate() {
+    if tile > BITBOARD_LARGEST_TILE {
+      return BITBOARD_OVERFLOW;
+    }
+
+    if tile != 0 {
+      stacked |= (tile.trailing_zeros() as u16) << (BITBOARD_BITS * k);
+    }
+  }
+
+  stacked
+}
+
+/// Reverses the tiles of a bitboard row.
+fn reverse_row(row: u16) -> u16 {
+
+  // swap the tiles within each byte, then the bytes
+  (((row & 0x0F0F) << 4) | ((row >> 4) & 0x0F0F)).swap_bytes()
+}
+
+/// Finds the merges of a stacked line of any length from its encoded tiles before the move and their displacements, from its first cell.
+/// Merging tiles are consecutive non-empty tiles ending up in the same cell.
+pub(crate) fn line_merges(prev_line: EncodedEntryType, destinations: &[DestEntryType]) -> impl Iterator<Item = LineMerge> + '_ {
+  let mut last: Option<(u8, u8)> = None;
+
+  destinations.iter().enumerate().filter_map(move |(j, &dest)| {
+    let code = (prev_line >> (ENCODING_BITS * j)) & ENCODING_TILE_MASK;
+    if code == 0 { return None; }
+
+    let source = j as u8;
+    let target = (j as DestEntryType + dest) as u8;
+    let merge = last
+      .filter(|&(_, last_target)| last_target == target)
+      .map(|(last_source, _)| LineMerge {
+        sources: [last_source, source],
+        target,
+        value: encoding::decode_tile(code) * 2,
+      });
+
+    last = Some((source, target));
+    merge
+  })
+}
+
+/// Converts a compact (row, column) position of a merge to grid coordinates.
+fn to_position((row, column): (u8, u8)) -> (usize, usize) {
+  (row as usize, column as usize)
+}
+
+/// Stacks a row of any length to the left according to the 2048 game rules.
+/// The stacked row and the displacement of each tile are written to the zero-initialized out parameters, and the score gained is returned.
+pub fn stack_line(line: &[EntryType], new_line: &mut [EntryType], destinations: &mut [DestEntryType]) -> u64 {
   let mut delta_score = 0;
   let mut k = 0;
 
-  for i in 0..4 {
+  for i in 0..line.len() {
 
     // move only non-zero tiles
     if line[i] != 0 {
 
       // if current tile in new line is equal to current tile in old line, merge and point to next current tile in new line
-      if new_line[k] == line[i] {
+      // LARGEST_TILE can't be doubled within the encoding, so these tiles never merge
+      if new_line[k] == line[i] && line[i] < LARGEST_TILE {
         new_line[k] += line[i];
-        delta_score += new_line[k];
+        delta_score += new_line[k] as u64;
         destinations[i] = k as i8 - i as i8;
         k += 1;
 
@@ -170,20 +670,21 @@ fn process_line(line: &Array1D<EntryType>) -> LineStackingResult {
 
   }
 
-  LineStackingResult::new(line, &new_line, delta_score, &destinations)
+  delta_score
 }
 
 /// Function that recursively generates only and all the admissible row states to be encoded and saved in a `HashMap`.
 /// The definition allows to avoid nesting n loops for n the length of a row, and is applicable to any row length.
-fn traverse_row(row: &Array1D<EntryType>, position: usize, moves_table: &mut HashMap<EncodedEntryType, LineStackingResult>) {
+/// The tiles of the rows go up to `largest`, which bounds the number of rows generated.
+fn traverse_row(row: &Array1D<EntryType>, position: usize, largest: EntryType, moves_table: &mut HashMap<EncodedEntryType, LineStackingResult>) {
 
   if position < row.len() {
 
     // loop through all the admissible values for each tile position in the row
-    for num in AdmissibleTileValue::new(0) {
+    for num in AdmissibleTileValue::new(0, largest) {
       let mut new_row = *row;
       new_row[position] = num;
-      traverse_row(&new_row, position + 1, moves_table);
+      traverse_row(&new_row, position + 1, largest, moves_table);
     }
 
   // when all the tiles in a single row are chosen (base case), process the row and store the result
@@ -198,68 +699,109 @@ fn traverse_row(row: &Array1D<EntryType>, position: usize, moves_table: &mut Has
   }
 }
 
-/// Generates the `HashMap` of precomputed single-row moves required to speed up the processing of full-grid moves.
+/// Generates the `HashMap` of precomputed effectful single-row left moves, used to export the moves to JavaScript.
+/// The rows hold tiles up to 65536 as the JavaScript front end expects, merging into 131072 at most,
+/// which keeps the export to the 17^4 rows it always had instead of the 32^4 rows of the full exponent range.
 pub fn make_precomputed_hashmap() -> HashMap<EncodedEntryType, LineStackingResult> {
   let mut moves_table: HashMap<EncodedEntryType, LineStackingResult> = HashMap::new();
 
   //Generate moves
-  traverse_row(&[0, 0, 0, 0], 0, &mut moves_table);
+  traverse_row(&[0, 0, 0, 0], 0, JS_LARGEST_TILE, &mut moves_table);
 
   moves_table
 }
 
-/// Process the stacking of the grid based on the player move
-pub fn process_grid_stacking(player_move: PlayerMove, grid: &Grid<EncodedGrid>, moves_table: &HashMap<EncodedEntryType, LineStackingResult>) -> MoveStackingResult {
+/// Returns the table of precomputed moves shared by the whole program, building it on first use.
+pub fn precomputed_moves() -> &'static MovesTable {
+  static MOVES_TABLE: OnceLock<MovesTable> = OnceLock::new();
+
+  MOVES_TABLE.get_or_init(MovesTable::new)
+}
+
+/// Returns the table of precomputed bitboard moves shared by the whole program, building it on first use.
+pub fn precomputed_bitboard_moves() -> &'static BitboardMovesTable {
+  static BITBOARD_MOVES_TABLE: OnceLock<BitboardMovesTable> = OnceLock::new();
+
+  BITBOARD_MOVES_TABLE.get_or_init(BitboardMovesTable::new)
+}
+
+/// Returns the moves changing the grid, see `MovesTable::legal_moves()`.
+pub fn legal_moves(grid: &Grid<EncodedGrid>) -> MoveSet {
+  precomputed_moves().legal_moves(grid)
+}
+
+/// Process the stacking of the grid based on the player move.
+pub fn process_grid_stacking(player_move: PlayerMove, grid: &Grid<EncodedGrid>, moves_table: &MovesTable) -> MoveStackingResult {
   let mut new_grid = *grid;
-  let mut tot_delta_score: u32 = 0;
+  let mut tot_delta_score: u64 = 0;
   let mut dest_grid = Grid::<DestinationsGrid>::new(&[[0; GRID_SIDE]; GRID_SIDE]);
+  let mut merges = [MergeEvent::default(); MAX_GRID_MERGES];
+  let mut merge_count = 0;
+  let vertical = matches!(player_move, PlayerMove::Up | PlayerMove::Down);
+
+  // Vertical moves process the columns as rows
+  if vertical {
+    new_grid.transpose();
+  }
 
-  // Transform grid to conform to left move
-  match player_move {
-    PlayerMove::Up => {
-      new_grid.transpose();
-    },
-    PlayerMove::Left => (),
-    PlayerMove::Right => {
-      new_grid.reverse();
-    },
-    PlayerMove::Down => {
-      new_grid.transpose().reverse();
-    },
-  };
-
-  // find new state from move_table
+  // find new state from move_table, ineffective rows are stored with their old value and no displacement
   for i in 0..GRID_SIDE {
+    let result = match player_move {
+      PlayerMove::Up | PlayerMove::Left => moves_table.get_left(new_grid[i]),
+      PlayerMove::Right | PlayerMove::Down => moves_table.get_right(new_grid[i]),
+    };
+
+    new_grid[i] = result.get_new_line();
+    tot_delta_score += result.get_delta_score();
+    dest_grid[i] = result.get_destinations();
+
+    // merges are found on the processed line, map them back to the grid coordinates
+    let position = |k: u8| if vertical { (k, i as u8) } else { (i as u8, k) };
+    for merge in result.get_merges() {
+      merges[merge_count] = MergeEvent {
+        sources: [position(merge.sources[0]), position(merge.sources[1])],
+        target: position(merge.target),
+        value: merge.value,
+      };
+      merge_count += 1;
+    }
+  }
 
-    // process each row if in table, else it means that it had no effect so the old value is kept 
-    if moves_table.contains_key(&new_grid[i]) {
-      let result = moves_table.get(&new_grid[i]).unwrap();
+  // Rebuild original orientation
+  if vertical {
+    new_grid.transpose();
+    dest_grid.transpose();
+  }
 
-      new_grid[i] = result.get_new_line();
-      tot_delta_score += result.get_delta_score();
-      dest_grid[i] = result.get_destinations();
-    }
+  MoveStackingResult::new(player_move, grid, &new_grid, tot_delta_score, &dest_grid, &merges[..merge_count])
+}
 
+/// Process the stacking of a bitboard grid based on the player move, giving the stacked grid only.
+/// Returns `GridError::TileTooLarge` if a merge would go past `BITBOARD_LARGEST_TILE`, the move being then played on the encoded grid.
+pub fn process_bitboard_stacking(player_move: PlayerMove, grid: &Grid<BitboardGrid>, moves_table: &BitboardMovesTable) -> Result<Grid<BitboardGrid>, GridError> {
+  let mut new_grid = *grid;
+  let vertical = matches!(player_move, PlayerMove::Up | PlayerMove::Down);
+
+  // Vertical moves process the columns as rows
+  if vertical {
+    new_grid.transpose();
   }
 
-  // Rebuild original move and destination
-  match player_move {
-    PlayerMove::Up => {
-      new_grid.transpose();
-      dest_grid.transpose();
-    },
-    PlayerMove::Left => (),
-    PlayerMove::Right => {
-      new_grid.reverse();
-      dest_grid.reverse().change_sign();
-    },
-    PlayerMove::Down => {
-      new_grid.reverse().transpose();
-      dest_grid.reverse().transpose().change_sign();
-    },
-  };
+  for i in 0..GRID_SIDE {
+    let stacked = match player_move {
+      PlayerMove::Up | PlayerMove::Left => moves_table.get_left(new_grid.get_row(i)),
+      PlayerMove::Right | PlayerMove::Down => moves_table.get_right(new_grid.get_row(i)),
+    };
 
-  MoveStackingResult::new(&grid, &new_grid, tot_delta_score, &dest_grid)
+    new_grid.set_row(i, stacked.ok_or(GridError::TileTooLarge(2 * BITBOARD_LARGEST_TILE))?);
+  }
+
+  // Rebuild original orientation
+  if vertical {
+    new_grid.transpose();
+  }
+
+  Ok(new_grid)
 }
 
 
@@ -318,6 +860,19 @@ mod tests {
   }
 
 
+  #[test]
+  fn stacks_long_line_correctly() {
+    let mut new_line = [0; 6];
+    let mut destinations = [0; 6];
+
+    let delta_score = stack_line(&[2, 2, 0, 4, 4, 4], &mut new_line, &mut destinations);
+
+    assert_eq!(new_line, [4, 8, 4, 0, 0, 0]);
+    assert_eq!(destinations, [0, -1, 0, -2, -3, -3]);
+    assert_eq!(delta_score, 12);
+  }
+
+
   // Test single row scoring
 
   #[test]
@@ -366,11 +921,127 @@ mod tests {
   }
 
 
+  // Test MovesTable
+
+  // Reverses the tiles of an encoded line
+  fn reverse_line(line: EncodedEntryType) -> EncodedEntryType {
+    let mut reversed = 0;
+
+    for k in 0..GRID_SIDE {
+      reversed |= ((line >> (ENCODING_BITS * k)) & ENCODING_TILE_MASK) << (ENCODING_BITS * (GRID_SIDE - 1 - k));
+    }
+
+    reversed
+  }
+
+  #[test]
+  pub fn test_moves_table_matches_hashmap() {
+    let moves_table = precomputed_moves();
+    let moves_hashmap = make_precomputed_hashmap();
+
+    // every effectful move in the hashmap is in the table, and every other row of exported tiles is ineffective
+    let exported = |code: EncodedEntryType| encoding::decode_line(code).iter().all(|&tile| tile <= JS_LARGEST_TILE);
+
+    for code in (0..(1 << (ENCODING_BITS * GRID_SIDE))).filter(|&code| exported(code)) {
+      let result = moves_table.get_left(code);
+
+      match moves_hashmap.get(&code) {
+        Some(expected) => assert_eq!(result, *expected),
+        None => {
+          assert_eq!(result.get_new_line(), code);
+          assert_eq!(result.get_destinations(), [0; GRID_SIDE]);
+        },
+      }
+    }
+  }
+
+  #[test]
+  pub fn test_precomputed_hashmap_js_tiles() {
+    let moves_hashmap = make_precomputed_hashmap();
+
+    // the export holds no tile above 65536, while the rows of 65536s still merge
+    assert!(moves_hashmap.keys().all(|&code| encoding::decode_line(code).iter().all(|&tile| tile <= JS_LARGEST_TILE)));
+    assert!(!moves_hashmap.contains_key(&encoding::encode_line(&[2 * JS_LARGEST_TILE, 0, 0, 2])));
+    assert_eq!(moves_hashmap[&encoding::encode_line(&[JS_LARGEST_TILE, JS_LARGEST_TILE, 0, 0])].get_delta_score(), 2 * JS_LARGEST_TILE as u64);
+  }
+
+  #[test]
+  pub fn test_moves_table_right() {
+    let moves_table = precomputed_moves();
+
+    let result = moves_table.get_right(encoding::encode_line(&[4, 4, 2, 2]));
+
+    assert_eq!(result.get_new_line(), encoding::encode_line(&[0, 0, 8, 4]));
+    assert_eq!(result.get_delta_score(), 12);
+    assert_eq!(result.get_destinations(), [2, 1, 1, 0]);
+  }
+
+  #[test]
+  pub fn test_moves_table_right_mirrors_left() {
+    let moves_table = precomputed_moves();
+
+    // every right move matches stacking the row reversed, sampling rows over the whole table
+    for code in (0..(1 << (ENCODING_BITS * GRID_SIDE))).step_by(97) {
+      let mut line = encoding::decode_line(code);
+      line.reverse();
+
+      let mirrored = process_line(&line);
+      let result = moves_table.get_right(code);
+
+      assert_eq!(result.get_prev_line(), code);
+      assert_eq!(result.get_new_line(), reverse_line(mirrored.get_new_line()));
+      assert_eq!(result.get_delta_score(), mirrored.get_delta_score());
+      assert_eq!(result, process_right_line(&encoding::decode_line(code)));
+      for k in 0..GRID_SIDE {
+        assert_eq!(result.get_destinations()[k], -mirrored.get_destinations()[GRID_SIDE - 1 - k]);
+      }
+    }
+  }
+
+  #[test]
+  pub fn test_moves_table_footprint() {
+    assert_eq!(std::mem::size_of::<PackedLine>(), 8);
+    assert_eq!(precomputed_moves().left.len(), 1 << 20);
+    assert_eq!(precomputed_moves().right.len(), 1 << 20);
+  }
+
+  #[test]
+  pub fn test_moves_table_above_65536() {
+    let moves_table = precomputed_moves();
+    let line = encoding::encode_line(&[65536, 65536, 1 << 20, 1 << 20]);
+    let result = moves_table.get_left(line);
+
+    assert_eq!(result.get_new_line(), encoding::encode_line(&[131072, 1 << 21, 0, 0]));
+    assert_eq!(result.get_delta_score(), 131072 + (1 << 21));
+    assert_eq!(result.get_merges().len(), 2);
+  }
+
+  #[test]
+  pub fn test_moves_table_largest_tile() {
+    let moves_table = precomputed_moves();
+
+    // the largest tiles can't merge any further
+    let line = encoding::encode_line(&[LARGEST_TILE, LARGEST_TILE, 0, 0]);
+
+    assert_eq!(moves_table.get_left(line).get_new_line(), line);
+    assert_eq!(moves_table.get_right(line).get_new_line(), encoding::encode_line(&[0, 0, LARGEST_TILE, LARGEST_TILE]));
+    assert_eq!(moves_table.get_right(line).get_delta_score(), 0);
+    assert!(moves_table.get_right(line).get_merges().is_empty());
+
+    // merging into the largest tile is allowed, the score exceeds 32 bits
+    let line = encoding::encode_line(&[LARGEST_TILE / 2, LARGEST_TILE / 2, LARGEST_TILE / 2, LARGEST_TILE / 2]);
+    let result = moves_table.get_left(line);
+
+    assert_eq!(result.get_new_line(), encoding::encode_line(&[LARGEST_TILE, LARGEST_TILE, 0, 0]));
+    assert_eq!(result.get_delta_score(), 2 * LARGEST_TILE as u64);
+  }
+
+
   // Test full move results
 
   #[test]
   pub fn test_up_move() {
-    let moves_table = make_precomputed_hashmap();
+    let moves_table = precomputed_moves();
 
     let grid = Grid::from_decoded(&[
       [0, 2, 2, 0],
@@ -393,7 +1064,7 @@ mod tests {
       [-2, 0, -2, -3],
     ]);
 
-    let result = process_grid_stacking(PlayerMove::Up, &grid, &moves_table);
+    let result = process_grid_stacking(PlayerMove::Up, &grid, moves_table);
 
     assert_eq!(*result.get_new_grid().get_state(), *new_grid.get_state(), "\n{}{}\n", result.get_new_grid(), new_grid);
     assert_eq!(result.get_delta_score(), 20);
@@ -402,7 +1073,7 @@ mod tests {
 
   #[test]
   pub fn test_left_move() {
-    let moves_table = make_precomputed_hashmap();
+    let moves_table = precomputed_moves();
 
     let grid = Grid::from_decoded(&[
       [0, 2, 2, 0],
@@ -425,7 +1096,7 @@ mod tests {
       [0, 0, -1, -1],
     ]);
 
-    let result: MoveStackingResult = process_grid_stacking(PlayerMove::Left, &grid, &moves_table);
+    let result: MoveStackingResult = process_grid_stacking(PlayerMove::Left, &grid, moves_table);
 
     assert_eq!(*result.get_new_grid().get_state(), *new_grid.get_state(), "\n{}{}\n", result.get_new_grid(), new_grid);
     assert_eq!(result.get_delta_score(), 12);
@@ -434,7 +1105,7 @@ mod tests {
 
   #[test]
   pub fn test_right_move() {
-    let moves_table = make_precomputed_hashmap();
+    let moves_table = precomputed_moves();
 
     let grid = Grid::from_decoded(&[
       [0, 2, 2, 0],
@@ -457,7 +1128,7 @@ mod tests {
       [1, 0, 0, 0],
     ]);
 
-    let result: MoveStackingResult = process_grid_stacking(PlayerMove::Right, &grid, &moves_table);
+    let result: MoveStackingResult = process_grid_stacking(PlayerMove::Right, &grid, moves_table);
 
     assert_eq!(*result.get_new_grid().get_state(), *new_grid.get_state(), "\n{}{}\n", result.get_new_grid(), new_grid);
     assert_eq!(result.get_delta_score(), 12);
@@ -466,7 +1137,7 @@ mod tests {
 
   #[test]
   pub fn test_down_move() {
-    let moves_table = make_precomputed_hashmap();
+    let moves_table = precomputed_moves();
 
     let grid = Grid::from_decoded(&[
       [0, 2, 2, 0],
@@ -489,10 +1160,216 @@ mod tests {
       [0, 0, 0, 0],
     ]);
 
-    let result: MoveStackingResult = process_grid_stacking(PlayerMove::Down, &grid, &moves_table);
+    let result: MoveStackingResult = process_grid_stacking(PlayerMove::Down, &grid, moves_table);
 
     assert_eq!(*result.get_new_grid().get_state(), *new_grid.get_state(), "\n{}{}\n", result.get_new_grid(), new_grid);
     assert_eq!(result.get_delta_score(), 20);
     assert_eq!(*result.get_destination_grid(), dest_grid);
   }
+
+
+  // Test merge events
+
+  fn merge(sources: [(u8, u8); 2], target: (u8, u8), value: EntryType) -> MergeEvent {
+    MergeEvent { sources, target, value }
+  }
+
+  #[test]
+  pub fn test_line_merges() {
+    let moves_table = precomputed_moves();
+    let line = encoding::encode_line(&[4, 4, 2, 2]);
+
+    assert_eq!(moves_table.get_left(line).get_merges(), vec![
+      LineMerge { sources: [0, 1], target: 0, value: 8 },
+      LineMerge { sources: [2, 3], target: 1, value: 4 },
+    ]);
+    assert_eq!(moves_table.get_right(line).get_merges(), vec![
+      LineMerge { sources: [0, 1], target: 2, value: 8 },
+      LineMerge { sources: [2, 3], target: 3, value: 4 },
+    ]);
+    assert_eq!(process_line(&[2, 0, 2, 2]).get_merges(), vec![LineMerge { sources: [0, 2], target: 0, value: 4 }]);
+    assert!(process_line(&[2, 4, 2, 4]).get_merges().is_empty());
+  }
+
+  #[test]
+  pub fn test_grid_merges() {
+    let moves_table = precomputed_moves();
+
+    let grid = Grid::from_decoded(&[
+      [0, 2, 2, 0],
+      [2, 2, 2, 2],
+      [0, 0, 4, 0],
+      [8, 0, 4, 2],
+    ]);
+
+    let expected = [
+      (PlayerMove::Up, vec![
+        merge([(0, 1), (1, 1)], (0, 1), 4),
+        merge([(0, 2), (1, 2)], (0, 2), 4),
+        merge([(2, 2), (3, 2)], (1, 2), 8),
+        merge([(1, 3), (3, 3)], (0, 3), 4),
+      ]),
+      (PlayerMove::Left, vec![
+        merge([(0, 1), (0, 2)], (0, 0), 4),
+        merge([(1, 0), (1, 1)], (1, 0), 4),
+        merge([(1, 2), (1, 3)], (1, 1), 4),
+      ]),
+      (PlayerMove::Right, vec![
+        merge([(0, 1), (0, 2)], (0, 3), 4),
+        merge([(1, 0), (1, 1)], (1, 2), 4),
+        merge([(1, 2), (1, 3)], (1, 3), 4),
+      ]),
+      (PlayerMove::Down, vec![
+        merge([(0, 1), (1, 1)], (3, 1), 4),
+        merge([(0, 2), (1, 2)], (2, 2), 4),
+        merge([(2, 2), (3, 2)], (3, 2), 8),
+        merge([(1, 3), (3, 3)], (3, 3), 4),
+      ]),
+    ];
+
+    for (player_move, merges) in expected.iter() {
+      let result = process_grid_stacking(*player_move, &grid, moves_table);
+
+      assert_eq!(result.get_merges(), *merges, "{:?}", player_move);
+      assert_eq!(result.get_merges().iter().map(|m| m.get_score()).sum::<u64>(), result.get_delta_score());
+    }
+  }
+
+
+  // Test bitboard moves
+
+  #[test]
+  pub fn test_bitboard_moves_match_encoded() {
+    let moves_table = precomputed_moves();
+    let bitboard_moves_table = precomputed_bitboard_moves();
+
+    let grid = Grid::from_decoded(&[
+      [0, 2, 2, 0],
+      [2, 2, 16384, 16384],
+      [0, 0, 4, 0],
+      [8, 1024, 4, 2],
+    ]);
+    let bitboard = Grid::<BitboardGrid>::from_encoded(&grid).unwrap();
+
+    assert_eq!(bitboard_moves_table.legal_moves(&bitboard), moves_table.legal_moves(&grid));
+
+    use PlayerMove::{Up, Left, Right, Down};
+    for &player_move in [Up, Left, Right, Down].iter() {
+      let result = process_grid_stacking(player_move, &grid, moves_table);
+      let bitboard_result = process_bitboard_stacking(player_move, &bitboard, bitboard_moves_table).unwrap();
+
+      assert_eq!(bitboard_result.to_encoded(), *result.get_new_grid(), "{:?}", player_move);
+    }
+  }
+
+  #[test]
+  pub fn test_bitboard_moves_table_rows() {
+    let moves_table = precomputed_moves();
+    let bitboard_moves_table = precomputed_bitboard_moves();
+
+    // every row without tiles of BITBOARD_LARGEST_TILE stacks as its encoded line
+    for row in (0..=u16::MAX).step_by(97).filter(|row| (0..GRID_SIDE).all(|k| (row >> (BITBOARD_BITS * k)) & 0xF != 0xF)) {
+      let mut bitboard = Grid::<BitboardGrid>::new(&0);
+      bitboard.set_row(0, row);
+      let line = bitboard.get_line(0);
+
+      bitboard.set_row(0, bitboard_moves_table.get_left(row).unwrap());
+      assert_eq!(bitboard.get_line(0), moves_table.get_left(line).get_new_line(), "{:#x}", row);
+
+      bitboard.set_row(0, bitboard_moves_table.get_right(row).unwrap());
+      assert_eq!(bitboard.get_line(0), moves_table.get_right(line).get_new_line(), "{:#x}", row);
+    }
+
+    assert_eq!(bitboard_moves_table.left.len(), 1 << 16);
+  }
+
+  #[test]
+  pub fn test_bitboard_move_overflow() {
+    let bitboard_moves_table = precomputed_bitboard_moves();
+
+    let bitboard = Grid::<BitboardGrid>::from_encoded(&Grid::from_decoded(&[
+      [32768, 0, 32768, 0],
+      [0, 0, 0, 0],
+      [2, 0, 0, 2],
+      [0, 0, 0, 0],
+    ])).unwrap();
+
+    // the first row merges past the largest tile the bitboard can hold, the move is legal but not representable
+    assert!(bitboard_moves_table.legal_moves(&bitboard).contains(PlayerMove::Left));
+    assert_eq!(process_bitboard_stacking(PlayerMove::Left, &bitboard, bitboard_moves_table), Err(GridError::TileTooLarge(65536)));
+    assert_eq!(process_bitboard_stacking(PlayerMove::Right, &bitboard, bitboard_moves_table), Err(GridError::TileTooLarge(65536)));
+
+    // while moves not merging the largest tiles are
+    assert_eq!(process_bitboard_stacking(PlayerMove::Down, &bitboard, bitboard_moves_table).unwrap().to_encoded(), Grid::from_decoded(&[
+      [0, 0, 0, 0],
+      [0, 0, 0, 0],
+      [32768, 0, 0, 0],
+      [2, 0, 32768, 2],
+    ]));
+  }
+
+
+  // Test legal moves
+
+  #[test]
+  pub fn test_move_set() {
+    let mut set = MoveSet::empty();
+    assert!(set.is_empty());
+
+    set.insert(PlayerMove::Down).insert(PlayerMove::Left);
+
+    assert_eq!(set.len(), 2);
+    assert!(set.contains(PlayerMove::Left));
+    assert!(!set.contains(PlayerMove::Up));
+    assert_eq!(set.iter().collect::<Vec<_>>(), vec![PlayerMove::Left, PlayerMove::Down]);
+
+    set.remove(PlayerMove::Down);
+    assert_eq!(set, vec![PlayerMove::Left].into_iter().collect());
+    assert_eq!(MoveSet::all().into_iter().collect::<Vec<_>>(), vec![PlayerMove::Up, PlayerMove::Left, PlayerMove::Right, PlayerMove::Down]);
+  }
+
+  #[test]
+  pub fn test_moves_table_flags() {
+    let moves_table = precomputed_moves();
+
+    assert!(!moves_table.can_move_left(encoding::encode_line(&[2, 4, 0, 0])));
+    assert!(moves_table.can_move_right(encoding::encode_line(&[2, 4, 0, 0])));
+    assert!(moves_table.can_move_left(encoding::encode_line(&[2, 2, 8, 16])));
+    assert!(!moves_table.can_move_right(encoding::encode_line(&[0, 0, 0, 0])));
+    assert!(!moves_table.can_move_left(encoding::encode_line(&[LARGEST_TILE, LARGEST_TILE, 0, 0])));
+  }
+
+  #[test]
+  pub fn test_legal_moves() {
+    let grid = Grid::from_decoded(&[
+      [2, 4, 8, 16],
+      [0, 0, 0, 0],
+      [0, 0, 0, 0],
+      [0, 0, 0, 0],
+    ]);
+
+    assert_eq!(legal_moves(&grid).iter().collect::<Vec<_>>(), vec![PlayerMove::Down]);
+
+    let grid = Grid::from_decoded(&[
+      [2, 4, 2, 4],
+      [4, 2, 4, 2],
+      [2, 4, 2, 4],
+      [4, 2, 4, 2],
+    ]);
+
+    assert!(legal_moves(&grid).is_empty());
+
+    let grid = Grid::from_decoded(&[
+      [2, 4, 2, 4],
+      [4, 2, 4, 2],
+      [2, 4, 2, 4],
+      [4, 2, 8, 8],
+    ]);
+
+    let mut horizontal = MoveSet::all();
+    horizontal.remove(PlayerMove::Up).remove(PlayerMove::Down);
+
+    assert_eq!(legal_moves(&grid), horizontal);
+  }
+
 }
\ No newline at end of file
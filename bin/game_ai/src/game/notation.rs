@@ -0,0 +1,268 @@
+//! # `notation` module
+//!
+//! This module defines the text forms of a grid: parsing with `FromStr` and rendering as a board.
+//! Two notations are parsed, the compact `2 4 . 8 / . . 2 . / ...` one with `.` or `0` for empty cells and rows split by `/` or new lines,
+//! and the bracketed `[[2, 4, 0, 8], ...]` one, which includes the output of `Display`.
+
+use std::convert::TryFrom;
+use std::str::FromStr;
+
+use crate::error::GridError;
+use crate::game::core::*;
+
+
+//------------------------------------------------
+// Types and Definitions
+//------------------------------------------------
+
+const ANSI_RESET: &str = "\x1b[0m";
+
+// 256-colour ANSI foreground codes, indexed by tile exponent and cycled for tiles beyond the palette
+const ANSI_PALETTE: [u8; 12] = [250, 230, 223, 215, 209, 203, 196, 228, 227, 226, 220, 214];
+
+
+//------------------------------------------------
+// Implementations
+//------------------------------------------------
+
+// Inherent
+
+impl Grid<EncodedGrid> {
+
+  /// Returns the grid in compact notation, e.g. `2 4 . 8 / . . 2 . / . . . . / 4 . . .`, which can be parsed back.
+  pub fn to_compact(&self) -> String {
+    (0..GRID_SIDE)
+      .map(|i| {
+        self.get_row_tiles(i).iter()
+          .map(|&tile| if tile == 0 { ".".to_string() } else { tile.to_string() })
+          .collect::<Vec<_>>()
+          .join(" ")
+      })
+      .collect::<Vec<_>>()
+      .join(" / ")
+  }
+
+  /// Renders the grid as a board drawn with box characters, the columns aligned on the largest tile.
+  pub fn render(&self) -> String {
+    render_board(self, false)
+  }
+
+  /// Renders the grid like `render()`, colouring each tile by its exponent with ANSI escape codes.
+  pub fn render_colored(&self) -> String {
+    render_board(self, true)
+  }
+
+}
+
+
+// FromStr
+
+impl FromStr for Grid<EncodedGrid> {
+  type Err = GridError;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let rows = if s.contains('[') { bracketed_rows(s) } else { compact_rows(s) };
+
+    if rows.len() != GRID_SIDE {
+      return Err(GridError::WrongRowCount(rows.len()));
+    }
+
+    let mut decoded = [[0; GRID_SIDE]; GRID_SIDE];
+
+    for (i, row) in rows.iter().enumerate() {
+      if row.len() != GRID_SIDE {
+        return Err(GridError::WrongRowLength { row: i, length: row.len() });
+      }
+
+      for (j, token) in row.iter().enumerate() {
+        decoded[i][j] = match *token {
+          "." => 0,
+          _ => token.parse().map_err(|_| GridError::InvalidToken { row: i, col: j })?,
+        };
+      }
+    }
+
+    Grid::try_from_decoded(&decoded)
+  }
+
+}
+
+impl FromStr for Grid<BitboardGrid> {
+  type Err = GridError;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    Grid::try_from(s.parse::<Grid<EncodedGrid>>()?)
+  }
+
+}
+
+
+//------------------------------------------------
+// Functions
+//------------------------------------------------
+
+/// Splits the compact notation into rows of tokens, ignoring blank rows.
+fn compact_rows(s: &str) -> Vec<Vec<&str>> {
+  s.split(['/', '\n'])
+    .filter(|row| !row.trim().is_empty())
+    .map(|row| row.split_whitespace().collect())
+    .collect()
+}
+
+/// Splits the bracketed notation into rows of tokens, one row per innermost pair of brackets.
+fn bracketed_rows(s: &str) -> Vec<Vec<&str>> {
+  let mut rows = vec![];
+  let mut start = None;
+
+  for (k, c) in s.char_indices() {
+    match c {
+      '[' => start = Some(k + 1),
+      ']' => {
+        if let Some(begin) = start.take() {
+          rows.push(s[begin..k].split(',').map(|token| token.trim()).filter(|token| !token.is_empty()).collect());
+        }
+      },
+      _ => {},
+    }
+  }
+
+  rows
+}
+
+/// Draws the board, padding every cell to the width of the largest tile.
+fn render_board(grid: &Grid<EncodedGrid>, colored: bool) -> String {
+  let width = grid.max_tile().max(1).to_string().len() + 2;
+  let border = |left: char, middle: char, right: char| {
+    let segments = vec!["─".repeat(width); GRID_SIDE];
+    format!("{}{}{}\n", left, segments.join(&middle.to_string()), right)
+  };
+
+  let mut board = border('┌', '┬', '┐');
+
+  for i in 0..GRID_SIDE {
+    if i > 0 {
+      board.push_str(&border('├', '┼', '┤'));
+    }
+
+    for j in 0..GRID_SIDE {
+      let tile = grid.get_tile(i, j);
+      let text = if tile == 0 { String::new() } else { tile.to_string() };
+      let padding = " ".repeat(width - 1 - text.len());
+
+      board.push('│');
+      board.push_str(&padding);
+
+      if colored && tile != 0 {
+        let color = ANSI_PALETTE[(grid.get_exponent(i, j) as usize - 1) % ANSI_PALETTE.len()];
+        board.push_str(&format!("\x1b[38;5;{}m{}{}", color, text, ANSI_RESET));
+      } else {
+        board.push_str(&text);
+      }
+
+      board.push(' ');
+    }
+
+    board.push_str("│\n");
+  }
+
+  board.push_str(&border('└', '┴', '┘'));
+  board
+}
+
+
+//------------------------------------------------
+// Unit tests
+//------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+
+  use super::*;
+
+  fn test_grid() -> Grid<EncodedGrid> {
+    Grid::from_decoded(&[
+      [2, 4, 0, 8],
+      [0, 0, 2, 0],
+      [0, 0, 0, 0],
+      [1024, 0, 0, 4],
+    ])
+  }
+
+  // Test FromStr
+
+  #[test]
+  pub fn test_parse_compact() {
+    assert_eq!("2 4 . 8 / . . 2 . / . . . . / 1024 . . 4".parse(), Ok(test_grid()));
+    assert_eq!("2 4 0 8\n0 0 2 0\n0 0 0 0\n1024 0 0 4\n".parse(), Ok(test_grid()));
+    assert_eq!(test_grid().to_compact().parse(), Ok(test_grid()));
+  }
+
+  #[test]
+  pub fn test_parse_bracketed() {
+    assert_eq!("[[2, 4, 0, 8], [0, 0, 2, 0], [0, 0, 0, 0], [1024, 0, 0, 4]]".parse(), Ok(test_grid()));
+    assert_eq!(test_grid().to_string().parse(), Ok(test_grid()));
+  }
+
+  #[test]
+  pub fn test_parse_bitboard() {
+    let bitboard: Grid<BitboardGrid> = test_grid().to_compact().parse().unwrap();
+
+    assert_eq!(bitboard.to_encoded(), test_grid());
+    assert_eq!("65536 . . . / . . . . / . . . . / . . . .".parse::<Grid<BitboardGrid>>(), Err(GridError::TileTooLarge(65536)));
+  }
+
+  #[test]
+  pub fn test_parse_errors() {
+    assert_eq!("2 4 . 8 / . . 2 . / . . . .".parse::<Grid<EncodedGrid>>(), Err(GridError::WrongRowCount(3)));
+    assert_eq!("2 4 . 8 / . . 2 / . . . . / . . . .".parse::<Grid<EncodedGrid>>(), Err(GridError::WrongRowLength { row: 1, length: 3 }));
+    assert_eq!("2 4 . 8 / . . x . / . . . . / . . . .".parse::<Grid<EncodedGrid>>(), Err(GridError::InvalidToken { row: 1, col: 2 }));
+    assert_eq!("2 4 . 8 / . . 6 . / . . . . / . . . .".parse::<Grid<EncodedGrid>>(), Err(GridError::NotPowerOfTwo(6)));
+    assert_eq!("[[1, 0, 0, 0], [0, 0, 0, 0], [0, 0, 0, 0], [0, 0, 0, 0]]".parse::<Grid<EncodedGrid>>(), Err(GridError::TileOfOne));
+  }
+
+
+  // Test rendering
+
+  #[test]
+  pub fn test_render() {
+    let grid: Grid<EncodedGrid> = "2 . . . / . 16 . . / . . . . / . . . 128".parse().unwrap();
+
+    assert_eq!(grid.render(), "\
+┌─────┬─────┬─────┬─────┐
+│   2 │     │     │     │
+├─────┼─────┼─────┼─────┤
+│     │  16 │     │     │
+├─────┼─────┼─────┼─────┤
+│     │     │     │     │
+├─────┼─────┼─────┼─────┤
+│     │     │     │ 128 │
+└─────┴─────┴─────┴─────┘
+");
+  }
+
+  #[test]
+  pub fn test_render_colored() {
+    let grid = test_grid();
+    let colored = grid.render_colored();
+
+    assert!(colored.contains("\x1b[38;5;250m2\x1b[0m"));
+    assert_eq!(strip_ansi(&colored), grid.render());
+  }
+
+  fn strip_ansi(s: &str) -> String {
+    let mut stripped = String::new();
+    let mut escaped = false;
+
+    for c in s.chars() {
+      match c {
+        '\x1b' => escaped = true,
+        'm' if escaped => escaped = false,
+        _ if !escaped => stripped.push(c),
+        _ => {},
+      }
+    }
+
+    stripped
+  }
+
+}
@@ -0,0 +1,227 @@
+//! # `observer` module
+//!
+//! This module defines the hooks notified of the events of a `Game`, registered with `Game::add_observer()` or `AIEngine::add_observer()`.
+//! Every hook has an empty default, so an observer only implements the events it cares about.
+
+use crate::game::core::*;
+use crate::game::engine::GameState;
+use crate::game::moves::{MergeEvent, PlayerMove};
+use crate::game::stats::GameStats;
+
+
+//------------------------------------------------
+// Traits
+//------------------------------------------------
+
+/// Trait for the observers of the events of a game.
+/// For each effective move the hooks are called in the order `on_move`, `on_merge` for each merge, `on_spawn`,
+/// then `on_victory` and `on_game_over` when the move reaches them.
+/// Moving through the history only calls `on_undo` or `on_redo`, once per move, `GameAPI::jump_to()` included.
+/// Observers are registered as `Send` so that the game, and the `AIEngine` playing it, can move across threads.
+pub trait GameObserver {
+
+  /// Called after an effective move, with the state reached.
+  fn on_move(&mut self, _player_move: PlayerMove, _state: &GameState) {}
+
+  /// Called for every merge of a move.
+  fn on_merge(&mut self, _merge: &MergeEvent) {}
+
+  /// Called when a tile spawns after a move, with its grid coordinates.
+  fn on_spawn(&mut self, _tile: EntryType, _position: (usize, usize)) {}
+
+  /// Called on the move first reaching the victory tile.
+  fn on_victory(&mut self, _state: &GameState) {}
+
+  /// Called on the move ending the game, with the final state and statistics.
+  fn on_game_over(&mut self, _state: &GameState, _stats: &GameStats) {}
+
+  /// Called after a move is undone, with the restored state.
+  fn on_undo(&mut self, _state: &GameState) {}
+
+  /// Called after an undone move is redone, with the restored state.
+  fn on_redo(&mut self, _state: &GameState) {}
+
+  /// Called after the game is reset, with the state of the new game.
+  fn on_reset(&mut self, _state: &GameState) {}
+
+}
+
+
+//------------------------------------------------
+// Unit tests
+//------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+
+  use super::*;
+  use std::sync::{Arc, Mutex};
+  use crate::ai::engine::AIEngine;
+  use crate::game::config::GameConfig;
+  use crate::game::engine::{Game, GameAPI, GameStatus};
+
+  #[derive(Clone, PartialEq, Debug)]
+  enum Event {
+    Move(PlayerMove),
+    Merge(EntryType),
+    Spawn(EntryType, (usize, usize)),
+    Victory,
+    GameOver(u32),
+    Undo(u32),
+    Redo(u32),
+    Reset(u32),
+  }
+
+  // Observer logging the events in a shared list
+  struct EventLog {
+    events: Arc<Mutex<Vec<Event>>>,
+  }
+
+  impl GameObserver for EventLog {
+    fn on_move(&mut self, player_move: PlayerMove, _state: &GameState) { self.events.lock().unwrap().push(Event::Move(player_move)); }
+    fn on_merge(&mut self, merge: &MergeEvent) { self.events.lock().unwrap().push(Event::Merge(merge.get_value())); }
+    fn on_spawn(&mut self, tile: EntryType, position: (usize, usize)) { self.events.lock().unwrap().push(Event::Spawn(tile, position)); }
+    fn on_victory(&mut self, _state: &GameState) { self.events.lock().unwrap().push(Event::Victory); }
+    fn on_game_over(&mut self, state: &GameState, _stats: &GameStats) { self.events.lock().unwrap().push(Event::GameOver(state.get_move_count())); }
+    fn on_undo(&mut self, state: &GameState) { self.events.lock().unwrap().push(Event::Undo(state.get_move_count())); }
+    fn on_redo(&mut self, state: &GameState) { self.events.lock().unwrap().push(Event::Redo(state.get_move_count())); }
+    fn on_reset(&mut self, state: &GameState) { self.events.lock().unwrap().push(Event::Reset(state.get_move_count())); }
+  }
+
+  fn observed_game(game: &mut Game) -> Arc<Mutex<Vec<Event>>> {
+    let events = Arc::new(Mutex::new(vec![]));
+    game.add_observer(Box::new(EventLog { events: Arc::clone(&events) }));
+    events
+  }
+
+  // Test the events of moves
+
+  #[test]
+  pub fn test_observer_move_events() {
+    let config = GameConfig::builder().victory_threshold(16).build().unwrap();
+    let mut game = Game::with_config_and_seed(config, 3);
+    let events = observed_game(&mut game);
+
+    use PlayerMove::{Up, Left, Right, Down};
+    let player_move = [Up, Left, Right, Down];
+    let mut count = 0;
+
+    while game.get_state().get_status() != GameStatus::Over {
+      game.process_move(Some(player_move[count % 4]));
+      count += 1;
+    }
+
+    let events = events.lock().unwrap();
+    let move_count = game.get_state().get_move_count() as usize;
+    let merge_count: u32 = (1..TILE_EXPONENTS).map(|exponent| game.get_stats().get_merge_count(1 << exponent)).sum();
+
+    assert_eq!(events.iter().filter(|event| matches!(event, Event::Move(_))).count(), move_count);
+    assert_eq!(events.iter().filter(|event| matches!(event, Event::Spawn(..))).count(), move_count);
+    assert_eq!(events.iter().filter(|event| matches!(event, Event::Merge(_))).count(), merge_count as usize);
+    assert_eq!(events.iter().filter(|event| **event == Event::Victory).count(), 1);
+    assert_eq!(events.last(), Some(&Event::GameOver(move_count as u32)));
+
+    // the spawn follows the move and its merges, and matches the recorded move
+    let record = game.get_record();
+    let spawns: Vec<_> = events.iter().filter_map(|event| match event {
+      Event::Spawn(tile, position) => Some((*tile, *position)),
+      _ => None,
+    }).collect();
+
+    assert_eq!(events[0], Event::Move(record.get_moves()[0].get_player_move()));
+    for (recorded, &(tile, position)) in record.get_moves().iter().zip(spawns.iter()) {
+      assert_eq!((recorded.get_tile(), recorded.get_tile_position()), (tile, position));
+    }
+  }
+
+  #[test]
+  pub fn test_observer_no_events_on_ineffective_move() {
+    let mut game = Game::with_seed(4);
+    let events = observed_game(&mut game);
+
+    use PlayerMove::{Up, Left, Right, Down};
+    let mut count = 0;
+
+    // play until some direction can't change the grid
+    while game.legal_moves().len() == 4 {
+      game.process_move(Some([Up, Left, Right, Down][count % 4]));
+      count += 1;
+    }
+
+    let logged = events.lock().unwrap().len();
+    let illegal = [Up, Left, Right, Down].iter().copied().find(|&m| !game.legal_moves().contains(m));
+
+    game.process_move(illegal);
+    assert_eq!(events.lock().unwrap().len(), logged);
+  }
+
+
+  // Test undo and reset events
+
+  #[test]
+  pub fn test_observer_undo_and_reset() {
+    let mut game = Game::with_seed(5);
+    let events = observed_game(&mut game);
+
+    use PlayerMove::{Up, Left, Right, Down};
+    let player_move = [Up, Left, Right, Down];
+    let mut count = 0;
+
+    while game.get_state().get_move_count() < 2 {
+      game.process_move(Some(player_move[count % 4]));
+      count += 1;
+    }
+
+    game.undo_last_move();
+    assert_eq!(events.lock().unwrap().last(), Some(&Event::Undo(1)));
+
+    // observers survive the reset
+    game.reset();
+    assert_eq!(events.lock().unwrap().last(), Some(&Event::Reset(0)));
+
+    events.lock().unwrap().clear();
+    while game.get_state().get_move_count() < 1 {
+      game.process_move(Some(player_move[count % 4]));
+      count += 1;
+    }
+    assert!(matches!(events.lock().unwrap()[0], Event::Move(_)));
+
+    game.clear_observers();
+    game.undo_last_move();
+    assert!(!matches!(events.lock().unwrap().last(), Some(Event::Undo(_))));
+  }
+
+  #[test]
+  pub fn test_observer_redo_and_jump_to() {
+    let mut game = Game::with_seed(6);
+
+    for _ in 0..4 {
+      let player_move = game.legal_moves().iter().next();
+      game.process_move(player_move);
+    }
+
+    let events = observed_game(&mut game);
+
+    game.undo_last_move();
+    game.redo_move();
+    assert_eq!(*events.lock().unwrap(), vec![Event::Undo(3), Event::Redo(4)]);
+
+    // a jump reports every move it undoes or redoes
+    events.lock().unwrap().clear();
+    assert!(game.jump_to(1));
+    assert!(game.jump_to(3));
+    assert_eq!(*events.lock().unwrap(), vec![Event::Undo(3), Event::Undo(2), Event::Undo(1), Event::Redo(2), Event::Redo(3)]);
+  }
+
+
+  // Test the games can move across threads along with their observers
+
+  #[test]
+  pub fn test_observed_game_is_send() {
+    fn assert_send<T: Send>() {}
+
+    assert_send::<Game>();
+    assert_send::<AIEngine>();
+  }
+
+}
@@ -0,0 +1,424 @@
+//! # `record` module
+//!
+//! This module defines the recording of a `Game` as the list of its moves and spawned tiles.
+//! A `GameRecord` can be replayed step by step to rebuild every intermediate grid and state,
+//! checking the recorded scores and final state along the way.
+
+use std::{error, fmt, fmt::Display};
+
+use crate::game::core::*;
+use crate::game::board::GameBoard;
+use crate::game::config::GameConfig;
+use crate::game::engine::{self, GameState, GameStatus};
+use crate::game::moves::PlayerMove;
+
+
+//------------------------------------------------
+// Types and Definitions
+//------------------------------------------------
+
+/// A single recorded move: the player move, the tile spawned after it at its grid coordinates, and the score reached.
+#[derive(Copy, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RecordedMove {
+  player_move: PlayerMove,
+  tile: EntryType,
+  tile_position: (usize, usize),
+  score: u64,
+}
+
+/// The full record of a game, from the grid with the starting tiles to the final grid and state, along with the rules it was played with.
+#[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GameRecord<B = Grid<EncodedGrid>> {
+  seed: u64,
+  config: GameConfig,
+  initial_grid: B,
+  moves: Vec<RecordedMove>,
+  final_grid: B,
+  final_state: GameState,
+}
+
+/// A replayed step, with the grid and state right after the move and its tile spawn.
+#[derive(Clone, PartialEq, Debug)]
+pub struct ReplayStep<B = Grid<EncodedGrid>> {
+  player_move: PlayerMove,
+  grid: B,
+  state: GameState,
+}
+
+/// Iterator over the steps of a `GameRecord`, stops after the first divergence.
+pub struct Replay<'a, B = Grid<EncodedGrid>> {
+  record: &'a GameRecord<B>,
+  step: usize,
+  grid: B,
+  state: GameState,
+  diverged: bool,
+}
+
+/// The first point where a replay diverges from its record, `step` is the index of the move.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum ReplayDivergence {
+  MoveAfterGameOver { step: usize },
+  IneffectiveMove { step: usize },
+  InvalidTile { step: usize, tile: EntryType },
+  SpawnOutOfBounds { step: usize, tile_position: (usize, usize) },
+  OccupiedSpawn { step: usize, tile_position: (usize, usize) },
+  ScoreMismatch { step: usize, recorded: u64, replayed: u64 },
+  FinalGridMismatch,
+  FinalStateMismatch { recorded: GameState, replayed: GameState },
+}
+
+
+//------------------------------------------------
+// Implementations
+//------------------------------------------------
+
+// Inherent
+
+impl RecordedMove {
+
+  /// Constructor.
+  pub fn new(player_move: PlayerMove, tile: EntryType, tile_position: (usize, usize), score: u64) -> Self {
+    RecordedMove {
+      player_move,
+      tile,
+      tile_position,
+      score,
+    }
+  }
+
+  // Getters
+  pub fn get_player_move(&self) -> PlayerMove { self.player_move }
+  pub fn get_tile(&self) -> EntryType { self.tile }
+  pub fn get_tile_position(&self) -> (usize, usize) { self.tile_position }
+  pub fn get_score(&self) -> u64 { self.score }
+
+}
+
+impl<B: GameBoard> GameRecord<B> {
+
+  /// Constructor.
+  pub fn new(
+    seed: u64,
+    config: &GameConfig,
+    initial_grid: &B,
+    moves: Vec<RecordedMove>,
+    final_grid: &B,
+    final_state: &GameState,
+  ) -> Self {
+    GameRecord {
+      seed,
+      config: *config,
+      initial_grid: initial_grid.clone(),
+      moves,
+      final_grid: final_grid.clone(),
+      final_state: *final_state,
+    }
+  }
+
+  // Getters
+  pub fn get_seed(&self) -> u64 { self.seed }
+  pub fn get_config(&self) -> &GameConfig { &self.config }
+  pub fn get_initial_grid(&self) -> &B { &self.initial_grid }
+  pub fn get_moves(&self) -> &[RecordedMove] { &self.moves }
+  pub fn get_final_grid(&self) -> &B { &self.final_grid }
+  pub fn get_final_state(&self) -> &GameState { &self.final_state }
+
+  /// Returns an iterator replaying the recorded moves one at a time.
+  pub fn replay(&self) -> Replay<'_, B> {
+    Replay {
+      record: self,
+      step: 0,
+      grid: self.initial_grid.clone(),
+      state: GameState::new(),
+      diverged: false,
+    }
+  }
+
+  /// Replays the whole record and checks that it ends in the recorded grid and state, which is returned.
+  pub fn verify(&self) -> Result<GameState, ReplayDivergence> {
+    let mut grid = self.initial_grid.clone();
+    let mut state = GameState::new();
+
+    for step in self.replay() {
+      let step = step?;
+      grid = step.grid;
+      state = step.state;
+    }
+
+    if grid != self.final_grid {
+      return Err(ReplayDivergence::FinalGridMismatch);
+    }
+
+    if state != self.final_state {
+      return Err(ReplayDivergence::FinalStateMismatch { recorded: self.final_state, replayed: state });
+    }
+
+    Ok(state)
+  }
+
+}
+
+impl<B> ReplayStep<B> {
+
+  // Getters
+  pub fn get_player_move(&self) -> PlayerMove { self.player_move }
+  pub fn get_grid(&self) -> &B { &self.grid }
+  pub fn get_state(&self) -> &GameState { &self.state }
+
+}
+
+impl<B: GameBoard> Replay<'_, B> {
+
+  /// Applies the recorded move `step` to the current grid and state, following the same rules as `Game::process_move()`.
+  fn apply(&mut self, step: usize, recorded: &RecordedMove) -> Result<ReplayStep<B>, ReplayDivergence> {
+    if let GameStatus::Over = self.state.get_status() {
+      return Err(ReplayDivergence::MoveAfterGameOver { step });
+    }
+
+    let move_result = self.grid.stack(recorded.player_move);
+
+    if !move_result.is_effective() {
+      return Err(ReplayDivergence::IneffectiveMove { step });
+    }
+
+    if recorded.tile != 2 && recorded.tile != 4 {
+      return Err(ReplayDivergence::InvalidTile { step, tile: recorded.tile });
+    }
+
+    let (i, j) = recorded.tile_position;
+    let mut grid = move_result.get_new_board().clone();
+
+    if i >= grid.get_rows() || j >= grid.get_cols() {
+      return Err(ReplayDivergence::SpawnOutOfBounds { step, tile_position: recorded.tile_position });
+    }
+
+    if grid.get_tile(i, j) != 0 {
+      return Err(ReplayDivergence::OccupiedSpawn { step, tile_position: recorded.tile_position });
+    }
+
+    grid.set_tile(i, j, recorded.tile);
+    engine::update_game_state(&mut self.state, &grid, move_result.get_delta_score(), self.record.config.get_victory_threshold());
+    self.grid = grid;
+
+    if self.state.get_score() != recorded.score {
+      return Err(ReplayDivergence::ScoreMismatch { step, recorded: recorded.score, replayed: self.state.get_score() });
+    }
+
+    Ok(ReplayStep {
+      player_move: recorded.player_move,
+      grid: self.grid.clone(),
+      state: self.state,
+    })
+  }
+
+}
+
+
+// Iterator
+
+impl<B: GameBoard> Iterator for Replay<'_, B> {
+  type Item = Result<ReplayStep<B>, ReplayDivergence>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    if self.diverged {
+      return None;
+    }
+
+    let recorded = *self.record.moves.get(self.step)?;
+    let result = self.apply(self.step, &recorded);
+
+    self.diverged = result.is_err();
+    self.step += 1;
+
+    Some(result)
+  }
+}
+
+
+// Display
+
+impl Display for ReplayDivergence {
+
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      ReplayDivergence::MoveAfterGameOver { step } => write!(f, "move {} recorded after the game was over", step),
+      ReplayDivergence::IneffectiveMove { step } => write!(f, "move {} does not change the grid", step),
+      ReplayDivergence::InvalidTile { step, tile } => write!(f, "move {} spawns an invalid tile {}", step, tile),
+      ReplayDivergence::SpawnOutOfBounds { step, tile_position } => write!(f, "move {} spawns a tile out of the grid at {:?}", step, tile_position),
+      ReplayDivergence::OccupiedSpawn { step, tile_position } => write!(f, "move {} spawns a tile on non-empty cell {:?}", step, tile_position),
+      ReplayDivergence::ScoreMismatch { step, recorded, replayed } => write!(f, "move {} recorded score {} but replayed {}", step, recorded, replayed),
+      ReplayDivergence::FinalGridMismatch => write!(f, "replayed grid differs from the recorded final grid"),
+      ReplayDivergence::FinalStateMismatch { recorded, replayed } => write!(f, "replayed state {:?} differs from the recorded final state {:?}", replayed, recorded),
+    }
+  }
+
+}
+
+
+// Error
+
+impl error::Error for ReplayDivergence {}
+
+
+//------------------------------------------------
+// Unit tests
+//------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+
+  use super::*;
+  use crate::game::engine::{Game, GameAPI};
+  use crate::game::moves;
+
+  const MOVES: [PlayerMove; 4] = [PlayerMove::Left, PlayerMove::Up, PlayerMove::Right, PlayerMove::Down];
+
+  fn play(game: &mut Game, count: usize) {
+    for i in 0..count {
+      game.process_move(Some(MOVES[i % MOVES.len()]));
+    }
+  }
+
+  // Test recording
+
+  #[test]
+  pub fn test_record_spawn_positions() {
+    let mut game = Game::with_seed(21);
+
+    for i in 0..40 {
+      let prev_grid = *game.get_grid();
+      let animation = game.process_move(Some(MOVES[i % MOVES.len()]));
+
+      if animation.is_some() {
+        let recorded = *game.get_record().get_moves().last().unwrap();
+        let (row, col) = recorded.get_tile_position();
+        let stacked = *moves::process_grid_stacking(recorded.get_player_move(), &prev_grid, moves::precomputed_moves()).get_new_grid();
+
+        assert_eq!(stacked.get_tile(row, col), 0);
+        assert_eq!(game.get_grid().get_tile(row, col), recorded.get_tile());
+        assert_eq!(recorded.get_score(), game.get_state().get_score());
+      }
+    }
+  }
+
+  #[cfg(feature = "serde")]
+  #[test]
+  pub fn test_record_serde_round_trip() {
+    let mut game = Game::with_seed(23);
+    play(&mut game, 30);
+
+    let record = game.get_record();
+    let json = serde_json::to_string(&record).unwrap();
+    let restored: GameRecord = serde_json::from_str(&json).unwrap();
+
+    assert_eq!(restored, record);
+    assert_eq!(restored.replay().last().unwrap().unwrap().get_grid(), game.get_grid());
+  }
+
+  #[test]
+  pub fn test_record_follows_undo() {
+    let mut game = Game::with_seed(22);
+    play(&mut game, 10);
+
+    let count = game.get_record().get_moves().len();
+    game.undo_last_move();
+
+    assert_eq!(game.get_record().get_moves().len(), count - 1);
+    assert_eq!(game.get_record().verify(), Ok(*game.get_state()));
+  }
+
+
+  // Test replay
+
+  #[test]
+  pub fn test_replay_rebuilds_every_step() {
+    let mut game = Game::with_seed(23);
+    let mut grids = vec![];
+
+    for i in 0..50 {
+      if game.process_move(Some(MOVES[i % MOVES.len()])).is_some() {
+        grids.push((*game.get_grid(), *game.get_state()));
+      }
+    }
+
+    let record = game.get_record();
+    let replayed: Vec<(Grid<EncodedGrid>, GameState)> = record.replay()
+      .map(|step| step.map(|step| (*step.get_grid(), *step.get_state())))
+      .collect::<Result<_, _>>()
+      .unwrap();
+
+    assert_eq!(replayed, grids);
+    assert_eq!(record.verify(), Ok(*game.get_state()));
+  }
+
+  #[test]
+  pub fn test_replay_until_game_over() {
+    let mut game = Game::with_seed(24);
+
+    while game.get_state().get_status() != GameStatus::Over {
+      play(&mut game, 4);
+    }
+
+    assert_eq!(game.get_record().verify().unwrap().get_status(), GameStatus::Over);
+  }
+
+  #[test]
+  pub fn test_replay_score_divergence() {
+    let mut game = Game::with_seed(25);
+    play(&mut game, 20);
+
+    let mut record = game.get_record();
+    let step = record.moves.iter().position(|recorded| recorded.score > 0).unwrap();
+    record.moves[step].score += 2;
+
+    assert_eq!(
+      record.verify(),
+      Err(ReplayDivergence::ScoreMismatch { step, recorded: record.moves[step].score, replayed: record.moves[step].score - 2 })
+    );
+    assert_eq!(record.replay().count(), step + 1);
+  }
+
+  #[test]
+  pub fn test_replay_spawn_divergence() {
+    let mut game = Game::with_seed(26);
+    play(&mut game, 5);
+
+    let mut record = game.get_record();
+    record.moves[1].tile = 8;
+
+    assert_eq!(record.verify(), Err(ReplayDivergence::InvalidTile { step: 1, tile: 8 }));
+
+    let mut record = game.get_record();
+    let (i, j) = record.moves[0].tile_position;
+    record.moves[0].tile_position = (i + GRID_SIDE, j);
+
+    assert_eq!(record.verify(), Err(ReplayDivergence::SpawnOutOfBounds { step: 0, tile_position: (i + GRID_SIDE, j) }));
+
+    // spawn on a cell the first move leaves occupied
+    let mut record = game.get_record();
+    let stacked = *moves::process_grid_stacking(record.moves[0].player_move, record.get_initial_grid(), moves::precomputed_moves()).get_new_grid();
+    let (i, j, _) = stacked.tiles().find(|&(_, _, tile)| tile != 0).unwrap();
+    record.moves[0].tile_position = (i, j);
+
+    assert_eq!(record.verify(), Err(ReplayDivergence::OccupiedSpawn { step: 0, tile_position: (i, j) }));
+  }
+
+  #[test]
+  pub fn test_replay_final_state_divergence() {
+    let mut game = Game::with_seed(27);
+    play(&mut game, 5);
+
+    let mut record = game.get_record();
+    record.final_grid = *record.get_initial_grid();
+
+    assert_eq!(record.verify(), Err(ReplayDivergence::FinalGridMismatch));
+
+    let mut record = game.get_record();
+    record.moves.pop();
+
+    assert!(record.verify().is_err());
+  }
+
+}
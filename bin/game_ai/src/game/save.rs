@@ -0,0 +1,396 @@
+//! # `save` module
+//!
+//! This module defines the versioned save format of a `Game`, available with the `serde` feature.
+//! A save holds everything needed to resume a game exactly where it stopped, random tile generator included,
+//! and can be written either as JSON or in a compact binary form.
+
+use std::{error, fmt, fmt::Display};
+
+use serde::{Serialize, Deserialize};
+use serde::de::DeserializeOwned;
+
+use crate::game::core::*;
+use crate::game::board::GameBoard;
+use crate::game::config::GameConfig;
+use crate::game::engine::GameState;
+use crate::game::record::RecordedMove;
+use crate::game::stats::GameStats;
+
+
+//------------------------------------------------
+// Types and Definitions
+//------------------------------------------------
+
+/// Version of the save format written by this build, the only one read back, bumped at every incompatible change:
+/// 2 added the record, redo history, rules and statistics of the game, and widened the scores to 64 bits.
+pub const SAVE_FORMAT_VERSION: u32 = 2;
+
+/// Snapshot of a game, obtained with `BoardGame::save()` and turned back into a game with `BoardGame::load()`.
+/// The random tile generator is stored as its seed and the position of its stream, the game record as its initial grid and moves.
+/// The rules and statistics of the game are saved with it. Grids are saved as their encoded rows, boards along with their size.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct SavedGame<B = Grid<EncodedGrid>> {
+  pub(super) version: u32,
+  pub(super) grid: B,
+  pub(super) state: GameState,
+  pub(super) history: Vec<SavedHistoryItem<B>>,
+  pub(super) redo_history: Vec<SavedRedoItem<B>>,
+  pub(super) initial_grid: B,
+  pub(super) moves: Vec<RecordedMove>,
+  pub(super) stats: GameStats,
+  pub(super) config: GameConfig,
+  pub(super) seed: u64,
+  pub(super) word_pos: u128,
+}
+
+/// A saved undo history entry, most recent first as in the `Game` history.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct SavedHistoryItem<B> {
+  pub(super) grid: B,
+  pub(super) state: GameState,
+}
+
+/// A saved undone state, last to be redone first as in the `Game` redo history.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct SavedRedoItem<B> {
+  pub(super) grid: B,
+  pub(super) state: GameState,
+  pub(super) recorded_move: Option<RecordedMove>,
+}
+
+/// Leading field of every save, read before the rest to reject saves of other versions.
+#[derive(Deserialize)]
+struct SaveHeader {
+  version: u32,
+}
+
+/// Errors raised when writing or reading a save.
+#[derive(Debug)]
+pub enum SaveError {
+  UnsupportedVersion(u32),
+  InvalidData(&'static str),
+  Json(serde_json::Error),
+  Binary(bincode::Error),
+}
+
+
+//------------------------------------------------
+// Implementations
+//------------------------------------------------
+
+// Inherent
+
+impl<B: GameBoard> SavedGame<B> {
+
+  // Getters
+  pub fn get_version(&self) -> u32 { self.version }
+  pub fn get_seed(&self) -> u64 { self.seed }
+
+  /// Checks that the save describes a game that can be resumed.
+  pub(super) fn validate(&self) -> Result<(), SaveError> {
+    check_version(self.version)?;
+
+    if self.config.validate().is_err() {
+      return Err(SaveError::InvalidData("invalid game rules"));
+    }
+
+    if self.history.len() + self.redo_history.len() > self.config.get_history_length() {
+      return Err(SaveError::InvalidData("history longer than the configured undo depth"));
+    }
+
+    let (rows, cols) = (self.config.get_rows(), self.config.get_cols());
+
+    if !self.grid.fits(rows, cols)
+      || !self.initial_grid.fits(rows, cols)
+      || self.history.iter().any(|item| !item.grid.fits(rows, cols))
+      || self.redo_history.iter().any(|item| !item.grid.fits(rows, cols)) {
+      return Err(SaveError::InvalidData("grid of another size or row out of the encoding range"));
+    }
+
+    Ok(())
+  }
+
+}
+
+impl<B: GameBoard + Serialize + DeserializeOwned> SavedGame<B> {
+
+  /// Serializes the save to JSON.
+  pub fn to_json(&self) -> Result<String, SaveError> {
+    Ok(serde_json::to_string(self)?)
+  }
+
+  /// Deserializes a save from JSON, checking its version and content.
+  pub fn from_json(json: &str) -> Result<Self, SaveError> {
+    let header: SaveHeader = serde_json::from_str(json)?;
+    check_version(header.version)?;
+
+    let saved: SavedGame<B> = serde_json::from_str(json)?;
+    saved.validate()?;
+
+    Ok(saved)
+  }
+
+  /// Serializes the save to its compact binary form.
+  pub fn to_bytes(&self) -> Result<Vec<u8>, SaveError> {
+    Ok(bincode::serialize(self)?)
+  }
+
+  /// Deserializes a save from its compact binary form, checking its version and content.
+  pub fn from_bytes(bytes: &[u8]) -> Result<Self, SaveError> {
+    let header: SaveHeader = bincode::deserialize(bytes)?;
+    check_version(header.version)?;
+
+    let saved: SavedGame<B> = bincode::deserialize(bytes)?;
+    saved.validate()?;
+
+    Ok(saved)
+  }
+
+}
+
+
+// Display
+
+impl Display for SaveError {
+
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      SaveError::UnsupportedVersion(version) => write!(f, "unsupported save version {}, expected {}", version, SAVE_FORMAT_VERSION),
+      SaveError::InvalidData(reason) => write!(f, "invalid save data: {}", reason),
+      SaveError::Json(err) => write!(f, "JSON save error: {}", err),
+      SaveError::Binary(err) => write!(f, "binary save error: {}", err),
+    }
+  }
+
+}
+
+
+// Error
+
+impl error::Error for SaveError {
+
+  fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+    match self {
+      SaveError::Json(err) => Some(err),
+      SaveError::Binary(err) => Some(err),
+      _ => None,
+    }
+  }
+
+}
+
+
+// From
+
+impl From<serde_json::Error> for SaveError {
+
+  fn from(err: serde_json::Error) -> Self {
+    SaveError::Json(err)
+  }
+
+}
+
+impl From<bincode::Error> for SaveError {
+
+  fn from(err: bincode::Error) -> Self {
+    SaveError::Binary(err)
+  }
+
+}
+
+
+//------------------------------------------------
+// Functions
+//------------------------------------------------
+
+fn check_version(version: u32) -> Result<(), SaveError> {
+  if version != SAVE_FORMAT_VERSION {
+    return Err(SaveError::UnsupportedVersion(version));
+  }
+
+  Ok(())
+}
+
+
+//------------------------------------------------
+// Unit tests
+//------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+
+  use super::*;
+  use crate::game::board::Board;
+  use crate::game::engine::{BoardGame, Game, GameAPI};
+  use crate::game::moves::PlayerMove;
+
+  const MOVES: [PlayerMove; 4] = [PlayerMove::Left, PlayerMove::Up, PlayerMove::Right, PlayerMove::Down];
+
+  fn play(game: &mut Game, count: usize) {
+    for i in 0..count {
+      game.process_move(Some(MOVES[i % MOVES.len()]));
+    }
+  }
+
+  fn assert_same_continuation(mut game: Game, mut loaded: Game) {
+    assert_eq!(game.get_grid(), loaded.get_grid());
+    assert_eq!(game.get_state(), loaded.get_state());
+
+    play(&mut game, 30);
+    play(&mut loaded, 30);
+
+    assert_eq!(game.get_grid(), loaded.get_grid());
+    assert_eq!(game.get_state(), loaded.get_state());
+
+    game.undo_last_move();
+    loaded.undo_last_move();
+
+    assert_eq!(game.get_grid(), loaded.get_grid());
+    assert_eq!(game.get_state(), loaded.get_state());
+    assert_eq!(game.get_record(), loaded.get_record());
+    assert_eq!(game.get_stats(), loaded.get_stats());
+  }
+
+  // Test JSON round trip
+
+  #[test]
+  pub fn test_json_round_trip() {
+    let mut game = Game::with_seed(11);
+    play(&mut game, 25);
+
+    let json = game.save().to_json().unwrap();
+    let loaded = Game::load(&SavedGame::from_json(&json).unwrap()).unwrap();
+
+    assert_same_continuation(game, loaded);
+  }
+
+
+  // Test binary round trip
+
+  #[test]
+  pub fn test_binary_round_trip() {
+    let mut game = Game::with_seed(12);
+    play(&mut game, 25);
+
+    let bytes = game.save().to_bytes().unwrap();
+    let loaded = Game::load(&SavedGame::from_bytes(&bytes).unwrap()).unwrap();
+
+    assert_eq!(loaded.get_seed(), 12);
+    assert_same_continuation(game, loaded);
+  }
+
+  #[test]
+  pub fn test_binary_round_trip_after_reset() {
+    let mut game = Game::with_seed(13);
+    play(&mut game, 10);
+    game.reset();
+    play(&mut game, 5);
+
+    let bytes = game.save().to_bytes().unwrap();
+    let loaded = Game::load(&SavedGame::from_bytes(&bytes).unwrap()).unwrap();
+
+    assert_same_continuation(game, loaded);
+  }
+
+
+  #[test]
+  pub fn test_round_trip_keeps_redo_history() {
+    let mut game = Game::with_seed(16);
+    play(&mut game, 12);
+    game.undo_last_move();
+    game.undo_last_move();
+
+    let mut loaded = Game::load(&SavedGame::from_json(&game.save().to_json().unwrap()).unwrap()).unwrap();
+
+    assert_eq!(loaded.history_len(), game.history_len());
+
+    game.redo_move();
+    loaded.redo_move();
+
+    assert_same_continuation(game, loaded);
+  }
+
+
+  #[test]
+  pub fn test_round_trip_keeps_config() {
+    let config = GameConfig::builder().victory_threshold(64).history_length(3).starting_tiles(2).build().unwrap();
+    let mut game = Game::with_config_and_seed(config, 17);
+    play(&mut game, 10);
+
+    let loaded = Game::load(&SavedGame::from_bytes(&game.save().to_bytes().unwrap()).unwrap()).unwrap();
+
+    assert_eq!(*loaded.get_config(), config);
+    assert_same_continuation(game, loaded);
+  }
+
+
+  #[test]
+  pub fn test_round_trip_board_game() {
+    let config = GameConfig::builder().board_size(3, 5).starting_tiles(2).build().unwrap();
+    let mut game = BoardGame::<Board>::with_config_and_seed(config, 18);
+    for i in 0..20 {
+      game.process_move(Some(MOVES[i % MOVES.len()]));
+    }
+
+    let json = game.save().to_json().unwrap();
+    let mut loaded = BoardGame::load(&SavedGame::<Board>::from_json(&json).unwrap()).unwrap();
+
+    assert_eq!(game.get_grid(), loaded.get_grid());
+    assert_eq!(game.get_state(), loaded.get_state());
+
+    game.process_move(Some(PlayerMove::Left));
+    loaded.process_move(Some(PlayerMove::Left));
+
+    assert_eq!(game.get_grid(), loaded.get_grid());
+    assert_eq!(game.get_record(), loaded.get_record());
+
+    // a board of another size than the one of the rules is rejected
+    let mut saved = game.save();
+    saved.config = GameConfig::builder().board_size(5, 3).starting_tiles(2).build().unwrap();
+
+    assert!(matches!(SavedGame::<Board>::from_bytes(&saved.to_bytes().unwrap()), Err(SaveError::InvalidData(_))));
+  }
+
+
+  // Test validation
+
+  #[test]
+  pub fn test_unsupported_version() {
+    let mut saved = Game::with_seed(14).save();
+    saved.version = SAVE_FORMAT_VERSION + 1;
+
+    let json = saved.to_json().unwrap();
+    let bytes = saved.to_bytes().unwrap();
+
+    assert!(matches!(<SavedGame>::from_json(&json), Err(SaveError::UnsupportedVersion(v)) if v == SAVE_FORMAT_VERSION + 1));
+    assert!(matches!(<SavedGame>::from_bytes(&bytes), Err(SaveError::UnsupportedVersion(_))));
+    assert!(matches!(Game::load(&saved), Err(SaveError::UnsupportedVersion(_))));
+  }
+
+  #[test]
+  pub fn test_invalid_data() {
+    let mut saved = Game::with_seed(15).save();
+    saved.grid[2] = 1 << (ENCODING_BITS * GRID_SIDE);
+
+    assert!(matches!(<SavedGame>::from_json(&saved.to_json().unwrap()), Err(SaveError::InvalidData(_))));
+
+    let mut saved = Game::with_seed(15).save();
+    saved.history = vec![SavedHistoryItem { grid: saved.grid, state: saved.state }; HISTORY_LENGTH + 1];
+
+    assert!(matches!(Game::load(&saved), Err(SaveError::InvalidData(_))));
+
+    let mut saved = Game::with_seed(15).save();
+    saved.config = GameConfig::builder().history_length(0).build().unwrap();
+    saved.history = vec![SavedHistoryItem { grid: saved.grid, state: saved.state }];
+
+    assert!(matches!(Game::load(&saved), Err(SaveError::InvalidData(_))));
+  }
+
+  #[test]
+  pub fn test_malformed_input() {
+    assert!(matches!(<SavedGame>::from_json(&format!("{{\"version\": {}}}", SAVE_FORMAT_VERSION)), Err(SaveError::Json(_))));
+    assert!(matches!(<SavedGame>::from_json("not json"), Err(SaveError::Json(_))));
+    assert!(matches!(<SavedGame>::from_bytes(&SAVE_FORMAT_VERSION.to_le_bytes()), Err(SaveError::Binary(_))));
+  }
+
+}
@@ -0,0 +1,277 @@
+//! # `stats` module
+//!
+//! This module defines the statistics collected along a `Game`, used to compare how different players, or AI versions, play.
+//! The statistics follow the moves leading to the current state: undoing a move takes it back out of them, redoing it counts it again.
+//! Only the ineffective move attempts, which never enter the history, stay counted.
+
+use crate::game::core::*;
+use crate::game::board::BoardView;
+use crate::game::moves::{MergeEvent, PlayerMove};
+
+
+//------------------------------------------------
+// Types and Definitions
+//------------------------------------------------
+
+/// Statistics of a game, maintained by `Game::process_move()` and queryable at any time with `Game::get_stats()`.
+#[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GameStats {
+  first_reached: [Option<u32>; TILE_EXPONENTS],
+  move_counts: [u32; 4],
+  spawned_2s: u32,
+  spawned_4s: u32,
+  merges: [u32; TILE_EXPONENTS],
+  ineffective_moves: u32,
+  score_timeline: Vec<u64>,
+}
+
+
+//------------------------------------------------
+// Implementations
+//------------------------------------------------
+
+// Inherent
+
+impl GameStats {
+
+  /// Constructor of empty statistics.
+  pub fn new() -> Self {
+    GameStats {
+      first_reached: [None; TILE_EXPONENTS],
+      move_counts: [0; 4],
+      spawned_2s: 0,
+      spawned_4s: 0,
+      merges: [0; TILE_EXPONENTS],
+      ineffective_moves: 0,
+      score_timeline: vec![0],
+    }
+  }
+
+  /// Constructor from the starting grid, whose tiles count as spawned and reached at move 0.
+  pub(crate) fn from_initial_grid<B: BoardView>(grid: &B) -> Self {
+    let mut stats = GameStats::new();
+
+    for tile in board_tiles(grid) {
+      stats.record_spawn(tile);
+    }
+    stats.record_reached(grid, 0);
+
+    stats
+  }
+
+  /// Returns the move number at which `tile` first appeared on the grid, `None` if it never did.
+  pub fn get_first_reached(&self, tile: EntryType) -> Option<u32> {
+    tile_index(tile).and_then(|index| self.first_reached[index])
+  }
+
+  /// Returns how many effective moves were played in the given direction.
+  pub fn get_move_count(&self, player_move: PlayerMove) -> u32 {
+    self.move_counts[move_index(player_move)]
+  }
+
+  /// Returns how many merges produced `tile`.
+  pub fn get_merge_count(&self, tile: EntryType) -> u32 {
+    tile_index(tile).map_or(0, |index| self.merges[index])
+  }
+
+  // Getters
+  pub fn get_spawned_2s(&self) -> u32 { self.spawned_2s }
+  pub fn get_spawned_4s(&self) -> u32 { self.spawned_4s }
+  pub fn get_ineffective_moves(&self) -> u32 { self.ineffective_moves }
+
+  /// Returns the score after each move, starting from the score of 0 of the starting grid, so the score after move `i` is at index `i`.
+  pub fn get_score_timeline(&self) -> &[u64] { &self.score_timeline }
+
+  /// Updates the statistics with an effective move, its merges, the tile spawned after it and the grid and state reached.
+  pub(crate) fn record_move<B: BoardView>(&mut self, player_move: PlayerMove, merges: &[MergeEvent], tile: EntryType, grid: &B, move_count: u32, score: u64) {
+    self.move_counts[move_index(player_move)] += 1;
+
+    for merge in merges {
+      if let Some(index) = tile_index(merge.get_value()) {
+        self.merges[index] += 1;
+      }
+    }
+
+    self.record_spawn(tile);
+    self.record_reached(grid, move_count);
+    self.score_timeline.push(score);
+  }
+
+  /// Takes back an effective move recorded by `record_move()` with the same move, merges and spawned tile, reached at `move_count`.
+  /// Moves must be taken back from the last one, so the tiles first reached at `move_count` are exactly the ones the move reached.
+  pub(crate) fn undo_move(&mut self, player_move: PlayerMove, merges: &[MergeEvent], tile: EntryType, move_count: u32) {
+    self.move_counts[move_index(player_move)] -= 1;
+
+    for merge in merges {
+      if let Some(index) = tile_index(merge.get_value()) {
+        self.merges[index] -= 1;
+      }
+    }
+
+    match tile {
+      2 => self.spawned_2s -= 1,
+      4 => self.spawned_4s -= 1,
+      _ => {},
+    }
+
+    for reached in self.first_reached.iter_mut() {
+      if *reached == Some(move_count) {
+        *reached = None;
+      }
+    }
+
+    self.score_timeline.pop();
+  }
+
+  /// Counts a move attempt that didn't change the grid.
+  pub(crate) fn record_ineffective_move(&mut self) {
+    self.ineffective_moves += 1;
+  }
+
+  fn record_spawn(&mut self, tile: EntryType) {
+    match tile {
+      2 => self.spawned_2s += 1,
+      4 => self.spawned_4s += 1,
+      _ => {},
+    }
+  }
+
+  fn record_reached<B: BoardView>(&mut self, grid: &B, move_count: u32) {
+    for tile in board_tiles(grid) {
+      if let Some(index) = tile_index(tile) {
+        self.first_reached[index].get_or_insert(move_count);
+      }
+    }
+  }
+
+}
+
+
+// Default
+
+impl Default for GameStats {
+
+  fn default() -> Self {
+    GameStats::new()
+  }
+
+}
+
+
+//------------------------------------------------
+// Functions
+//------------------------------------------------
+
+/// Index of a tile in the statistics per tile, `None` for empty cells and values that aren't tiles.
+fn tile_index(tile: EntryType) -> Option<usize> {
+  if tile > 1 && tile.is_power_of_two() {
+    Some(tile.trailing_zeros() as usize)
+  } else {
+    None
+  }
+}
+
+/// Returns an iterator over the tiles of the board in reading order, empty cells included as 0.
+fn board_tiles<B: BoardView>(grid: &B) -> impl Iterator<Item = EntryType> + '_ {
+  (0..grid.get_rows()).flat_map(move |i| (0..grid.get_cols()).map(move |j| grid.get_tile(i, j)))
+}
+
+fn move_index(player_move: PlayerMove) -> usize {
+  match player_move {
+    PlayerMove::Up => 0,
+    PlayerMove::Left => 1,
+    PlayerMove::Right => 2,
+    PlayerMove::Down => 3,
+  }
+}
+
+
+//------------------------------------------------
+// Unit tests
+//------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+
+  use super::*;
+  use crate::game::moves::{self, precomputed_moves};
+
+  // Test GameStats::from_initial_grid()
+
+  #[test]
+  pub fn test_initial_grid_stats() {
+    let grid = Grid::from_decoded(&[
+      [2, 0, 0, 0],
+      [0, 0, 4, 0],
+      [0, 0, 0, 0],
+      [0, 0, 0, 2],
+    ]);
+
+    let stats = GameStats::from_initial_grid(&grid);
+
+    assert_eq!(stats.get_spawned_2s(), 2);
+    assert_eq!(stats.get_spawned_4s(), 1);
+    assert_eq!(stats.get_first_reached(2), Some(0));
+    assert_eq!(stats.get_first_reached(4), Some(0));
+    assert_eq!(stats.get_first_reached(8), None);
+    assert_eq!(stats.get_score_timeline(), &[0]);
+  }
+
+
+  // Test GameStats::record_move()
+
+  #[test]
+  pub fn test_record_move() {
+    let grid = Grid::from_decoded(&[
+      [2, 2, 4, 4],
+      [0, 0, 4, 0],
+      [0, 0, 0, 0],
+      [8, 8, 0, 2],
+    ]);
+    let mut stats = GameStats::from_initial_grid(&grid);
+
+    let move_result = moves::process_grid_stacking(PlayerMove::Left, &grid, precomputed_moves());
+    let mut new_grid = *move_result.get_new_grid();
+    new_grid.set_tile(2, 2, 4);
+
+    stats.record_move(PlayerMove::Left, move_result.get_merges(), 4, &new_grid, 1, move_result.get_delta_score());
+    stats.record_ineffective_move();
+
+    assert_eq!(stats.get_move_count(PlayerMove::Left), 1);
+    assert_eq!(stats.get_move_count(PlayerMove::Up), 0);
+    assert_eq!(stats.get_merge_count(4), 1);
+    assert_eq!(stats.get_merge_count(8), 1);
+    assert_eq!(stats.get_merge_count(16), 1);
+    assert_eq!(stats.get_spawned_4s(), 4);
+    assert_eq!(stats.get_first_reached(8), Some(0));
+    assert_eq!(stats.get_first_reached(16), Some(1));
+    assert_eq!(stats.get_ineffective_moves(), 1);
+    assert_eq!(stats.get_score_timeline(), &[0, 28]);
+  }
+
+
+  // Test GameStats::undo_move()
+
+  #[test]
+  pub fn test_undo_move() {
+    let grid = Grid::from_decoded(&[
+      [2, 2, 4, 4],
+      [0, 0, 4, 0],
+      [0, 0, 0, 0],
+      [8, 8, 0, 2],
+    ]);
+    let initial = GameStats::from_initial_grid(&grid);
+    let mut stats = initial.clone();
+
+    let move_result = moves::process_grid_stacking(PlayerMove::Left, &grid, precomputed_moves());
+    let mut new_grid = *move_result.get_new_grid();
+    new_grid.set_tile(2, 2, 2);
+
+    stats.record_move(PlayerMove::Left, move_result.get_merges(), 2, &new_grid, 1, move_result.get_delta_score());
+    stats.undo_move(PlayerMove::Left, move_result.get_merges(), 2, 1);
+
+    assert_eq!(stats, initial);
+  }
+
+}
@@ -0,0 +1,391 @@
+//! # `symmetry` module
+//!
+//! This module defines the eight symmetries of the square grid (rotations and reflections) and the canonical form of a grid.
+//! Every symmetry is built from the `Transpose` and `Reverse` primitives, so it's available on all the tile grid representations.
+//! Destination grids also need the move they belong to, since their displacements are along the axis of the move.
+
+use crate::game::core::*;
+use crate::game::moves::PlayerMove;
+
+
+//------------------------------------------------
+// Types and Definitions
+//------------------------------------------------
+
+/// The symmetries of the square grid. Rotations are clockwise, reflections are named after the line they mirror around.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum Symmetry {
+  Identity,
+  Rotate90,
+  Rotate180,
+  Rotate270,
+  ReflectVertical,
+  ReflectHorizontal,
+  ReflectDiagonal,
+  ReflectAntiDiagonal,
+}
+
+
+//------------------------------------------------
+// Traits
+//------------------------------------------------
+
+/// Trait for grids that can be transformed by any `Symmetry`.
+pub trait Symmetric: Transpose + Reverse {
+
+  /// Applies the symmetry to the grid in place and returns a mutable reference of itself for chaining.
+  fn apply_symmetry(&mut self, symmetry: Symmetry) -> &mut Self {
+    move_cells(self, symmetry)
+  }
+
+}
+
+
+//------------------------------------------------
+// Implementations
+//------------------------------------------------
+
+// Inherent
+
+impl Symmetry {
+
+  /// All the symmetries, `Identity` first.
+  pub const ALL: [Symmetry; 8] = [
+    Symmetry::Identity,
+    Symmetry::Rotate90,
+    Symmetry::Rotate180,
+    Symmetry::Rotate270,
+    Symmetry::ReflectVertical,
+    Symmetry::ReflectHorizontal,
+    Symmetry::ReflectDiagonal,
+    Symmetry::ReflectAntiDiagonal,
+  ];
+
+  /// Returns the symmetry undoing this one.
+  pub fn inverse(self) -> Self {
+    match self {
+      Symmetry::Rotate90 => Symmetry::Rotate270,
+      Symmetry::Rotate270 => Symmetry::Rotate90,
+      other => other,
+    }
+  }
+
+  /// Maps a move on a grid to the equivalent move on the transformed grid,
+  /// so that moving and then transforming gives the same grid as transforming and then moving.
+  pub fn map_move(self, player_move: PlayerMove) -> PlayerMove {
+
+    // direction of the move as (row, column) displacement
+    let direction = match player_move {
+      PlayerMove::Up => (-1, 0),
+      PlayerMove::Left => (0, -1),
+      PlayerMove::Right => (0, 1),
+      PlayerMove::Down => (1, 0),
+    };
+
+    match self.map_direction(direction) {
+      (-1, 0) => PlayerMove::Up,
+      (0, -1) => PlayerMove::Left,
+      (0, 1) => PlayerMove::Right,
+      _ => PlayerMove::Down,
+    }
+  }
+
+  /// Maps a (row, column) displacement on a grid to the displacement of the same cells on the transformed grid.
+  fn map_direction(self, (dr, dc): (i8, i8)) -> (i8, i8) {
+    match self {
+      Symmetry::Identity => (dr, dc),
+      Symmetry::Rotate90 => (dc, -dr),
+      Symmetry::Rotate180 => (-dr, -dc),
+      Symmetry::Rotate270 => (-dc, dr),
+      Symmetry::ReflectVertical => (dr, -dc),
+      Symmetry::ReflectHorizontal => (-dr, dc),
+      Symmetry::ReflectDiagonal => (dc, dr),
+      Symmetry::ReflectAntiDiagonal => (-dc, -dr),
+    }
+  }
+
+}
+
+impl<T: GridState + Ord> Grid<T>
+where Grid<T>: Symmetric {
+
+  /// Returns the symmetric image of the grid with the smallest state, together with the symmetry producing it.
+  /// All the symmetric grids share the same canonical grid; ties keep the first symmetry in `Symmetry::ALL`.
+  pub fn canonical(&self) -> (Grid<T>, Symmetry) {
+    let mut best = (*self, Symmetry::Identity);
+
+    for &symmetry in Symmetry::ALL[1..].iter() {
+      let mut image = *self;
+      image.apply_symmetry(symmetry);
+
+      if image.get_state() < best.0.get_state() {
+        best = (image, symmetry);
+      }
+    }
+
+    best
+  }
+
+}
+
+
+impl Grid<DestinationsGrid> {
+
+  /// Applies the symmetry to the displacements of `player_move`, giving the displacements of `symmetry.map_move(player_move)` on the transformed grid.
+  /// The cells move as with `Symmetric::apply_symmetry()`, and the displacements change sign when the mapped move runs the other way along its axis.
+  pub fn apply_move_symmetry(&mut self, symmetry: Symmetry, player_move: PlayerMove) -> &mut Self {
+
+    // displacements are along the rows for vertical moves, along the columns for horizontal ones
+    let axis = match player_move {
+      PlayerMove::Up | PlayerMove::Down => (1, 0),
+      PlayerMove::Left | PlayerMove::Right => (0, 1),
+    };
+    let (dr, dc) = symmetry.map_direction(axis);
+    let sign = dr + dc;
+
+    move_cells(self, symmetry);
+
+    for i in 0..GRID_SIDE {
+      for dest in self[i].iter_mut() {
+        *dest *= sign;
+      }
+    }
+
+    self
+  }
+
+}
+
+
+// Symmetric
+
+impl Symmetric for Grid<EncodedGrid> {}
+impl Symmetric for Grid<BitboardGrid> {}
+
+
+//------------------------------------------------
+// Functions
+//------------------------------------------------
+
+/// Moves the cells of the grid according to the symmetry.
+fn move_cells<G: Transpose + Reverse + ?Sized>(grid: &mut G, symmetry: Symmetry) -> &mut G {
+  match symmetry {
+    Symmetry::Identity => grid,
+    Symmetry::Rotate90 => grid.transpose().reverse(),
+    Symmetry::Rotate180 => grid.transpose().reverse().transpose().reverse(),
+    Symmetry::Rotate270 => grid.reverse().transpose(),
+    Symmetry::ReflectVertical => grid.reverse(),
+    Symmetry::ReflectHorizontal => grid.transpose().reverse().transpose(),
+    Symmetry::ReflectDiagonal => grid.transpose(),
+    Symmetry::ReflectAntiDiagonal => grid.reverse().transpose().reverse(),
+  }
+}
+
+
+//------------------------------------------------
+// Unit tests
+//------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+
+  use super::*;
+  use crate::game::moves::{self, precomputed_moves};
+
+  const MOVES: [PlayerMove; 4] = [PlayerMove::Up, PlayerMove::Left, PlayerMove::Right, PlayerMove::Down];
+
+  fn test_grid() -> Grid<EncodedGrid> {
+    Grid::from_decoded(&[
+      [2, 4, 8, 16],
+      [0, 2, 0, 32],
+      [0, 0, 4, 0],
+      [2, 0, 0, 64],
+    ])
+  }
+
+  // Test Symmetric::apply_symmetry()
+
+  #[test]
+  pub fn test_rotate90() {
+    let mut grid = test_grid();
+
+    grid.apply_symmetry(Symmetry::Rotate90);
+
+    assert_eq!(grid, Grid::from_decoded(&[
+      [2, 0, 0, 2],
+      [0, 0, 2, 4],
+      [0, 4, 0, 8],
+      [64, 0, 32, 16],
+    ]));
+  }
+
+  #[test]
+  pub fn test_reflections() {
+    let mut grid = test_grid();
+    grid.apply_symmetry(Symmetry::ReflectHorizontal);
+
+    assert_eq!(grid, Grid::from_decoded(&[
+      [2, 0, 0, 64],
+      [0, 0, 4, 0],
+      [0, 2, 0, 32],
+      [2, 4, 8, 16],
+    ]));
+
+    let mut grid = test_grid();
+    grid.apply_symmetry(Symmetry::ReflectAntiDiagonal);
+
+    assert_eq!(grid, Grid::from_decoded(&[
+      [64, 0, 32, 16],
+      [0, 4, 0, 8],
+      [0, 0, 2, 4],
+      [2, 0, 0, 2],
+    ]));
+  }
+
+  #[test]
+  pub fn test_symmetries_distinct_and_inverse() {
+    let grid = test_grid();
+    let mut images = vec![];
+
+    for &symmetry in Symmetry::ALL.iter() {
+      let mut image = grid;
+      image.apply_symmetry(symmetry);
+      images.push(image);
+
+      image.apply_symmetry(symmetry.inverse());
+      assert_eq!(image, grid, "{:?}", symmetry);
+    }
+
+    for i in 0..images.len() {
+      for j in (i + 1)..images.len() {
+        assert_ne!(images[i], images[j]);
+      }
+    }
+  }
+
+  #[test]
+  pub fn test_symmetries_on_every_representation() {
+    let grid = test_grid();
+
+    for &symmetry in Symmetry::ALL.iter() {
+      let mut image = grid;
+      let mut bitboard = Grid::<BitboardGrid>::from_encoded(&grid).unwrap();
+
+      image.apply_symmetry(symmetry);
+      bitboard.apply_symmetry(symmetry);
+
+      assert_eq!(bitboard.to_encoded(), image, "{:?}", symmetry);
+    }
+  }
+
+
+  // Test Grid::<DestinationsGrid>::apply_move_symmetry()
+
+  #[test]
+  pub fn test_move_symmetry_commutes_with_stacking() {
+    let grid = test_grid();
+
+    for &symmetry in Symmetry::ALL.iter() {
+      for &player_move in MOVES.iter() {
+        let mut transformed = grid;
+        transformed.apply_symmetry(symmetry);
+
+        let mut destinations = *moves::process_grid_stacking(player_move, &grid, precomputed_moves()).get_destination_grid();
+        destinations.apply_move_symmetry(symmetry, player_move);
+
+        let mapped_move = symmetry.map_move(player_move);
+        let expected = *moves::process_grid_stacking(mapped_move, &transformed, precomputed_moves()).get_destination_grid();
+
+        assert_eq!(destinations, expected, "{:?} {:?}", symmetry, player_move);
+      }
+    }
+  }
+
+  #[test]
+  pub fn test_move_symmetry_inverse() {
+    let grid = test_grid();
+
+    // a reflection turns the leftward displacements into rightward ones, and its inverse turns them back
+    let left = *moves::process_grid_stacking(PlayerMove::Left, &grid, precomputed_moves()).get_destination_grid();
+    let mut reflected = left;
+    reflected.apply_move_symmetry(Symmetry::ReflectVertical, PlayerMove::Left);
+
+    assert!(reflected.get_state().iter().flatten().all(|&dest| dest >= 0));
+    assert!(reflected.get_state().iter().flatten().any(|&dest| dest > 0));
+
+    reflected.apply_move_symmetry(Symmetry::ReflectVertical.inverse(), PlayerMove::Right);
+    assert_eq!(reflected, left);
+
+    // a diagonal reflection also swaps the axis of the move
+    for &symmetry in Symmetry::ALL.iter() {
+      for &player_move in MOVES.iter() {
+        let mut destinations = *moves::process_grid_stacking(player_move, &grid, precomputed_moves()).get_destination_grid();
+        let original = destinations;
+
+        destinations.apply_move_symmetry(symmetry, player_move);
+        destinations.apply_move_symmetry(symmetry.inverse(), symmetry.map_move(player_move));
+
+        assert_eq!(destinations, original, "{:?} {:?}", symmetry, player_move);
+      }
+    }
+  }
+
+
+  // Test Symmetry::map_move()
+
+  #[test]
+  pub fn test_map_move_commutes_with_stacking() {
+    let grid = test_grid();
+
+    for &symmetry in Symmetry::ALL.iter() {
+      for &player_move in MOVES.iter() {
+        let mut moved_then_transformed = *moves::process_grid_stacking(player_move, &grid, precomputed_moves()).get_new_grid();
+        moved_then_transformed.apply_symmetry(symmetry);
+
+        let mut transformed = grid;
+        transformed.apply_symmetry(symmetry);
+        let transformed_then_moved = *moves::process_grid_stacking(symmetry.map_move(player_move), &transformed, precomputed_moves()).get_new_grid();
+
+        assert_eq!(moved_then_transformed, transformed_then_moved, "{:?} {:?}", symmetry, player_move);
+        assert_eq!(symmetry.inverse().map_move(symmetry.map_move(player_move)), player_move);
+      }
+    }
+  }
+
+
+  // Test Grid::canonical()
+
+  #[test]
+  pub fn test_canonical() {
+    let grid = test_grid();
+    let (canonical, symmetry) = grid.canonical();
+
+    let mut image = grid;
+    image.apply_symmetry(symmetry);
+    assert_eq!(image, canonical);
+
+    for &other in Symmetry::ALL.iter() {
+      let mut image = grid;
+      image.apply_symmetry(other);
+
+      assert!(canonical.get_state() <= image.get_state());
+      assert_eq!(image.canonical().0, canonical);
+
+      let bitboard = Grid::<BitboardGrid>::from_encoded(&image).unwrap();
+      assert_eq!(bitboard.canonical().0, Grid::<BitboardGrid>::from_encoded(&grid).unwrap().canonical().0);
+    }
+  }
+
+  #[test]
+  pub fn test_canonical_symmetric_grid() {
+    let grid = Grid::from_decoded(&[
+      [2, 0, 0, 2],
+      [0, 0, 0, 0],
+      [0, 0, 0, 0],
+      [2, 0, 0, 2],
+    ]);
+
+    assert_eq!(grid.canonical(), (grid, Symmetry::Identity));
+  }
+
+}
@@ -0,0 +1,210 @@
+//! # `zobrist` module
+//!
+//! This module defines the Zobrist hash of an encoded grid: the XOR of a random 64-bit key per cell and tile.
+//! The keys are generated at compile time from a fixed seed, so hashes are stable across runs and platforms and can be persisted.
+//! Empty cells have no key, hence spawning a tile or changing a row only XORs the keys of the affected cells.
+
+use crate::game::core::*;
+
+
+//------------------------------------------------
+// Types and Definitions
+//------------------------------------------------
+
+// Seed of the keys, changing it invalidates every persisted hash
+const ZOBRIST_SEED: u64 = 0x2048_2048_2048_2048;
+
+// Keys indexed by cell in reading order and tile exponent, exponent 0 (empty cell) has key 0
+const ZOBRIST_KEYS: [[u64; TILE_EXPONENTS]; GRID_SIDE * GRID_SIDE] = generate_keys(ZOBRIST_SEED);
+
+/// Zobrist hash of a `Grid<EncodedGrid>`, updated incrementally as tiles spawn and rows change.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ZobristHash {
+  value: u64,
+}
+
+
+//------------------------------------------------
+// Implementations
+//------------------------------------------------
+
+// Inherent
+
+impl ZobristHash {
+
+  /// Constructor hashing the whole grid.
+  pub fn new(grid: &Grid<EncodedGrid>) -> Self {
+    let mut hash = ZobristHash::default();
+
+    for i in 0..GRID_SIDE {
+      hash.value ^= row_key(i, grid[i]);
+    }
+
+    hash
+  }
+
+  // Getters
+  pub fn get_value(&self) -> u64 { self.value }
+
+  /// Adds or removes `tile` in row `row` and column `col`, e.g. to account for a spawned tile.
+  pub fn toggle_tile(&mut self, row: usize, col: usize, tile: EntryType) -> &mut Self {
+    if tile > 1 {
+      self.value ^= ZOBRIST_KEYS[GRID_SIDE * row + col][tile.trailing_zeros() as usize];
+    }
+
+    self
+  }
+
+  /// Replaces the encoded row `row` from `old_line` to `new_line`, e.g. after a move.
+  pub fn update_row(&mut self, row: usize, old_line: EncodedEntryType, new_line: EncodedEntryType) -> &mut Self {
+    if old_line != new_line {
+      self.value ^= row_key(row, old_line) ^ row_key(row, new_line);
+    }
+
+    self
+  }
+
+  /// Replaces the grid `old_grid` with `new_grid`, updating only the rows that changed.
+  pub fn update_grid(&mut self, old_grid: &Grid<EncodedGrid>, new_grid: &Grid<EncodedGrid>) -> &mut Self {
+    for i in 0..GRID_SIDE {
+      self.update_row(i, old_grid[i], new_grid[i]);
+    }
+
+    self
+  }
+
+}
+
+impl Grid<EncodedGrid> {
+
+  /// Returns the Zobrist hash of the grid, see `ZobristHash`.
+  pub fn zobrist_hash(&self) -> ZobristHash {
+    ZobristHash::new(self)
+  }
+
+}
+
+
+//------------------------------------------------
+// Functions
+//------------------------------------------------
+
+/// XOR of the keys of the tiles of an encoded row.
+fn row_key(row: usize, line: EncodedEntryType) -> u64 {
+  let mut key = 0;
+
+  for j in 0..GRID_SIDE {
+    key ^= ZOBRIST_KEYS[GRID_SIDE * row + j][((line >> (ENCODING_BITS * j)) & ENCODING_TILE_MASK) as usize];
+  }
+
+  key
+}
+
+/// Step of the SplitMix64 generator, returns the new state and the next number.
+const fn splitmix64(state: u64) -> (u64, u64) {
+  let state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+  let mut z = state;
+  z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+  z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+
+  (state, z ^ (z >> 31))
+}
+
+/// Generates the keys of every cell and non-zero exponent from the seed.
+const fn generate_keys(seed: u64) -> [[u64; TILE_EXPONENTS]; GRID_SIDE * GRID_SIDE] {
+  let mut keys = [[0; TILE_EXPONENTS]; GRID_SIDE * GRID_SIDE];
+  let mut state = seed;
+  let mut cell = 0;
+
+  while cell < GRID_SIDE * GRID_SIDE {
+    let mut exponent = 1;
+
+    while exponent < TILE_EXPONENTS {
+      let (next_state, key) = splitmix64(state);
+      state = next_state;
+      keys[cell][exponent] = key;
+      exponent += 1;
+    }
+
+    cell += 1;
+  }
+
+  keys
+}
+
+
+//------------------------------------------------
+// Unit tests
+//------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+
+  use super::*;
+  use crate::game::moves::{self, precomputed_moves, PlayerMove};
+
+  fn test_grid() -> Grid<EncodedGrid> {
+    Grid::from_decoded(&[
+      [2, 4, 0, 8],
+      [0, 0, 2, 0],
+      [0, 0, 0, 0],
+      [1024, 0, 0, 4],
+    ])
+  }
+
+  // Test ZobristHash::new()
+
+  #[test]
+  pub fn test_zobrist_stable() {
+    assert_eq!(ZobristHash::new(&Grid::new(&[0; GRID_SIDE])).get_value(), 0);
+    assert_eq!(ZOBRIST_KEYS[0][1], 0x3BAD_7115_1CA2_EB61);
+
+    // pinned value, a change here breaks every persisted hash
+    assert_eq!(test_grid().zobrist_hash().get_value(), 0xED3C_BC25_21A6_E579);
+  }
+
+  #[test]
+  pub fn test_zobrist_distinguishes_grids() {
+    let mut other = test_grid();
+    other.set_tile(2, 2, 2);
+
+    assert_ne!(test_grid().zobrist_hash(), other.zobrist_hash());
+
+    let mut transposed = test_grid();
+    transposed.transpose();
+
+    assert_ne!(test_grid().zobrist_hash(), transposed.zobrist_hash());
+  }
+
+
+  // Test incremental updates
+
+  #[test]
+  pub fn test_zobrist_toggle_tile() {
+    let mut grid = test_grid();
+    let mut hash = grid.zobrist_hash();
+
+    grid.set_tile(2, 1, 4);
+    hash.toggle_tile(2, 1, 4);
+
+    assert_eq!(hash, grid.zobrist_hash());
+
+    hash.toggle_tile(2, 1, 4);
+    assert_eq!(hash, test_grid().zobrist_hash());
+  }
+
+  #[test]
+  pub fn test_zobrist_update_after_moves() {
+    let grid = test_grid();
+
+    for &player_move in [PlayerMove::Up, PlayerMove::Left, PlayerMove::Right, PlayerMove::Down].iter() {
+      let move_result = moves::process_grid_stacking(player_move, &grid, precomputed_moves());
+      let mut hash = grid.zobrist_hash();
+
+      hash.update_grid(&grid, move_result.get_new_grid());
+      assert_eq!(hash, move_result.get_new_grid().zobrist_hash(), "{:?}", player_move);
+    }
+  }
+
+}
@@ -2,12 +2,14 @@
 //! 
 //! This library containse the set of functionalities of the 2048 game and AI to compile to WebAssembly.
 
+pub mod error;
 mod encoding;
 pub mod game;
 pub mod ai;
 
 // re-exported 
 pub use crate::game::core::EncodedEntryType;
+pub use crate::error::GridError;
 
 /*
 The public API should expose: